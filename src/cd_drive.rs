@@ -0,0 +1,186 @@
+// reads the status & the table of contents (TOC) of a CD drive using the Linux CD-ROM ioctls
+// the request numbers, status codes & structs must match those in the kernel's include/uapi/linux/cdrom.h
+use nix::sys::ioctl::ioctl_num_type;
+use std::os::fd::AsRawFd;
+
+/// Get tray position, etc.; the status is the return value of the ioctl
+const CDROM_DRIVE_STATUS: ioctl_num_type = 0x5326;
+/// Get disc type, etc.; the status is the return value of the ioctl
+const CDROM_DISC_STATUS: ioctl_num_type = 0x5327;
+/// Read TOC header (struct cdrom_tochdr)
+const CDROMREADTOCHDR: ioctl_num_type = 0x5305;
+/// Read TOC entry (struct cdrom_tocentry)
+const CDROMREADTOCENTRY: ioctl_num_type = 0x5306;
+
+/// we have to specify a format when reading a TOC entry, even though we do not use the address
+const CDROM_LBA: u8 = 0x01;
+/// the bit in the control field of a TOC entry that is set for data tracks
+const CDROM_DATA_TRACK: u8 = 0x04;
+
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The values returned by the CDROM_DRIVE_STATUS ioctl
+pub enum DriveStatus {
+    NoInfo = 0,        // CDS_NO_INFO
+    NoDisc = 1,        // CDS_NO_DISC
+    TrayOpen = 2,      // CDS_TRAY_OPEN
+    DriveNotReady = 3, // CDS_DRIVE_NOT_READY
+    DiscOk = 4,        // CDS_DISC_OK
+}
+
+impl DriveStatus {
+    /// Returns the status with the raw value returned by the ioctl, or None if the value is not a drive status
+    fn from_raw(raw_status: i32) -> Option<Self> {
+        match raw_status {
+            0 => Some(DriveStatus::NoInfo),
+            1 => Some(DriveStatus::NoDisc),
+            2 => Some(DriveStatus::TrayOpen),
+            3 => Some(DriveStatus::DriveNotReady),
+            4 => Some(DriveStatus::DiscOk),
+            _ => None,
+        }
+    }
+}
+
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The values returned by the CDROM_DISC_STATUS ioctl; the first 4 are the same as the drive status
+pub enum DiscStatus {
+    NoInfo = 0,        // CDS_NO_INFO
+    NoDisc = 1,        // CDS_NO_DISC
+    TrayOpen = 2,      // CDS_TRAY_OPEN
+    DriveNotReady = 3, // CDS_DRIVE_NOT_READY
+    Audio = 100,       // CDS_AUDIO; the normal case
+    Data1 = 101,       // CDS_DATA_1
+    Data2 = 102,       // CDS_DATA_2
+    Xa21 = 103,        // CDS_XA_2_1
+    Xa22 = 104,        // CDS_XA_2_2
+    Mixed = 105,       // CDS_MIXED; has both audio & data tracks
+}
+
+impl DiscStatus {
+    /// Returns the status with the raw value returned by the ioctl, or None if the value is not a disc status
+    fn from_raw(raw_status: i32) -> Option<Self> {
+        match raw_status {
+            0 => Some(DiscStatus::NoInfo),
+            1 => Some(DiscStatus::NoDisc),
+            2 => Some(DiscStatus::TrayOpen),
+            3 => Some(DiscStatus::DriveNotReady),
+            100 => Some(DiscStatus::Audio),
+            101 => Some(DiscStatus::Data1),
+            102 => Some(DiscStatus::Data2),
+            103 => Some(DiscStatus::Xa21),
+            104 => Some(DiscStatus::Xa22),
+            105 => Some(DiscStatus::Mixed),
+            _ => None,
+        }
+    }
+}
+
+/// The layout of the kernel's struct cdrom_tochdr, as used by the CDROMREADTOCHDR ioctl
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CdTocHeader {
+    /// cdth_trk0, the number of the first track
+    pub first_cd_track: u8,
+    /// cdth_trk1, the number of the last track
+    pub last_cd_track: u8,
+}
+
+/// The layout of the kernel's struct cdrom_tocentry, as used by the CDROMREADTOCENTRY ioctl
+#[repr(C)]
+#[derive(Debug, Default)]
+struct CdTocEntry {
+    /// cdte_track
+    track: u8,
+    /// the bit fields cdte_adr & cdte_ctrl; the address type is in the bottom 4 bits & the control bits are in the top 4 bits
+    adr_ctrl: u8,
+    /// cdte_format, either CDROM_LBA or CDROM_MSF; specifies how the kernel returns the address
+    format: u8,
+    /// cdte_addr, a union of either the logical block address or the minute, second, frame; we do not use it
+    address: i32,
+    /// cdte_datamode
+    data_mode: u8,
+}
+
+/// Returns the status of the drive, or the raw value if the ioctl failed (-1) or returned an unknown status
+pub fn drive_status(device: &std::fs::File) -> Result<DriveStatus, i32> {
+    let raw_status = unsafe { libc::ioctl(device.as_raw_fd(), CDROM_DRIVE_STATUS) };
+    DriveStatus::from_raw(raw_status).ok_or(raw_status)
+}
+
+/// Returns the type of disc in the drive, or the raw value if the ioctl failed (-1) or returned an unknown status
+pub fn disc_status(device: &std::fs::File) -> Result<DiscStatus, i32> {
+    let raw_status = unsafe { libc::ioctl(device.as_raw_fd(), CDROM_DISC_STATUS) };
+    DiscStatus::from_raw(raw_status).ok_or(raw_status)
+}
+
+/// Returns the numbers of the first & last tracks on the CD, or the value returned by the ioctl if it failed
+pub fn read_toc_header(device: &std::fs::File) -> Result<CdTocHeader, i32> {
+    let mut toc_header = CdTocHeader::default();
+    let result = unsafe { libc::ioctl(device.as_raw_fd(), CDROMREADTOCHDR, &mut toc_header) };
+    match result {
+        0 => Ok(toc_header), // 0 is the Ok result
+        _ => Err(result),
+    }
+}
+
+/// Uses the CDROMREADTOCENTRY ioctl to find out if the specified track is an audio track.
+/// Returns false if it is a data track, as found on mixed mode CDs, or the value returned by the ioctl if it failed.
+pub fn track_is_audio(device: &std::fs::File, track_number: u8) -> Result<bool, i32> {
+    let mut toc_entry = CdTocEntry {
+        track: track_number,
+        format: CDROM_LBA,
+        ..Default::default()
+    };
+    let result = unsafe { libc::ioctl(device.as_raw_fd(), CDROMREADTOCENTRY, &mut toc_entry) };
+    if result != 0 {
+        return Err(result);
+    }
+    let control = toc_entry.adr_ctrl >> 4; // the control bits are in the top 4 bits
+    Ok(control & CDROM_DATA_TRACK == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn structs_match_the_kernel_layout() {
+        // struct cdrom_tochdr is 2 bytes
+        assert_eq!(std::mem::size_of::<CdTocHeader>(), 2);
+        assert_eq!(std::mem::offset_of!(CdTocHeader, last_cd_track), 1);
+
+        // in struct cdrom_tocentry, the union cdrom_addr is aligned to 4 bytes, as it contains an int
+        assert_eq!(std::mem::offset_of!(CdTocEntry, format), 2);
+        assert_eq!(std::mem::offset_of!(CdTocEntry, address), 4);
+        assert_eq!(std::mem::offset_of!(CdTocEntry, data_mode), 8);
+        assert_eq!(std::mem::size_of::<CdTocEntry>(), 12);
+    }
+
+    #[test]
+    fn statuses_convert_from_the_raw_values() {
+        for status in [
+            DriveStatus::NoInfo,
+            DriveStatus::NoDisc,
+            DriveStatus::TrayOpen,
+            DriveStatus::DriveNotReady,
+            DriveStatus::DiscOk,
+        ] {
+            assert_eq!(DriveStatus::from_raw(status as i32), Some(status));
+        }
+        assert_eq!(DriveStatus::from_raw(-1), None);
+        assert_eq!(DiscStatus::from_raw(100), Some(DiscStatus::Audio));
+        assert_eq!(DiscStatus::from_raw(105), Some(DiscStatus::Mixed));
+        assert_eq!(DiscStatus::from_raw(4), None); // CDS_DISC_OK is only a drive status
+    }
+
+    #[test]
+    fn drive_status_can_be_read_if_there_is_a_drive() {
+        let Ok(device) = std::fs::File::open("/dev/sr0") else {
+            return; // there is no CD drive, so there is nothing to test
+        };
+        // a drive that exists must be able to report its status, even if it has no disc
+        assert!(drive_status(&device).is_ok(), "{:?}", drive_status(&device));
+    }
+}
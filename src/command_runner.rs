@@ -0,0 +1,128 @@
+// runs the external programs, such as nmcli & ping, that rradio relies on
+use std::process::{Child, Output, Stdio};
+
+/// Runs external programs. Functions that need to run a program are given a CommandRunner,
+/// so that tests can use MockCommandRunner to check the arguments without spawning any processes.
+/// The errors are returned as strings that name the program, so callers do not need to.
+pub trait CommandRunner {
+    /// Runs the program with the given arguments, waits for it to finish & returns its output
+    fn output(&self, program: &str, args: &[&str]) -> Result<Output, String>;
+
+    /// Starts the program with the given arguments & returns at once, with its stdout piped so it can be read later
+    fn spawn(&self, program: &str, args: &[&str]) -> Result<Child, String>;
+
+    /// Runs the program without blocking the runtime, killing it if it has not finished within timeout
+    fn output_with_timeout(
+        &self,
+        program: &str,
+        args: &[&str],
+        timeout: std::time::Duration,
+    ) -> impl Future<Output = Result<Output, String>>;
+}
+
+#[derive(Debug)]
+/// The CommandRunner that actually runs the programs
+pub struct SystemCommandRunner;
+
+impl CommandRunner for SystemCommandRunner {
+    fn output(&self, program: &str, args: &[&str]) -> Result<Output, String> {
+        std::process::Command::new(program)
+            .args(args)
+            .output()
+            .map_err(|error| format!("Could not run {program}; got {error}"))
+    }
+
+    fn spawn(&self, program: &str, args: &[&str]) -> Result<Child, String> {
+        std::process::Command::new(program)
+            .args(args)
+            .stdout(Stdio::piped()) // needed as we need to capture what is sent back
+            .spawn()
+            .map_err(|error| format!("Could not start {program}; got {error}"))
+    }
+
+    async fn output_with_timeout(
+        &self,
+        program: &str,
+        args: &[&str],
+        timeout: std::time::Duration,
+    ) -> Result<Output, String> {
+        // we use tokio's Command so that we do not block the runtime while the program runs,
+        // & kill_on_drop so that the program is killed if the timeout expires
+        match tokio::time::timeout(
+            timeout,
+            tokio::process::Command::new(program)
+                .args(args)
+                .kill_on_drop(true)
+                .output(),
+        )
+        .await
+        {
+            Ok(output_as_result) => {
+                output_as_result.map_err(|error| format!("Could not run {program}; got {error}"))
+            }
+            Err(_elapsed) => Err(format!("{program} did not finish within {timeout:?}")),
+        }
+    }
+}
+
+#[cfg(test)]
+#[derive(Debug)]
+/// A CommandRunner for tests; it records the commands it is asked to run, without running them,
+/// & gives stdout as the output of every command
+pub struct MockCommandRunner {
+    pub stdout: String,
+    /// each command run, as the program followed by its arguments
+    pub commands_run: std::cell::RefCell<Vec<Vec<String>>>,
+}
+
+#[cfg(test)]
+impl MockCommandRunner {
+    pub fn new(stdout: &str) -> Self {
+        MockCommandRunner {
+            stdout: stdout.to_string(),
+            commands_run: std::cell::RefCell::new(Vec::new()),
+        }
+    }
+
+    fn record(&self, program: &str, args: &[&str]) {
+        self.commands_run.borrow_mut().push(
+            std::iter::once(program)
+                .chain(args.iter().copied())
+                .map(str::to_string)
+                .collect(),
+        );
+    }
+
+    fn successful_output(&self) -> Output {
+        Output {
+            status: std::os::unix::process::ExitStatusExt::from_raw(0),
+            stdout: self.stdout.clone().into_bytes(),
+            stderr: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl CommandRunner for MockCommandRunner {
+    fn output(&self, program: &str, args: &[&str]) -> Result<Output, String> {
+        self.record(program, args);
+        Ok(self.successful_output())
+    }
+
+    fn spawn(&self, program: &str, args: &[&str]) -> Result<Child, String> {
+        self.record(program, args);
+        Err(format!(
+            "{program} was not started as MockCommandRunner does not start processes"
+        ))
+    }
+
+    async fn output_with_timeout(
+        &self,
+        program: &str,
+        args: &[&str],
+        _timeout: std::time::Duration,
+    ) -> Result<Output, String> {
+        self.record(program, args);
+        Ok(self.successful_output())
+    }
+}
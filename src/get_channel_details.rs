@@ -6,22 +6,92 @@ use crate::read_config::AuralNotifications;
 use crate::read_config::{self, MediaDetails};
 use crate::{
     gstreamer_interfaces::PlaybinElement,
-    player_status::{PlayerStatus, START_UP_DING_CHANNEL_NUMBER},
+    player_status::{NUMBER_OF_POSSIBLE_CHANNELS, PlayerStatus, START_UP_DING_CHANNEL_NUMBER},
 };
 
+use crate::cd_drive;
+use crate::command_runner::CommandRunner;
 use crate::lcd;
 use crate::mount_media::{self};
 use gstreamer::ClockTime;
-use std::{fs, os::fd::AsRawFd};
+use std::fs;
 use substring::Substring;
 
 pub const OS_ERROR_NO_SUCH_FILE_OR_DIRECTORY: i32 = 2;
 
+/// The error given if a channel file specifies both what to play & a device to play from, as we cannot tell which the user wants
+const STATION_URL_AND_MEDIA_DETAILS_CONFLICT: &str = "station_url & media_details cannot both be specified; use station_url for streams, or media_details for a CD or USB device";
+
 fn station_url_default() -> Vec<String> {
     Vec::new()
 }
 
-#[derive(Debug, PartialEq, Clone, serde::Deserialize)]
+/// Reads the icon, rejecting any that is not one of the user defined characters, so that the channel file cannot be parsed
+fn deserialize_icon<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<usize>, D::Error> {
+    let icon = <Option<usize> as serde::Deserialize>::deserialize(deserializer)?;
+    if let Some(icon) = icon
+        && icon >= crate::lcd::character_pattern::BITMAPS.len()
+    {
+        return Err(serde::de::Error::custom(format!(
+            "icon is {icon}, but it must be in the range 0 to {}",
+            crate::lcd::character_pattern::BITMAPS.len() - 1
+        )));
+    }
+    Ok(icon)
+}
+
+/// The lowest & highest gains, in dB, that gstreamer's equalizers accept
+const EQUALIZER_GAIN_RANGE_DB: std::ops::RangeInclusive<f64> = -24.0..=12.0;
+
+#[derive(Debug, PartialEq, Clone, serde::Deserialize, serde::Serialize)]
+/// The gains, in dB, of the equalizer used while a channel plays, specified in the channel file as eg
+/// [eq]
+///     gains_db = [-6.0, 0.0, 0.0]
+/// 3 gains give equalizer-3bands (low, mid & high); 10 gains give equalizer-10bands (29 Hz to 15 kHz).
+/// Each gain must be in the range -24 to +12 dB.
+pub struct Equalizer {
+    #[serde(deserialize_with = "deserialize_equalizer_gains")]
+    pub gains_db: Vec<f64>,
+}
+
+impl Equalizer {
+    /// The name of the gstreamer element with as many bands as there are gains
+    pub fn element_name(&self) -> &'static str {
+        if self.gains_db.len() == 3 {
+            "equalizer-3bands"
+        } else {
+            "equalizer-10bands"
+        }
+    }
+}
+
+/// Reads the equalizer's gains, rejecting any list that neither equalizer accepts, so that the channel file cannot be parsed
+fn deserialize_equalizer_gains<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<f64>, D::Error> {
+    let gains_db = <Vec<f64> as serde::Deserialize>::deserialize(deserializer)?;
+    if gains_db.len() != 3 && gains_db.len() != 10 {
+        return Err(serde::de::Error::custom(format!(
+            "gains_db has {} gains, but it must have 3 or 10",
+            gains_db.len()
+        )));
+    }
+    if let Some(gain) = gains_db
+        .iter()
+        .find(|gain| !EQUALIZER_GAIN_RANGE_DB.contains(gain))
+    {
+        return Err(serde::de::Error::custom(format!(
+            "the gain {gain} dB is not in the range {} to {} dB",
+            EQUALIZER_GAIN_RANGE_DB.start(),
+            EQUALIZER_GAIN_RANGE_DB.end()
+        )));
+    }
+    Ok(gains_db)
+}
+
+#[derive(Debug, PartialEq, Clone, serde::Deserialize, serde::Serialize)]
 /// enum of the possible media types
 pub enum SourceType {
     /// will be unknown if the channel cannot be found.
@@ -31,6 +101,8 @@ pub enum SourceType {
     Cd,
     /// we will play random tracks on this local or remote USB device
     Usb,
+    /// the URLs to play were read from an M3U or PLS playlist file
+    Playlist,
 }
 impl std::fmt::Display for SourceType {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -38,20 +110,20 @@ impl std::fmt::Display for SourceType {
             SourceType::Cd => write!(f, "CD"),
             SourceType::Usb => write!(f, "USB"),
             SourceType::UrlList => write!(f, "URL"),
+            SourceType::Playlist => write!(f, "Playlist"),
             Self::UnknownSource => write!(f, "Source type is unknown; programming error"),
         }
     }
 }
 
-pub const LIST_OF_SUPPORTED_FILE_TYPES: &[&str] = &["mp3", "wav", "ogg", "flac", "m4a"];
-
-fn is_supported_file_type(path: &std::path::Path) -> bool {
+/// Returns true if the path's extension is one of audio_extensions, which are expected to be lower case (as config.audio_extensions is)
+fn is_supported_file_type(path: &std::path::Path, audio_extensions: &[String]) -> bool {
     path.extension()
         .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
-        .is_some_and(|extension| LIST_OF_SUPPORTED_FILE_TYPES.contains(&extension.as_str()))
+        .is_some_and(|extension| audio_extensions.contains(&extension))
 }
 
-#[derive(Debug, PartialEq, Clone, serde::Deserialize)]
+#[derive(Debug, PartialEq, Clone, serde::Deserialize, serde::Serialize)]
 /// Decoded data sucessfully read from the station channel file, ie organisaton, source_type,
 /// if the last track is a ding, pause_before_playing_ms, media_details & station_urls as a Vec,
 pub struct ChannelFileDataDecoded {
@@ -77,6 +149,42 @@ pub struct ChannelFileDataDecoded {
     pub data_is_initialised: bool,
 
     pub media_details: Option<read_config::MediaDetails>,
+
+    /// If specified, the volume used while this channel is playing eg  volume = 85
+    /// The user's volume is restored when they change to a channel that does not specify a volume.
+    pub volume: Option<i32>,
+
+    /// If specified, the M3U or PLS playlist file whose entries are played    eg  playlist_file = "/home/pi/radio.m3u"
+    /// The entries are added after any specified by station_url
+    pub playlist_file: Option<String>,
+
+    /// If specified, the address pinged instead of the one derived from the first station URL  eg  ping_address = "1.1.1.1"
+    /// Useful for stations behind CDNs that do not respond to pings.
+    pub ping_address: Option<String>,
+
+    /// If true, the audio is downmixed to mono before it is played    eg  force_mono = true
+    /// Useful for talk stations whose poor encoders only put sound on one channel.
+    /// The extra conversion costs a few percent of the CPU on a Pi Zero, so only specify it if needed.
+    #[serde(default = "is_false")]
+    pub force_mono: bool,
+
+    /// If specified, the user defined character (0 to 7) shown at the start of line 2 while the channel plays   eg  icon = 5
+    /// Gives a visual cue, eg to tell news stations from music stations; config.custom_chars can be used to define the characters.
+    #[serde(default, deserialize_with = "deserialize_icon")]
+    pub icon: Option<usize>,
+
+    /// If specified, the equalizer used while the channel plays, eg to tame a bass-heavy station; see Equalizer for the format.
+    /// The equalizer adds to the CPU load, more so for 10 bands than for 3;
+    /// config.equalizer_enabled = false turns off the equalizers of every channel.
+    pub eq: Option<Equalizer>,
+
+    /// If specified, the username & password sent to streams that need HTTP basic authentication, eg
+    /// [http_authentication]
+    ///     username = "listener"
+    ///     password = "secret"
+    /// The password is never output, so it does not appear in logs, status dumps or on the LCD screen.
+    pub http_authentication: Option<read_config::AuthenticationData>,
+
     /// What to play       eg       station_url = "https://dc1.serverse.com/proxy/wiupfvnu?mp=/TradCan\"
     #[serde(default = "station_url_default")]
     /// What to play    eg  station_url = "https://dc1.serverse.com/proxy/wiupfvnu?mp=/TradCan\"
@@ -93,6 +201,25 @@ impl ChannelFileDataDecoded {
             media_details: None,
             random_tracks_wanted: false,
             data_is_initialised: false,
+            volume: None,
+            playlist_file: None,
+            ping_address: None,
+            force_mono: false,
+            icon: None,
+            eq: None,
+            http_authentication: None,
+        }
+    }
+
+    /// Returns the data that play_cd read from a CD, with the settings of the CD channel's file that the CD cannot provide,
+    /// so that they are kept both when the CD is first played & when it is reloaded
+    pub fn with_cd_channel_settings_of(self, channel_file_data: &ChannelFileDataDecoded) -> Self {
+        ChannelFileDataDecoded {
+            volume: channel_file_data.volume,
+            force_mono: channel_file_data.force_mono,
+            icon: channel_file_data.icon,
+            eq: channel_file_data.eq.clone(),
+            ..self
         }
     }
 }
@@ -124,6 +251,15 @@ pub enum ChannelErrorEvents {
     /// The message returned if the user enters a channel number that does not exist
     CouldNotFindChannelFile,
 
+    /// The stations directory contains no channel files that can be read, probably as the config points at the wrong folder
+    NoStationFiles(String),
+
+    /// More than one file in the stations directory starts with the channel number, so we cannot tell which one the user wants
+    DuplicateChannel {
+        channel_number: String,
+        file_names: Vec<String>,
+    },
+
     /// When enumerating the Samba files, could not find a folder or file with the specified name
     CouldNotFindSambaShareWithFolder(Option<String>),
 
@@ -142,6 +278,12 @@ pub enum ChannelErrorEvents {
         error_message: String,
     },
 
+    /// Could not read the M3U or PLS playlist file specified in the channel file
+    CouldNotReadPlaylistFile {
+        path_to_playlist_file: String,
+        error_message: String,
+    },
+
     /// We read the channel file, but could not parse it
     CouldNotParseChannelFile {
         channel_number: usize,
@@ -154,16 +296,29 @@ pub enum ChannelErrorEvents {
     /// Could not find the album specifed in the play list, possibly because the wrong memory stick is inserted
     CouldNotFindAlbum(String),
 
+    /// The Samba server rejected the protocol version; the string is the version, or the list of versions tried
+    SambaVersionRejected { device: String, version: String },
+
     /// No USBDevice
     NoUSBDevice,
 
     /// is the problem that the SAMBA device has the wrong letter paattern associated with it eg sdb1, not sda1
     NoSuchDeviceOrDirectory(String),
 
+    /// The USB device exists, but does not contain a filesystem that can be mounted, eg it is not formatted as FAT
+    UsbFilesystemNotMountable(String),
+
     /// USB mount error other than no USB device;
     /// the string contains the reason return by the Operating System
     UsbMountMountError(String),
 
+    /// The Samba server was reached, but the share could not be mounted, eg it does not exist; the string is the share
+    SambaShareNotMountable(String),
+
+    /// Samba mount error other than the share not being mountable or the version being rejected;
+    /// the string contains the reason return by the Operating System
+    SambaMountError(String),
+
     /// Error when trying to read a USB memory stick
     USBReadReadError(String),
 
@@ -176,6 +331,12 @@ pub enum ChannelErrorEvents {
     /// could not get the number of tracks on the CD
     CouldNotGetNumberOfCDTracks(i32),
 
+    /// could not get the details of one track on the CD, so could not tell if it is an audio track or a data track
+    CouldNotGetCDTrackDetails { track_number: u8, error: i32 },
+
+    /// the CD is a mixed CD, but none of its tracks are audio tracks
+    NoAudioTracksOnCD,
+
     /// probably a bug as there should be files
     NoFilesInArray,
 }
@@ -185,9 +346,24 @@ impl ChannelErrorEvents {
     pub fn to_lcd_screen(&self) -> String {
         match &self {
             ChannelErrorEvents::CouldNotFindPlaylistCD(cd_name) => {
-                format!("Could not find {} listed in the playlist", cd_name)
+                format!(
+                    "Could not find {} listed in the playlist. Is the right memory stick in place?",
+                    cd_name
+                )
             }
             ChannelErrorEvents::CouldNotFindChannelFile => "CouldNotFindChannelFile".to_string(),
+            ChannelErrorEvents::NoStationFiles(stations_directory) => {
+                format!("No station files found in {stations_directory}")
+            }
+            ChannelErrorEvents::DuplicateChannel {
+                channel_number,
+                file_names,
+            } => {
+                format!(
+                    "Duplicate channel {channel_number} {}",
+                    file_names.join(" & ")
+                )
+            }
             ChannelErrorEvents::CouldNotFindSambaShareWithFolder(folder_name) => {
                 if let Some(error_message) = folder_name {
                     format!(
@@ -205,7 +381,7 @@ impl ChannelErrorEvents {
                 format!("{}, {}", channel_number, error_message)
             }
             ChannelErrorEvents::CouldNotFindAlbum(album_name) => {
-                format!("Could not find {album_name}")
+                format!("Could not find {album_name}. Is the right memory stick in place?")
             }
             ChannelErrorEvents::CouldNotReadChannelFile {
                 path_to_channel_file,
@@ -216,9 +392,21 @@ impl ChannelErrorEvents {
                     path_to_channel_file, error_message
                 )
             }
+            ChannelErrorEvents::CouldNotReadPlaylistFile {
+                path_to_playlist_file,
+                error_message,
+            } => {
+                format!(
+                    "Could not read playlist file {}; got error {}",
+                    path_to_playlist_file, error_message
+                )
+            }
             ChannelErrorEvents::CouldNotEnumerateSamba(error_message) => {
                 format!("Could not enumerate Samba {}", error_message)
             }
+            ChannelErrorEvents::SambaVersionRejected { device, version } => {
+                format!("Samba server {device} rejected version {version}")
+            }
             ChannelErrorEvents::NoSuchDeviceOrDirectory(bad_path) => {
                 format!("Could not find device on path{}", bad_path)
             }
@@ -235,9 +423,20 @@ impl ChannelErrorEvents {
                 format!("Error reading channel folder entry {}", error_message)
             }
             ChannelErrorEvents::NoUSBDevice => "No USB device found".to_string(),
+            ChannelErrorEvents::UsbFilesystemNotMountable(device) => {
+                format!("USB device {device} does not have a filesystem that can be mounted")
+            }
             ChannelErrorEvents::UsbMountMountError(error_message) => {
                 format!("When trying to mount a USB device got error {error_message}")
             }
+            ChannelErrorEvents::SambaShareNotMountable(device) => {
+                format!(
+                    "Samba share {device} could not be mounted; check that the server shares it"
+                )
+            }
+            ChannelErrorEvents::SambaMountError(error_message) => {
+                format!("When trying to mount a Samba share got error {error_message}")
+            }
             ChannelErrorEvents::USBReadReadError(error_message) => {
                 format!(
                     "When trying to read USB memory stick got error {}",
@@ -272,6 +471,16 @@ impl ChannelErrorEvents {
             ChannelErrorEvents::CouldNotGetNumberOfCDTracks(error) => {
                 format!("When getting number of CD tracks, got error {}", error)
             }
+            ChannelErrorEvents::CouldNotGetCDTrackDetails {
+                track_number,
+                error,
+            } => {
+                format!(
+                    "When getting the details of CD track {}, got error {}",
+                    track_number, error
+                )
+            }
+            ChannelErrorEvents::NoAudioTracksOnCD => "CD has no audio tracks".to_string(),
             ChannelErrorEvents::NoFilesInArray => {
                 "Probably hit a bug as there were no files in the array".to_string()
             }
@@ -283,17 +492,24 @@ impl ChannelErrorEvents {
 /// If successful returns the details of the channel as the struct ChannelFileData
 /// namely organisation, station_url & sets the source type to be SourceType::UrlList
 /// works on both local USB devices & remotely mounted ones,
-/// which are expected to have different mount folders
+/// which are expected to have different mount folders.
+/// If retry_missing_albums is true, an album that cannot be found is replaced by another one, rather than giving an error.
 pub fn get_channel_details_from_mountable_media(
     aural_notifications: &AuralNotifications, // taken from config.toml
+    audio_extensions: &[String],              // taken from config.toml
+    retry_missing_albums: bool,               // taken from config.toml
     channel_file_data_decoded: &mut ChannelFileDataDecoded,
+    command_runner: &impl CommandRunner,
 ) -> Result<ChannelFileDataDecoded, ChannelErrorEvents> {
-    let mount_folder =
-        mount_media::mount_memory_stick_option(&mut channel_file_data_decoded.media_details)?;
+    let mount_folder = mount_media::mount_memory_stick_option(
+        &mut channel_file_data_decoded.media_details,
+        command_runner,
+    )?;
     if channel_file_data_decoded.random_tracks_wanted {
         return set_up_playlist_random_albums(
             mount_folder,
             &aural_notifications.filename_sound_at_end_of_playlist,
+            audio_extensions,
             channel_file_data_decoded,
         );
     }
@@ -334,7 +550,10 @@ pub fn get_channel_details_from_mountable_media(
                                             )
                                         })?;
 
-                                        if is_supported_file_type(dir_entry.file_name().as_ref()) {
+                                        if is_supported_file_type(
+                                            dir_entry.file_name().as_ref(),
+                                            audio_extensions,
+                                        ) {
                                             list_of_audio_album_images.push(
                                                 album_dir_entry
                                                     .path()
@@ -381,56 +600,41 @@ pub fn get_channel_details_from_mountable_media(
         return Err(ChannelErrorEvents::NoFilesInArray);
     }
 
-    let chosen_album = list_of_audio_album_images
-        [rand::random_range(0..=(list_of_audio_album_images.len() - 1))]
-    .as_str();
+    let (chosen_album, audio_files) = open_random_album(
+        list_of_audio_album_images,
+        retry_missing_albums,
+        !channel_file_data_decoded.station_url.is_empty(),
+    )?;
     let mut list_of_wanted_tracks = vec![]; // list of the tracks that we will return
-    match fs::read_dir(chosen_album) {
-        Ok(audio_files) => {
-            for file_as_result in audio_files {
-                if let Ok(audio_or_other_type_of_file_dir_entry) = file_as_result {
-                    if let Ok(file_type) = audio_or_other_type_of_file_dir_entry.file_type()
-                        && file_type.is_file()
-                        && let Some(one_audio_file) = audio_or_other_type_of_file_dir_entry
-                            .path()
-                            .as_os_str()
-                            .to_str()
-                    {
-                        // got a file not a folder, in the audio files folder. but is it an audio file
-                        if is_supported_file_type(
-                            audio_or_other_type_of_file_dir_entry.file_name().as_ref(),
-                        ) {
-                            list_of_wanted_tracks.push(format!("file://{}", one_audio_file));
-                            // we do not use {:?} in the format string as that adds unwanted quotes
-                        }
-                    }
-                } else {
-                    return Err(ChannelErrorEvents::USBReadReadError(
-                        "Failed while geting audio file entries".to_string(),
-                    ));
-                }
-            }
-        }
-        Err(error_message) => {
-            if let Some(OS_ERROR_NO_SUCH_FILE_OR_DIRECTORY) = error_message.raw_os_error() {
-                if channel_file_data_decoded.station_url.is_empty() {
-                    return Err(ChannelErrorEvents::CouldNotFindAlbum(format!(
-                        "whilst getting audio file names, could not find album {}",
-                        chosen_album
-                    )));
-                } else {
-                    return Err(ChannelErrorEvents::CouldNotFindPlaylistCD(
-                        chosen_album.to_string(),
-                    ));
+    for file_as_result in audio_files {
+        if let Ok(audio_or_other_type_of_file_dir_entry) = file_as_result {
+            if let Ok(file_type) = audio_or_other_type_of_file_dir_entry.file_type()
+                && file_type.is_file()
+                && let Some(one_audio_file) = audio_or_other_type_of_file_dir_entry
+                    .path()
+                    .as_os_str()
+                    .to_str()
+            {
+                // got a file not a folder, in the audio files folder. but is it an audio file
+                if is_supported_file_type(
+                    audio_or_other_type_of_file_dir_entry.file_name().as_ref(),
+                    audio_extensions,
+                ) {
+                    list_of_wanted_tracks.push(format!("file://{}", one_audio_file));
+                    // we do not use {:?} in the format string as that adds unwanted quotes
                 }
             }
-
-            return Err(ChannelErrorEvents::USBReadReadError(format!(
-                "whilst getting audio file names got {:?}",
-                error_message
-            )));
+        } else {
+            return Err(ChannelErrorEvents::USBReadReadError(
+                "Failed while geting audio file entries".to_string(),
+            ));
         }
     }
+    truncate_to_max_tracks(
+        &mut list_of_wanted_tracks,
+        &channel_file_data_decoded.media_details,
+    );
+
     let last_track_is_a_ding;
     // if we get here everything has worked
     if let Some(filename_sound_at_end_of_playlist) =
@@ -455,16 +659,70 @@ pub fn get_channel_details_from_mountable_media(
         random_tracks_wanted: channel_file_data_decoded.random_tracks_wanted,
         pause_before_playing_ms: channel_file_data_decoded.pause_before_playing_ms,
         media_details: channel_file_data_decoded.media_details.clone(),
+        volume: channel_file_data_decoded.volume,
+        playlist_file: channel_file_data_decoded.playlist_file.clone(),
+        ping_address: channel_file_data_decoded.ping_address.clone(),
+        force_mono: channel_file_data_decoded.force_mono,
+        icon: channel_file_data_decoded.icon,
+        eq: channel_file_data_decoded.eq.clone(),
+        http_authentication: channel_file_data_decoded.http_authentication.clone(),
     })
 }
 
-//#[repr(C)]
-#[derive(Debug, Default)]
-struct CdToc {
-    first_cd_track: u8, // start track
-    last_cd_track: u8,  // end track
+/// Chooses one of the candidate albums at random & opens its folder, returning the album & its contents.
+/// If retry_missing_albums is true & the chosen album cannot be found, eg as one entry in the playlist is wrong,
+/// it is removed from the candidates & another is chosen, until there are none left.
+/// from_playlist specifies which error is returned if the album cannot be found; candidate_albums must not be empty.
+fn open_random_album(
+    mut candidate_albums: Vec<String>,
+    retry_missing_albums: bool,
+    from_playlist: bool,
+) -> Result<(String, fs::ReadDir), ChannelErrorEvents> {
+    loop {
+        let chosen_album =
+            candidate_albums.swap_remove(rand::random_range(0..candidate_albums.len()));
+        match fs::read_dir(&chosen_album) {
+            Ok(audio_files) => return Ok((chosen_album, audio_files)),
+            Err(error_message)
+                if error_message.raw_os_error() == Some(OS_ERROR_NO_SUCH_FILE_OR_DIRECTORY) =>
+            {
+                if retry_missing_albums && !candidate_albums.is_empty() {
+                    println!("Could not find album {chosen_album}, so choosing another\r");
+                    continue;
+                }
+                if from_playlist {
+                    return Err(ChannelErrorEvents::CouldNotFindPlaylistCD(chosen_album));
+                } else {
+                    return Err(ChannelErrorEvents::CouldNotFindAlbum(format!(
+                        "whilst getting audio file names, could not find album {}",
+                        chosen_album
+                    )));
+                }
+            }
+            Err(error_message) => {
+                return Err(ChannelErrorEvents::USBReadReadError(format!(
+                    "whilst getting audio file names got {:?}",
+                    error_message
+                )));
+            }
+        }
+    }
+}
+
+/// If media_details specifies a non-zero max_tracks, shortens the list of tracks to that length.
+/// Must be called before any ding is added, so the ding is still played at the end.
+fn truncate_to_max_tracks(tracks: &mut Vec<String>, media_details: &Option<MediaDetails>) {
+    if let Some(media_details) = media_details
+        && let Some(max_tracks) = media_details.max_tracks
+        && max_tracks > 0
+    {
+        tracks.truncate(max_tracks);
+    }
 }
 
+/// The organisation of a CD until gstreamer tells us the album name from the CD-TEXT, if the CD has any
+pub const CD_ORGANISATION_WITHOUT_CD_TEXT: &str = "CD";
+
 // If successful returns the details of the channel as the struct ChannelFileData
 /// namely organisation (=CD), station_url & sets the source type to be SourceType::CD
 pub fn play_cd(
@@ -491,48 +749,64 @@ pub fn play_cd(
     let device = std::fs::File::open(media_details.device.clone())
         .map_err(|err| ChannelErrorEvents::FailedToOpenCdDrive(err.raw_os_error()))?;
 
-    const CDROM_DRIVE_STATUS: nix::sys::ioctl::ioctl_num_type = 0x5326; /* Get tray position, etc. */
-    const CDROM_DISC_STATUS: u64 = 0x5327; /* Get disc type, etc. */
-    const CDROMREADTOCHDR: u64 = 0x5305; /* Read TOC header
-    (struct cdrom_tochdr) */
-
     // first see if the CD drive is working OK & has a disk it it
-    match unsafe { libc::ioctl(device.as_raw_fd(), CDROM_DRIVE_STATUS) } {
-        4 => {} // CDS_DISC_OK
-        //0 => return Err(ChannelErrorEvents::FailedtoGetCDdriveStatus(0)), // CDS_NO_INFO
-        //1 => return Err(ChannelErrorEvents::FailedtoGetCDdriveStatus(1)), // CDS_NO_DISC
-        //2 => return Err(ChannelErrorEvents::FailedtoGetCDdriveStatus(2)), // CDS_TRAY_OPEN
-        //3 => return Err(ChannelErrorEvents::FailedtoGetCDdriveStatus(3)), // CDS_DRIVE_NOT_READY
-        n => return Err(ChannelErrorEvents::FailedtoGetCDdriveOrDiskStatus(n)),
+    match cd_drive::drive_status(&device) {
+        Ok(cd_drive::DriveStatus::DiscOk) => {}
+        Ok(drive_status) => {
+            return Err(ChannelErrorEvents::FailedtoGetCDdriveOrDiskStatus(
+                drive_status as i32,
+            ));
+        }
+        Err(raw_status) => {
+            return Err(ChannelErrorEvents::FailedtoGetCDdriveOrDiskStatus(
+                raw_status,
+            ));
+        }
     };
 
     // & having checked that there is a disk in a CD drive, check that it contains a audio tracks
-    match unsafe { libc::ioctl(device.as_raw_fd(), CDROM_DISC_STATUS) } {
-        100 => {} // CDS_AUDIO; the normal case
-        // 0 => return Err(CdError::NoCdInfo),         // CDS_NO_INFO
-        // 1 => return Err(CdError::NoCd),             // CDS_NO_DISC
-        // 2 => return Err(CdError::CdTrayIsOpen),     // CDS_TRAY_OPEN
-        // 3 => return Err(CdError::CdTrayIsNotReady), // CDS_DRIVE_NOT_READY
-        // 101 => return Err(CdError::CdIsData1),      // CDS_DATA_1
-        // 102 => return Err(CdError::CdIsData2),      // CDS_DATA_2
-        // 103 => return Err(CdError::CdIsXA21),       // CDS_XA_2_1
-        // 104 => return Err(CdError::CdIsXA22),       // CDS_XA_2_2
-        105 => println!("Mixed CD\r"), // CDS_MIXED
-        n => return Err(ChannelErrorEvents::FailedtoGetCDdriveOrDiskStatus(n)),
-    }
-    let mut toc = CdToc::default();
-    let result = unsafe { libc::ioctl(device.as_raw_fd(), CDROMREADTOCHDR, &mut toc) };
-    match result {
-        0 => {} // 0 is the Ok result
-        _ => return Err(ChannelErrorEvents::CouldNotGetNumberOfCDTracks(result)),
-    };
+    let mut cd_is_mixed = false;
+    match cd_drive::disc_status(&device) {
+        Ok(cd_drive::DiscStatus::Audio) => {} // the normal case
+        Ok(cd_drive::DiscStatus::Mixed) => {
+            // has both audio & data tracks, so we will need to skip the data tracks
+            println!("Mixed CD\r");
+            cd_is_mixed = true;
+        }
+        Ok(disc_status) => {
+            return Err(ChannelErrorEvents::FailedtoGetCDdriveOrDiskStatus(
+                disc_status as i32,
+            ));
+        }
+        Err(raw_status) => {
+            return Err(ChannelErrorEvents::FailedtoGetCDdriveOrDiskStatus(
+                raw_status,
+            ));
+        }
+    }
+    let toc = cd_drive::read_toc_header(&device)
+        .map_err(ChannelErrorEvents::CouldNotGetNumberOfCDTracks)?;
 
     let mut station_url = Vec::new();
 
     for track_count in toc.first_cd_track..=toc.last_cd_track {
         // the = sign means use last_cd_track  & not stop just beforehand
+        if cd_is_mixed
+            && !cd_drive::track_is_audio(&device, track_count).map_err(|error| {
+                ChannelErrorEvents::CouldNotGetCDTrackDetails {
+                    track_number: track_count,
+                    error,
+                }
+            })?
+        {
+            println!("Skipping data track {track_count}\r");
+            continue; // gstreamer cannot play data tracks
+        }
         station_url.push(format!("cdda://{track_count}"));
     }
+    if station_url.is_empty() {
+        return Err(ChannelErrorEvents::NoAudioTracksOnCD);
+    }
     // if we get here everything has worked, so work out if we need to add a ding if one has been specified at the end of the list of tracks.
     let last_track_is_a_ding;
     if let Some(filename_sound_at_end_of_playlist) = filename_sound_at_end_of_playlist {
@@ -548,7 +822,7 @@ pub fn play_cd(
     }
 
     Ok(ChannelFileDataDecoded {
-        organisation: "CD".to_string(),
+        organisation: CD_ORGANISATION_WITHOUT_CD_TEXT.to_string(),
         station_url,
         source_type: SourceType::Cd,
         last_track_is_a_ding,
@@ -560,23 +834,125 @@ pub fn play_cd(
             authentication_data: None,
             version: None,
             mount_folder: media_details.mount_folder.clone(),
+            max_tracks: None,
             is_mounted: true,
         }),
         data_is_initialised: true,
+        volume: None,
+        playlist_file: None,
+        ping_address: None,
+        force_mono: false,
+        icon: None,
+        eq: None,
+        http_authentication: None,
     })
 }
 
+/// Reads an M3U or PLS playlist file & returns its entries as URLs that gstreamer can play.
+/// The format is chosen by the file extension; anything other than .pls is treated as M3U.
+fn read_playlist_file(playlist_file: &str) -> Result<Vec<String>, ChannelErrorEvents> {
+    let playlist_contents = fs::read_to_string(playlist_file).map_err(|error| {
+        ChannelErrorEvents::CouldNotReadPlaylistFile {
+            path_to_playlist_file: playlist_file.to_string(),
+            error_message: error.to_string(),
+        }
+    })?;
+    let playlist_path = std::path::Path::new(playlist_file);
+    let playlist_folder = playlist_path.parent().unwrap_or(std::path::Path::new("/"));
+
+    let entries = if playlist_path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pls"))
+    {
+        parse_pls_playlist(&playlist_contents)
+    } else {
+        parse_m3u_playlist(&playlist_contents)
+    };
+
+    let station_urls: Vec<String> = entries
+        .into_iter()
+        .map(|entry| entry_to_url(entry, playlist_folder))
+        .collect();
+    if station_urls.is_empty() {
+        return Err(ChannelErrorEvents::CouldNotReadPlaylistFile {
+            path_to_playlist_file: playlist_file.to_string(),
+            error_message: "it does not contain any entries".to_string(),
+        });
+    }
+    Ok(station_urls)
+}
+
+/// Returns the entries in an M3U playlist, skipping blank lines & lines starting with "#" such as "#EXTINF:"
+fn parse_m3u_playlist(playlist_contents: &str) -> Vec<&str> {
+    playlist_contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+}
+
+/// Returns the entries in a PLS playlist, ie the values of the "FileN=" lines, in the order given by N
+fn parse_pls_playlist(playlist_contents: &str) -> Vec<&str> {
+    let mut numbered_entries: Vec<(u32, &str)> = playlist_contents
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            let entry_number = key
+                .get(..4)
+                .filter(|prefix| prefix.eq_ignore_ascii_case("file"))
+                .and_then(|_| key[4..].parse::<u32>().ok())?;
+            Some((entry_number, value.trim()))
+        })
+        .collect();
+    numbered_entries.sort_by_key(|(entry_number, _)| *entry_number);
+    numbered_entries
+        .into_iter()
+        .map(|(_, entry)| entry)
+        .collect()
+}
+
+/// URLs such as "http://..." & "file://..." are returned unchanged.
+/// Paths are turned into "file://" URLs; relative paths are taken as relative to base_folder,
+/// which is the folder containing the playlist, or config.local_media_base for a channel file's station_url
+fn entry_to_url(entry: &str, base_folder: &std::path::Path) -> String {
+    if entry.contains("://") {
+        entry.to_string()
+    } else if entry.starts_with('/') {
+        format!("file://{entry}")
+    } else {
+        format!("file://{}", base_folder.join(entry).to_string_lossy())
+    }
+}
+
 /// Given a URL (starting with http) & optionally a port number it extracts the station address.
-/// Given an IP address, it returns the IP address unchanged.
+/// Given an IP address, with or without a port number, it returns the IP address.
+/// IPv6 addresses are returned without their square brackets, as ping does not accept them.
 pub fn get_ip_address(url: &str) -> String {
     if let Ok(url2) = url::Url::parse(url) {
-        if let Some(ip_address) = url2.host_str() {
-            ip_address.to_owned()
-        } else {
-            url.to_owned()
+        match url2.host() {
+            Some(url::Host::Ipv6(ipv6_address)) => ipv6_address.to_string(),
+            Some(_) => url2.host_str().unwrap_or(url).to_owned(),
+            None => strip_port(url).to_owned(),
         }
     } else {
-        url.to_owned()
+        strip_port(url).to_owned()
+    }
+}
+
+/// Removes the port number, if any, from an address such as "192.168.1.1:8000" or "[2001:db8::1]:8000".
+/// IPv6 addresses contain colons, so if the address starts with "[" only a port after the closing "]" is removed,
+/// as are the brackets.
+fn strip_port(address: &str) -> &str {
+    if let Some(after_opening_bracket) = address.strip_prefix('[') {
+        match after_opening_bracket.split_once(']') {
+            Some((ipv6_address, _port)) => ipv6_address,
+            None => address, // no closing bracket, so we cannot make sense of it
+        }
+    } else {
+        match address.split_once(':') {
+            Some((ipv4_address_or_host, _port)) => ipv4_address_or_host,
+            None => address,
+        }
     }
 }
 
@@ -588,7 +964,13 @@ pub fn store_channel_details_and_implement_them(
     playbin: &PlaybinElement,
     previous_channel_number: usize,
     lcd: &mut lcd::Lc,
+    command_runner: &impl CommandRunner,
 ) -> Result<(), ChannelErrorEvents> {
+    debug_assert!(
+        status_of_rradio.channel_number < NUMBER_OF_POSSIBLE_CHANNELS,
+        "channel {} is reserved, so must not be overwritten by a channel file",
+        status_of_rradio.channel_number
+    );
     if status_of_rradio.channel_number != previous_channel_number
         && status_of_rradio.position_and_duration[status_of_rradio.channel_number]
             .channel_data
@@ -603,9 +985,13 @@ pub fn store_channel_details_and_implement_them(
     status_of_rradio.position_and_duration[status_of_rradio.channel_number].position =
         ClockTime::ZERO;
 
-    if let Err(error) = unmount_if_usb(
-        &mut status_of_rradio.position_and_duration[previous_channel_number].channel_data,
-    ) {
+    // if the user wants USB devices kept mounted, the previous channel's device stays mounted,
+    // unless we are about to re-read its channel file, which would lose the record that it is mounted
+    if (!config.keep_usb_mounted || status_of_rradio.channel_number == previous_channel_number)
+        && let Err(error) = unmount_if_usb(
+            &mut status_of_rradio.position_and_duration[previous_channel_number].channel_data,
+        )
+    {
         status_of_rradio
             .all_4lines
             .update_if_changed(error.as_str());
@@ -616,26 +1002,51 @@ pub fn store_channel_details_and_implement_them(
             status_of_rradio.position_and_duration[status_of_rradio.channel_number].channel_data =
                 new_channel_file_data.clone();
             status_of_rradio.toml_error = None;
+            // there is nothing on the network worth pinging for local media, so we do not derive an address
             if !status_of_rradio.position_and_duration[status_of_rradio.channel_number]
                 .channel_data
                 .station_url
                 .is_empty()
+                && !matches!(
+                    new_channel_file_data.source_type,
+                    SourceType::Cd | SourceType::Usb
+                )
             {
                 status_of_rradio.position_and_duration[status_of_rradio.channel_number]
                     .address_to_ping =
                     get_ip_address(new_channel_file_data.station_url[0].as_str());
             }
+            if let Some(ping_address) = &new_channel_file_data.ping_address {
+                // the user's choice overrides the address derived from the URL
+                status_of_rradio.position_and_duration[status_of_rradio.channel_number]
+                    .address_to_ping = ping_address.clone();
+            }
 
             if status_of_rradio.position_and_duration[status_of_rradio.channel_number]
                 .channel_data
                 .source_type
                 == SourceType::Usb
             {
+                if config.keep_usb_mounted {
+                    crate::unmount::take_over_mount_folder(
+                        status_of_rradio,
+                        status_of_rradio.channel_number,
+                    );
+                }
+                mount_media::write_mounting_message_to_lcd(
+                    &status_of_rradio.position_and_duration[status_of_rradio.channel_number]
+                        .channel_data
+                        .media_details,
+                    lcd,
+                );
                 status_of_rradio.position_and_duration[status_of_rradio.channel_number]
                     .channel_data = get_channel_details_from_mountable_media(
                     &config.aural_notifications,
+                    &config.audio_extensions,
+                    config.retry_missing_albums,
                     &mut status_of_rradio.position_and_duration[status_of_rradio.channel_number]
                         .channel_data,
+                    command_runner,
                 )?;
             }
             Ok(())
@@ -650,23 +1061,23 @@ pub fn store_channel_details_and_implement_them(
                 } else {
                     status_of_rradio.running_status = lcd::RunningStatus::NoChannel;
                 }
-                if let Some(ding_filename) = &config.aural_notifications.filename_error {
-                    // play a ding if one has been specified
+                if let Some(ding_filename) = &config.aural_notifications.filename_error
+                    && status_of_rradio.error_ding_is_due(config, chrono::Utc::now())
+                {
+                    // play a ding if one has been specified & it has not been played too recently
                     status_of_rradio.position_and_duration[START_UP_DING_CHANNEL_NUMBER]
                         .channel_data
                         .station_url = vec![format!("file://{ding_filename}")];
                     status_of_rradio.position_and_duration[START_UP_DING_CHANNEL_NUMBER]
                         .index_to_current_track = 0;
                     let _ignore_error_if_beep_fails =
-                        playbin.play_track(status_of_rradio, config, lcd, false);
+                        playbin.play_track(status_of_rradio, config, lcd, false, command_runner);
                     status_of_rradio.position_and_duration[START_UP_DING_CHANNEL_NUMBER]
                         .index_to_current_track = 0;
                 }
             } else {
                 status_of_rradio
-                    .all_4lines
-                    .update_if_changed(get_channel_details_error.to_lcd_screen().as_str());
-                status_of_rradio.running_status = lcd::RunningStatus::LongMessageOnAll4Lines;
+                    .show_error_on_all_4lines(get_channel_details_error.to_lcd_screen().as_str());
             };
             Err(get_channel_details_error)
         }
@@ -684,22 +1095,55 @@ fn get_channel_details(
     status_of_rradio_channel_number: usize,
 ) -> Result<ChannelFileDataDecoded, ChannelErrorEvents> {
     // we need to see if there is channel file with this number
-    match std::fs::read_dir(&config.stations_directory) {
+    let channel_file_prefix = format!(
+        "{:0>width$}",
+        status_of_rradio_channel_number,
+        width = config.channel_digits
+    );
+    // the channel might be in one of config.station_sources rather than in config.stations_directory
+    let stations_directory = config.stations_directory_for_channel(status_of_rradio_channel_number);
+    let mut found_a_readable_channel_file = false; // so we can tell the user if the directory has no channel files at all
+    match std::fs::read_dir(stations_directory) {
         Ok(directory_entries_in_playlist_folder) => {
             for directory_entry_in_playlist_folder_as_result in directory_entries_in_playlist_folder
             {
                 match directory_entry_in_playlist_folder_as_result {
                     Ok(directory_entry_in_playlist_folder) => {
                         // As OK, enumerate all the files in the folder
+                        if !found_a_readable_channel_file
+                            && is_channel_file_name(
+                                &directory_entry_in_playlist_folder
+                                    .file_name()
+                                    .to_string_lossy(),
+                                config.channel_digits,
+                            )
+                            && fs::File::open(directory_entry_in_playlist_folder.path()).is_ok()
+                        {
+                            found_a_readable_channel_file = true;
+                        }
 
-                        if directory_entry_in_playlist_folder
+                        let file_name = directory_entry_in_playlist_folder
                             .file_name()
                             .to_string_lossy()
-                            .starts_with(
-                                format!("{:0>2}", status_of_rradio_channel_number).as_str(),
-                            )
+                            .to_string();
+                        if file_name.starts_with(channel_file_prefix.as_str())
+                            && is_channel_file_name(&file_name, config.channel_digits)
                         {
-                            // if we get here, it matched & thus we have got the channel file the user wanted
+                            // if we get here, it matched & thus we have got the channel file the user wanted,
+                            // unless another file also matches, in which case which one we found depends on the directory order
+                            let matching_file_names = names_of_files_starting_with(
+                                stations_directory,
+                                &channel_file_prefix,
+                            );
+                            if matching_file_names.len() > 1 {
+                                eprintln!(
+                                    "Channel {channel_file_prefix} is in more than one file: {matching_file_names:?}\r"
+                                );
+                                return Err(ChannelErrorEvents::DuplicateChannel {
+                                    channel_number: channel_file_prefix,
+                                    file_names: matching_file_names,
+                                });
+                            }
                             let channel_file_info =
                                 std::fs::read_to_string(directory_entry_in_playlist_folder.path())
                                     .map_err(|error_string| {
@@ -718,6 +1162,27 @@ fn get_channel_details(
                             // next work out the type of media
                             match toml_result.clone() {
                                 Ok(mut channel_file_data_decoded) => {
+                                    if channel_file_data_decoded.media_details.is_some()
+                                        && !channel_file_data_decoded.station_url.is_empty()
+                                    {
+                                        // otherwise the URLs would be silently ignored
+                                        return Err(ChannelErrorEvents::CouldNotParseChannelFile {
+                                            channel_number: status_of_rradio_channel_number,
+                                            error_message: STATION_URL_AND_MEDIA_DETAILS_CONFLICT
+                                                .to_string(),
+                                        });
+                                    }
+                                    if let Some(icon) = channel_file_data_decoded.icon
+                                        && config.wifi_bars_character == Some(icon)
+                                    {
+                                        // the Wi-Fi bars redefine that character, so the icon would not be seen
+                                        return Err(ChannelErrorEvents::CouldNotParseChannelFile {
+                                            channel_number: status_of_rradio_channel_number,
+                                            error_message: format!(
+                                                "icon is {icon}, but that character shows the Wi-Fi bars"
+                                            ),
+                                        });
+                                    }
                                     if let Some(ref media_details) =
                                         channel_file_data_decoded.media_details
                                     {
@@ -735,9 +1200,38 @@ fn get_channel_details(
                                                 &config
                                                     .aural_notifications
                                                     .filename_sound_at_end_of_playlist,
-                                            );
+                                            )
+                                            .map(|cd_channel_data| {
+                                                cd_channel_data.with_cd_channel_settings_of(
+                                                    &channel_file_data_decoded,
+                                                )
+                                            });
                                         }
+                                    } else if let Some(playlist_file) =
+                                        &channel_file_data_decoded.playlist_file
+                                    {
+                                        let mut playlist_entries =
+                                            read_playlist_file(playlist_file)?;
+                                        channel_file_data_decoded
+                                            .station_url
+                                            .append(&mut playlist_entries);
+                                        channel_file_data_decoded.source_type =
+                                            SourceType::Playlist;
                                     } else {
+                                        if let Some(local_media_base) = &config.local_media_base {
+                                            // so the user can write eg station_url = ["sounds/jingle.mp3"]
+                                            channel_file_data_decoded.station_url =
+                                                channel_file_data_decoded
+                                                    .station_url
+                                                    .iter()
+                                                    .map(|entry| {
+                                                        entry_to_url(
+                                                            entry,
+                                                            std::path::Path::new(local_media_base),
+                                                        )
+                                                    })
+                                                    .collect();
+                                        }
                                         channel_file_data_decoded.source_type = SourceType::UrlList;
                                     }
 
@@ -777,7 +1271,106 @@ fn get_channel_details(
         }
     }
 
-    Err(ChannelErrorEvents::CouldNotFindChannelFile)
+    if found_a_readable_channel_file {
+        Err(ChannelErrorEvents::CouldNotFindChannelFile)
+    } else {
+        Err(ChannelErrorEvents::NoStationFiles(
+            stations_directory.to_string(),
+        ))
+    }
+}
+
+/// Returns the names, in alphabetical order, of the channel files in the stations directory that start with the channel file prefix,
+/// so we can tell if more than one file claims the same channel. Entries that cannot be read, & files that are not channel files
+/// (eg a backup such as "02-news.toml~"), are ignored.
+fn names_of_files_starting_with(
+    stations_directory: &str,
+    channel_file_prefix: &str,
+) -> Vec<String> {
+    let mut file_names: Vec<String> = fs::read_dir(stations_directory)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|directory_entry| directory_entry.file_name().to_string_lossy().to_string())
+        .filter(|file_name| {
+            file_name.starts_with(channel_file_prefix)
+                && is_channel_file_name(file_name, channel_file_prefix.len())
+        })
+        .collect();
+    file_names.sort();
+    file_names
+}
+
+/// Returns true if the file name is that of a channel file, ie it starts with the channel number,
+/// which has channel_digits digits, & ends with ".toml"
+fn is_channel_file_name(file_name: &str, channel_digits: usize) -> bool {
+    let channel_number = file_name.substring(0, channel_digits);
+    file_name.to_lowercase().ends_with(".toml")
+        && channel_number.len() == channel_digits
+        && channel_number
+            .chars()
+            .all(|character| character.is_ascii_digit())
+}
+
+/// Reads stations_directory & the directory of each of config.station_sources, & returns the channel number, which has
+/// config.channel_digits digits, & the organisation of every channel file found, sorted into channel order.
+/// A channel file is only listed from the directory its channel is looked for in, so a file hidden by a station source is not listed.
+/// Channel files that cannot be read or parsed are listed with a short reason instead of the organisation.
+pub fn list_stations(
+    config: &read_config::Config,
+) -> Result<Vec<(String, String)>, ChannelErrorEvents> {
+    let mut stations = Vec::new();
+    for stations_directory in config.all_stations_directories() {
+        stations.extend(
+            list_stations_in_directory(stations_directory, config.channel_digits)?
+                .into_iter()
+                .filter(|(channel_number, _organisation)| {
+                    config.channel_is_in_directory(channel_number, stations_directory)
+                }),
+        );
+    }
+    stations.sort();
+    Ok(stations)
+}
+
+/// Reads the stations directory & returns the channel number, which has channel_digits digits, & the organisation of every channel file found
+fn list_stations_in_directory(
+    stations_directory: &str,
+    channel_digits: usize,
+) -> Result<Vec<(String, String)>, ChannelErrorEvents> {
+    let directory_entries_in_playlist_folder =
+        fs::read_dir(stations_directory).map_err(|error| {
+            ChannelErrorEvents::CouldNotReadChannelsFolder {
+                channels_folder: stations_directory.to_string(),
+                error_message: error.to_string(),
+            }
+        })?;
+
+    let mut stations = Vec::new();
+    for directory_entry_as_result in directory_entries_in_playlist_folder {
+        let directory_entry = directory_entry_as_result.map_err(|error| {
+            ChannelErrorEvents::ErrorReadingFolderEntry {
+                error_message: error.to_string(),
+            }
+        })?;
+        let file_name = directory_entry.file_name().to_string_lossy().to_string();
+        if !is_channel_file_name(&file_name, channel_digits) {
+            continue; // not a channel file
+        }
+        let channel_number = file_name.substring(0, channel_digits);
+
+        let organisation = match fs::read_to_string(directory_entry.path()) {
+            Ok(channel_file_info) => {
+                match toml::from_str::<ChannelFileDataDecoded>(channel_file_info.trim_ascii_end()) {
+                    Ok(channel_file_data_decoded) => channel_file_data_decoded.organisation,
+                    Err(_error) => "cannot parse channel file".to_string(),
+                }
+            }
+            Err(_error) => "cannot read channel file".to_string(),
+        };
+        stations.push((channel_number.to_string(), organisation));
+    }
+    Ok(stations)
 }
 
 /// As the albums are not specified, sets up playlist based on a random choice of tracks from all the albums found
@@ -785,6 +1378,7 @@ fn get_channel_details(
 fn set_up_playlist_random_albums(
     mount_folder: String,
     filename_sound_at_end_of_playlist_as_option: &Option<String>,
+    audio_extensions: &[String],
     channel_data_for_wanted_channel: &mut ChannelFileDataDecoded,
 ) -> Result<ChannelFileDataDecoded, ChannelErrorEvents> {
     let mut track_list = Vec::new();
@@ -828,6 +1422,7 @@ fn set_up_playlist_random_albums(
                                                                             && track_file_type.is_file()
                                                                             && is_supported_file_type(
                                                                                 &track_as_dir_entry.path(),
+                                                                                audio_extensions,
                                                                             )
                                                                         {
                                                                             // we have found a supported audio track
@@ -901,6 +1496,10 @@ fn set_up_playlist_random_albums(
     use rand::seq::SliceRandom;
     let mut rng = rand::rng();
     track_list.shuffle(&mut rng);
+    truncate_to_max_tracks(
+        &mut track_list,
+        &channel_data_for_wanted_channel.media_details,
+    );
 
     let last_track_is_a_ding;
     // if we get here everything has worked
@@ -926,5 +1525,329 @@ fn set_up_playlist_random_albums(
         pause_before_playing_ms: channel_data_for_wanted_channel.pause_before_playing_ms,
         station_url: track_list,
         data_is_initialised: false,
+        volume: channel_data_for_wanted_channel.volume,
+        playlist_file: channel_data_for_wanted_channel.playlist_file.clone(),
+        ping_address: channel_data_for_wanted_channel.ping_address.clone(),
+        force_mono: channel_data_for_wanted_channel.force_mono,
+        icon: channel_data_for_wanted_channel.icon,
+        eq: channel_data_for_wanted_channel.eq.clone(),
+        http_authentication: channel_data_for_wanted_channel.http_authentication.clone(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn m3u_playlist_skips_comments() {
+        let playlist = "#EXTM3U\n#EXTINF:-1,Radio\nhttp://example.com/stream\n\n/music/track.mp3\n";
+        assert_eq!(
+            parse_m3u_playlist(playlist),
+            vec!["http://example.com/stream", "/music/track.mp3"]
+        );
+    }
+
+    #[test]
+    fn pls_playlist_reads_file_entries_in_order() {
+        let playlist = "[playlist]\nFile2=http://example.com/second\nTitle2=Second\nFile1=http://example.com/first\nNumberOfEntries=2\nVersion=2\n";
+        assert_eq!(
+            parse_pls_playlist(playlist),
+            vec!["http://example.com/first", "http://example.com/second"]
+        );
+    }
+
+    #[test]
+    fn entries_become_urls() {
+        let playlist_folder = std::path::Path::new("/home/pi/playlists");
+        assert_eq!(
+            entry_to_url("http://example.com/stream", playlist_folder),
+            "http://example.com/stream"
+        );
+        assert_eq!(
+            entry_to_url("file:///music/a.mp3", playlist_folder),
+            "file:///music/a.mp3"
+        );
+        assert_eq!(
+            entry_to_url("/music/a.mp3", playlist_folder),
+            "file:///music/a.mp3"
+        );
+        assert_eq!(
+            entry_to_url("album/a.mp3", playlist_folder),
+            "file:///home/pi/playlists/album/a.mp3"
+        );
+    }
+
+    #[test]
+    fn icon_must_be_a_user_defined_character() {
+        let channel_file_data: ChannelFileDataDecoded =
+            toml::from_str("organisation = \"News\"\nicon = 7").expect("valid channel file");
+        assert_eq!(channel_file_data.icon, Some(7));
+        assert!(toml::from_str::<ChannelFileDataDecoded>("icon = 8").is_err());
+        assert_eq!(
+            toml::from_str::<ChannelFileDataDecoded>("")
+                .expect("valid channel file")
+                .icon,
+            None
+        );
+    }
+
+    #[test]
+    fn equalizer_must_have_3_or_10_gains_in_range() {
+        let channel_file_data: ChannelFileDataDecoded =
+            toml::from_str("[eq]\ngains_db = [-6.0, 0.0, 2.5]").expect("valid channel file");
+        let equalizer = channel_file_data.eq.expect("eq was specified");
+        assert_eq!(equalizer.gains_db, vec![-6.0, 0.0, 2.5]);
+        assert_eq!(equalizer.element_name(), "equalizer-3bands");
+        let equalizer: ChannelFileDataDecoded =
+            toml::from_str("[eq]\ngains_db = [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -3.0]")
+                .expect("valid channel file");
+        assert_eq!(
+            equalizer.eq.expect("eq was specified").element_name(),
+            "equalizer-10bands"
+        );
+        assert!(toml::from_str::<ChannelFileDataDecoded>("[eq]\ngains_db = [0.0, 0.0]").is_err());
+        assert!(
+            toml::from_str::<ChannelFileDataDecoded>("[eq]\ngains_db = [-30.0, 0.0, 0.0]").is_err()
+        );
+    }
+
+    #[test]
+    fn a_reloaded_cd_channel_keeps_the_settings_of_its_channel_file() {
+        let channel_file_data: ChannelFileDataDecoded = toml::from_str(
+            "volume = 70\nforce_mono = true\nicon = 3\n[eq]\ngains_db = [-6.0, 0.0, 2.5]",
+        )
+        .expect("valid channel file");
+        // what play_cd returns knows nothing of the channel file, both when the CD is first played & when it is reloaded
+        let first_played =
+            ChannelFileDataDecoded::new().with_cd_channel_settings_of(&channel_file_data);
+        let reloaded = ChannelFileDataDecoded::new().with_cd_channel_settings_of(&first_played);
+        assert_eq!(
+            reloaded.eq.expect("eq was kept").gains_db,
+            vec![-6.0, 0.0, 2.5]
+        );
+        assert_eq!(reloaded.icon, Some(3));
+        assert_eq!(reloaded.volume, Some(70));
+        assert!(reloaded.force_mono);
+    }
+
+    /// A folder in the temporary directory for the files a test needs, eg channel files;
+    /// it is removed when dropped, so it is removed even if the test fails
+    struct TestFolder(std::path::PathBuf);
+
+    impl TestFolder {
+        /// Creates an empty folder whose name is unique to the test & to this process
+        fn new(test_name: &str) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("rradio_{test_name}_test_{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path); // in case an earlier run with the same process id left it behind
+            fs::create_dir_all(&path).expect("can create the test folder");
+            TestFolder(path)
+        }
+
+        /// Writes a file, eg a channel file, into the folder
+        fn write(&self, file_name: &str, contents: &str) {
+            fs::write(self.0.join(file_name), contents).expect("can write the test file");
+        }
+
+        /// The path of the folder as a string
+        fn path(&self) -> String {
+            self.0.to_string_lossy().to_string()
+        }
+
+        /// The path of something in the folder as a string
+        fn path_of(&self, name: &str) -> String {
+            self.0.join(name).to_string_lossy().to_string()
+        }
+
+        /// A config whose stations directory is this folder
+        fn config(&self) -> read_config::Config {
+            read_config::Config {
+                stations_directory: self.path(),
+                ..Default::default()
+            }
+        }
+    }
+
+    impl Drop for TestFolder {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn duplicate_channel_files_are_reported() {
+        let stations_folder = TestFolder::new("duplicate");
+        for file_name in [
+            "05-news.toml",
+            "05-music.toml",
+            "06-other.toml",
+            "06-other.toml~",
+        ] {
+            stations_folder.write(file_name, "station_url = [\"http://example.com/stream\"]");
+        }
+        let config = stations_folder.config();
+
+        let duplicate_result = get_channel_details(&config, 5);
+        let single_result = get_channel_details(&config, 6);
+
+        match duplicate_result {
+            Err(ChannelErrorEvents::DuplicateChannel {
+                channel_number,
+                file_names,
+            }) => {
+                assert_eq!(channel_number, "05");
+                assert_eq!(file_names, vec!["05-music.toml", "05-news.toml"]);
+            }
+            other => panic!("expected a duplicate channel error, got {other:?}"),
+        }
+        assert!(single_result.is_ok(), "{single_result:?}");
+    }
+
+    #[test]
+    fn missing_albums_are_replaced_only_if_retry_is_wanted() {
+        let albums_folder = TestFolder::new("missing_album");
+        let present_album = albums_folder.path_of("present");
+        fs::create_dir_all(&present_album).expect("can create the test album");
+        let missing_album = albums_folder.path_of("missing");
+
+        // whichever album is chosen first, the present one is found in the end
+        let retried_result = open_random_album(
+            vec![missing_album.clone(), present_album.clone()],
+            true,
+            false,
+        )
+        .map(|(chosen_album, _audio_files)| chosen_album);
+        let all_missing_result = open_random_album(vec![missing_album.clone()], true, true);
+        let not_retried_result = open_random_album(vec![missing_album.clone()], false, false);
+
+        assert_eq!(retried_result.ok(), Some(present_album));
+        assert!(matches!(
+            all_missing_result,
+            Err(ChannelErrorEvents::CouldNotFindPlaylistCD(album)) if album == missing_album
+        ));
+        assert!(matches!(
+            not_retried_result,
+            Err(ChannelErrorEvents::CouldNotFindAlbum(_))
+        ));
+    }
+
+    #[test]
+    fn station_url_and_media_details_together_are_rejected() {
+        let stations_folder = TestFolder::new("conflict");
+        stations_folder.write(
+            "07-conflict.toml",
+            r#"
+            station_url = ["http://example.com/stream"]
+            [media_details]
+            device = "/dev/sda"
+            mount_folder = "/tmp/usb"
+            "#,
+        );
+
+        let result = get_channel_details(&stations_folder.config(), 7);
+
+        match result {
+            Err(ChannelErrorEvents::CouldNotParseChannelFile {
+                channel_number,
+                error_message,
+            }) => {
+                assert_eq!(channel_number, 7);
+                assert_eq!(error_message, STATION_URL_AND_MEDIA_DETAILS_CONFLICT);
+            }
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn stations_are_listed_from_every_stations_directory() {
+        let stations_folder = TestFolder::new("list_main");
+        let music_folder = TestFolder::new("list_music");
+        stations_folder.write("05-news.toml", "organisation = \"News\"");
+        stations_folder.write("30-hidden.toml", "organisation = \"Hidden\""); // channel 30 is in music_folder
+        music_folder.write("30-jazz.toml", "organisation = \"Jazz\"");
+        let config = read_config::Config {
+            station_sources: vec![read_config::StationSource {
+                first_channel: 30,
+                last_channel: 39,
+                directory: music_folder.path(),
+            }],
+            ..stations_folder.config()
+        };
+
+        assert_eq!(
+            list_stations(&config).expect("the folders can be read"),
+            vec![
+                ("05".to_string(), "News".to_string()),
+                ("30".to_string(), "Jazz".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn station_urls_are_relative_to_local_media_base() {
+        let stations_folder = TestFolder::new("media_base");
+        stations_folder.write(
+            "08-jingles.toml",
+            r#"station_url = ["sounds/jingle.mp3", "/music/a.mp3", "http://example.com/stream"]"#,
+        );
+        let config = read_config::Config {
+            local_media_base: Some("/home/pi/media".to_string()),
+            ..stations_folder.config()
+        };
+
+        let result = get_channel_details(&config, 8);
+
+        assert_eq!(
+            result.expect("the channel file is valid").station_url,
+            vec![
+                "file:///home/pi/media/sounds/jingle.mp3",
+                "file:///music/a.mp3",
+                "http://example.com/stream"
+            ]
+        );
+    }
+
+    #[test]
+    fn channel_file_names_start_with_two_digits() {
+        assert!(is_channel_file_name("05 Radio 4.toml", 2));
+        assert!(is_channel_file_name("99.TOML", 2));
+        assert!(!is_channel_file_name("5 Radio 4.toml", 2));
+        assert!(!is_channel_file_name("05 Radio 4.txt", 2));
+        assert!(!is_channel_file_name("README.md", 2));
+    }
+
+    #[test]
+    fn channel_file_names_start_with_channel_digits_digits() {
+        assert!(is_channel_file_name("105 Radio 4.toml", 3));
+        assert!(!is_channel_file_name("05 Radio 4.toml", 3));
+        assert!(is_channel_file_name("5 Radio 4.toml", 1));
+    }
+
+    #[test]
+    fn ipv4_with_port() {
+        assert_eq!(
+            get_ip_address("http://192.168.1.1:8000/stream"),
+            "192.168.1.1"
+        );
+        assert_eq!(strip_port("192.168.1.1:8000"), "192.168.1.1");
+    }
+
+    #[test]
+    fn ipv4_without_port() {
+        assert_eq!(get_ip_address("http://192.168.1.1/stream"), "192.168.1.1");
+        assert_eq!(get_ip_address("192.168.1.1"), "192.168.1.1");
+    }
+
+    #[test]
+    fn bracketed_ipv6_with_port() {
+        assert_eq!(get_ip_address("http://[2001:db8::1]:8000/"), "2001:db8::1");
+        assert_eq!(strip_port("[2001:db8::1]:8000"), "2001:db8::1");
+    }
+
+    #[test]
+    fn bracketed_ipv6_without_port() {
+        assert_eq!(get_ip_address("http://[2001:db8::1]/"), "2001:db8::1");
+        assert_eq!(strip_port("[2001:db8::1]"), "2001:db8::1");
+    }
+}
@@ -11,12 +11,13 @@ pub fn get_config_file_path(default_toml_path: &String) -> Result<String, String
                     "the format is -c followed by the file name, but could not find the file name.",
                 )?;
             }
+            "--no-takeover" => {} // handled when the LCD screen is opened
             "-V" | "-v" | "--version" => {
                 println!("{} v{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
                 //return Ok("got -V".to_string());
             }
             _ => {
-                let error_message = format!("unhandled argument  {arg:?}. Valid arguments are -c then the config file name or -V");
+                let error_message = format!("unhandled argument  {arg:?}. Valid arguments are -c then the config file name, --no-takeover or -V");
 
                 return Err(error_message);
             }
@@ -1,28 +1,65 @@
+use crate::command_runner::CommandRunner;
 use crate::player_status::PlayerStatus;
 use std::fs;
 
-#[derive(Debug)]
-/// if is_valid is true, contains the SSID, local & gateway IP addresses as strings.
+/// The time to wait between attempts to get the IP address while NetworkManager is still connecting
+const CONNECTING_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// The number of times we try to get the IP address after NetworkManager has reconnected the Wi-Fi,
+/// CONNECTING_RETRY_INTERVAL apart, as the address can take a little longer to be given
+const ADDRESS_ATTEMPTS_AFTER_RECONNECTING: u32 = 10;
+
+/// How often the main loop checks whether the Wi-Fi has finished connecting; nmcli is too slow to run every tick
+pub const NETWORK_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// The longest that iwgetid or nmcli may take when run from the main loop, so a hung program cannot stop the radio responding
+const NETWORK_COMMAND_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+#[derive(Debug, PartialEq, Clone, Copy, serde::Serialize)]
+/// How far the Pi has got with connecting to the Wi-Fi
+pub enum NetworkState {
+    /// there is no SSID configured, or NetworkManager is not trying to connect, so waiting will not help
+    NotConfigured,
+    /// NetworkManager is connecting, but we do not yet have the IP addresses
+    Connecting,
+    /// the SSID, local & gateway IP addresses are all known
+    Connected,
+}
+
+#[derive(Debug, serde::Serialize)]
+/// if state is NetworkState::Connected, contains the SSID, local & gateway IP addresses as strings.
 pub struct NetworkDataNew {
     pub ssid: String,
     pub local_ip_address: String, // these are only ever used as a string, so it is simpler to keep them as a string
     pub gateway_ip_address: String,
-    pub is_valid: bool,
+    pub state: NetworkState,
 }
 
 /// Tries once to get the IP address of the Pi's Wi-Fi interface, the IP address of the gateway & the SSID.
 /// It might fail the first few times it is called, so might need to be called multiple times.
 /// The function assumes the Pi's language is English
-pub fn try_once_to_get_wifi_network_data() -> Result<NetworkDataNew, String> {
-    let ssid = String::from_utf8_lossy(
-        &std::process::Command::new("/usr/sbin/iwgetid")
-            .arg("--raw")
-            .output()
-            .map_err(|error| error.to_string())?
-            .stdout,
-    )
-    .trim_end()
-    .to_owned();
+pub fn try_once_to_get_wifi_network_data(
+    command_runner: &impl CommandRunner,
+) -> Result<NetworkDataNew, String> {
+    let iwgetid_output = command_runner.output("/usr/sbin/iwgetid", &["--raw"])?;
+    network_data_given_ssid(&iwgetid_output.stdout)
+}
+
+/// As try_once_to_get_wifi_network_data, but without blocking the runtime & giving up if iwgetid takes too long
+async fn try_once_to_get_wifi_network_data_with_timeout(
+    command_runner: &impl CommandRunner,
+) -> Result<NetworkDataNew, String> {
+    let iwgetid_output = command_runner
+        .output_with_timeout("/usr/sbin/iwgetid", &["--raw"], NETWORK_COMMAND_TIMEOUT)
+        .await?;
+    network_data_given_ssid(&iwgetid_output.stdout)
+}
+
+/// Given the output of "iwgetid --raw", which is the SSID, gets the local & gateway IP addresses
+fn network_data_given_ssid(iwgetid_stdout: &[u8]) -> Result<NetworkDataNew, String> {
+    let ssid = String::from_utf8_lossy(iwgetid_stdout)
+        .trim_end()
+        .to_owned();
 
     let local_ip_address = if let Ok(local_ip_address_found) = local_ip_address::local_ip() {
         local_ip_address_found.to_string()
@@ -37,19 +74,73 @@ pub fn try_once_to_get_wifi_network_data() -> Result<NetworkDataNew, String> {
             ssid,
             local_ip_address: local_ip_address.to_string(),
             gateway_ip_address: gateway_addresses[0].addr().to_string(),
-            is_valid: true,
+            state: NetworkState::Connected,
         })
     } else {
         Err("Failed to get gateway address".to_string())
     }
 }
 
+/// Asks NetworkManager how far the Wi-Fi device has got with connecting.
+/// If nmcli cannot be run, it is treated as NotConfigured, as waiting will not help.
+pub fn get_wifi_connection_state(command_runner: &impl CommandRunner) -> NetworkState {
+    wifi_connection_state_given_output(command_runner.output("/bin/nmcli", &NMCLI_WIFI_STATE_ARGS))
+}
+
+/// As get_wifi_connection_state, but without blocking the runtime & treating nmcli taking too long as NotConfigured
+async fn get_wifi_connection_state_with_timeout(
+    command_runner: &impl CommandRunner,
+) -> NetworkState {
+    wifi_connection_state_given_output(
+        command_runner
+            .output_with_timeout(
+                "/bin/nmcli",
+                &NMCLI_WIFI_STATE_ARGS,
+                NETWORK_COMMAND_TIMEOUT,
+            )
+            .await,
+    )
+}
+
+/// The nmcli arguments that list the type & state of each network device
+const NMCLI_WIFI_STATE_ARGS: [&str; 4] = ["--terse", "--fields", "TYPE,STATE", "device"];
+
+/// Given the result of running nmcli with NMCLI_WIFI_STATE_ARGS, returns the state of the Wi-Fi device
+fn wifi_connection_state_given_output(
+    nmcli_output: Result<std::process::Output, String>,
+) -> NetworkState {
+    match nmcli_output {
+        Ok(output) => wifi_connection_state_from_nmcli(&String::from_utf8_lossy(&output.stdout)),
+        Err(error) => {
+            eprintln!("When trying to get the Wi-Fi state got error {error}\r");
+            NetworkState::NotConfigured
+        }
+    }
+}
+
+/// Given the output of "nmcli --terse --fields TYPE,STATE device", returns the state of the first Wi-Fi device.
+/// Each line looks like "wifi:connected" or "wifi:connecting (getting IP configuration)".
+/// If there is no Wi-Fi device, or it is disconnected or unavailable, returns NotConfigured.
+fn wifi_connection_state_from_nmcli(nmcli_output: &str) -> NetworkState {
+    match nmcli_output
+        .lines()
+        .find_map(|line| line.strip_prefix("wifi:"))
+    {
+        Some(state) if state.starts_with("connected") => NetworkState::Connected,
+        Some(state) if state.starts_with("connecting") => NetworkState::Connecting,
+        _ => NetworkState::NotConfigured,
+    }
+}
+
 impl PlayerStatus {
     /// Tries multiple times to get the IP address of the Pi's Wi-Fi interface, the IP address of the gateway & the SSID.
+    /// Gives up at once if no Wi-Fi network is configured, but waits while NetworkManager is connecting;
+    /// if it is still connecting after the last attempt, network_data.state is left as NetworkState::Connecting.
     pub fn update_network_data(
         &mut self,
         lcd: &mut crate::lcd::Lc,
         config: &crate::read_config::Config,
+        command_runner: &impl CommandRunner,
     ) {
         self.running_status = crate::lcd::RunningStatus::LongMessageOnAll4Lines;
         for count in 0..40 {
@@ -57,33 +148,243 @@ impl PlayerStatus {
             self.all_4lines.update_if_changed(
                 format!("Looking for IP address. Attempt number {count}").as_str(),
             );
-            lcd.write_rradio_status_to_lcd(self, config);
+            if let Err(lcd_error) = lcd.write_rradio_status_to_lcd(self, config, command_runner) {
+                eprintln!("{lcd_error}\r");
+            }
 
-            match try_once_to_get_wifi_network_data() {
+            match try_once_to_get_wifi_network_data(command_runner) {
                 Ok(network_data) => {
                     self.network_data = network_data;
                     self.running_status = crate::RunningStatus::Startingup;
                     self.all_4lines.update_if_changed("");
                     return;
                 }
-                Err(error) => self
-                    .all_4lines
-                    .update_if_changed(format!("Got error {error}  on count {count}").as_str()),
+                Err(error) => {
+                    // NetworkManager thinks it is connected, but we cannot get the addresses yet, so wait as if it were connecting
+                    self.network_data.state = match get_wifi_connection_state(command_runner) {
+                        NetworkState::NotConfigured => NetworkState::NotConfigured,
+                        NetworkState::Connecting | NetworkState::Connected => {
+                            NetworkState::Connecting
+                        }
+                    };
+                    if self.network_data.state == NetworkState::NotConfigured {
+                        self.all_4lines.update_if_changed(
+                            format!("No Wi-Fi network is configured; got error {error}").as_str(),
+                        );
+                        return;
+                    }
+                    self.all_4lines.update_if_changed(
+                        format!("Connecting to Wi-Fi; got error {error}  on count {count}")
+                            .as_str(),
+                    );
+                    std::thread::sleep(CONNECTING_RETRY_INTERVAL);
+                }
+            }
+        }
+    }
+
+    /// While the Wi-Fi is connecting, tries once to get the network data, or finds out that connecting has failed.
+    /// Returns true if the state has changed, which happens when the connection completes or fails.
+    /// It is called from the main loop, so the programs it runs are given a timeout rather than being allowed to block it.
+    pub async fn check_network_while_connecting(
+        &mut self,
+        command_runner: &impl CommandRunner,
+    ) -> bool {
+        if self.network_data.state != NetworkState::Connecting {
+            return false;
+        }
+        match try_once_to_get_wifi_network_data_with_timeout(command_runner).await {
+            Ok(network_data) => self.network_data = network_data,
+            Err(_error) => {
+                if get_wifi_connection_state_with_timeout(command_runner).await
+                    == NetworkState::NotConfigured
+                {
+                    self.network_data.state = NetworkState::NotConfigured;
+                }
+            }
+        }
+        self.network_data.state != NetworkState::Connecting
+    }
+
+    /// Called when the user asks for the Wi-Fi to be reconnected, eg as the Pi has joined the network but has no IP address.
+    /// Looks for the IP address again &, if there still is not one, asks NetworkManager to reconnect the Wi-Fi device,
+    /// showing the progress on the screen. Whatever is playing carries on & the screen goes back to what it was showing.
+    pub async fn reconnect_wifi(
+        &mut self,
+        lcd: &mut crate::lcd::Lc,
+        config: &crate::read_config::Config,
+        command_runner: &impl CommandRunner,
+    ) -> Result<(), String> {
+        let running_status = self.running_status.clone();
+        let all_4lines =
+            std::mem::replace(&mut self.all_4lines, crate::lcd::ScrollData::new("", 4));
+        let result = self
+            .try_to_reconnect_wifi(lcd, config, command_runner)
+            .await;
+        self.running_status = running_status;
+        self.all_4lines = all_4lines;
+        result
+    }
+
+    /// Gets the network data again, reconnecting the Wi-Fi device using nmcli if that is not enough.
+    /// It is called from the main loop, so the programs it runs are given a timeout & it never sleeps the thread.
+    async fn try_to_reconnect_wifi(
+        &mut self,
+        lcd: &mut crate::lcd::Lc,
+        config: &crate::read_config::Config,
+        command_runner: &impl CommandRunner,
+    ) -> Result<(), String> {
+        // the Wi-Fi device's own state is checked, as the local IP address might be that of the ethernet port
+        if get_wifi_connection_state_with_timeout(command_runner).await == NetworkState::Connected
+            && let Ok(network_data) =
+                try_once_to_get_wifi_network_data_with_timeout(command_runner).await
+        {
+            self.network_data = network_data;
+            return Ok(());
+        }
+        let device_list = command_runner
+            .output_with_timeout(
+                "/bin/nmcli",
+                &["--terse", "--fields", "DEVICE,TYPE", "device"],
+                NETWORK_COMMAND_TIMEOUT,
+            )
+            .await?;
+        let device_list = String::from_utf8_lossy(&device_list.stdout);
+        let wifi_device = wifi_device_from_nmcli(&device_list)
+            .ok_or("Cannot reconnect the Wi-Fi as NetworkManager has no Wi-Fi device")?;
+        let output = run_nmcli_with_retries(
+            &["device", "connect", wifi_device],
+            config,
+            command_runner,
+            |attempt| {
+                self.running_status = crate::lcd::RunningStatus::LongMessageOnAll4Lines;
+                self.all_4lines.update_if_changed(
+                    format!(
+                        "Reconnecting Wi-Fi. Attempt {attempt} of {}",
+                        config.wifi_connect_attempts
+                    )
+                    .as_str(),
+                );
+                if let Err(lcd_error) = lcd.write_rradio_status_to_lcd(self, config, command_runner)
+                {
+                    eprintln!("{lcd_error}\r");
+                }
+            },
+        )
+        .await?;
+        if !output.status.success() {
+            return Err(format!(
+                "Could not reconnect the Wi-Fi; got {}",
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            ));
+        }
+        let mut network_data = try_once_to_get_wifi_network_data_with_timeout(command_runner).await;
+        for _attempt in 1..ADDRESS_ATTEMPTS_AFTER_RECONNECTING {
+            if network_data.is_ok() {
+                break;
             }
+            tokio::time::sleep(CONNECTING_RETRY_INTERVAL).await;
+            network_data = try_once_to_get_wifi_network_data_with_timeout(command_runner).await;
         }
+        self.network_data = network_data.map_err(|error| {
+            format!("Reconnected the Wi-Fi but could not get the IP address; got {error}")
+        })?;
+        Ok(())
     }
 }
 
+/// Given the output of "nmcli --terse --fields DEVICE,TYPE device", returns the name of the first Wi-Fi device, eg "wlan0".
+/// Each line looks like "wlan0:wifi".
+fn wifi_device_from_nmcli(nmcli_output: &str) -> Option<&str> {
+    nmcli_output
+        .lines()
+        .find_map(|line| line.strip_suffix(":wifi"))
+}
+
 // set_up_wifi_password can be tested by using
 // nmcli connection show        to find the name of the the wifi connection in use
 // nmcli connection delete name-of-the-connection
 // & then running the program
 
+/// Returns the nmcli arguments that connect to the Wi-Fi network with the given SSID & password
+fn nmcli_connect_args<'a>(ssid: &'a str, password: &'a str) -> [&'a str; 6] {
+    //  use the command "nmcli device password wifi connect the_ssid password thepassword"  (with no quotes)
+    ["device", "wifi", "connect", ssid, "password", password]
+}
+
+/// Runs nmcli with the given arguments, killing it if it has not finished within config.wifi_connect_timeout.
+/// Tries up to config.wifi_connect_attempts times, calling show_attempt with the attempt number before each one.
+/// The output of the last attempt is returned even if nmcli reported a failure, so the caller can display the reason.
+async fn run_nmcli_with_retries(
+    args: &[&str],
+    config: &crate::read_config::Config,
+    command_runner: &impl CommandRunner,
+    mut show_attempt: impl FnMut(u32),
+) -> Result<std::process::Output, String> {
+    let mut last_error =
+        "Did not try to set the Wi-Fi password as wifi_connect_attempts is zero".to_string();
+
+    for attempt in 1..=config.wifi_connect_attempts {
+        show_attempt(attempt);
+
+        match command_runner
+            .output_with_timeout("/bin/nmcli", args, config.wifi_connect_timeout)
+            .await
+        {
+            Ok(output) => {
+                if output.status.success() || attempt == config.wifi_connect_attempts {
+                    return Ok(output);
+                }
+                last_error = format!("nmcli failed with status {}", output.status);
+            }
+            Err(error) => last_error = error,
+        }
+        eprintln!("Wi-Fi connect attempt {attempt} failed: {last_error}\r");
+    }
+
+    Err(format!(
+        "Failed to connect to the Wi-Fi after {} attempts; {last_error}",
+        config.wifi_connect_attempts
+    ))
+}
+
+/// Decrypts pass.toml.enc, which must have been encrypted by age using a passphrase,
+/// eg  age --passphrase --output pass.toml.enc pass.toml
+/// The passphrase is config.wifi_pass_passphrase.
+fn decrypt_pass_file(
+    encrypted_wifi_data: &[u8],
+    passphrase: Option<&str>,
+) -> Result<String, String> {
+    let passphrase = passphrase.ok_or(
+        "Found the encrypted Wi-Fi password file pass.toml.enc, but wifi_pass_passphrase is not specified in the config file",
+    )?;
+    let decryptor = age::Decryptor::new(encrypted_wifi_data)
+        .map_err(|error| format!("pass.toml.enc is not a valid encrypted file; got {error}"))?;
+    let identity =
+        age::scrypt::Identity::new(age::secrecy::SecretString::from(passphrase.to_owned()));
+    let mut reader = decryptor
+        .decrypt(std::iter::once(&identity as &dyn age::Identity))
+        .map_err(|error| {
+            format!(
+                "Could not decrypt pass.toml.enc; got {error}. Is wifi_pass_passphrase correct?"
+            )
+        })?;
+
+    let mut wifi_data = String::new();
+    std::io::Read::read_to_string(&mut reader, &mut wifi_data)
+        .map_err(|error| format!("Could not decrypt pass.toml.enc; got {error}"))?;
+    Ok(wifi_data)
+}
+
 /// Reads from file pass.toml in the device specified in the TOML configuration file the SSID and password & stores them in the operating system.
+/// If pass.toml.enc exists it is decrypted using config.wifi_pass_passphrase & used instead of pass.toml.
 /// If the USB path is not specified in the TOML configuration file, returns an error.
 /// If successful status_of_rradio.running_status is set to RunningStatus::Startingup;
-pub fn set_up_wifi_password(
+pub async fn set_up_wifi_password(
     status_of_rradio: &mut crate::player_status::PlayerStatus,
+    lcd: &mut crate::lcd::Lc,
+    config: &crate::read_config::Config,
+    command_runner: &impl CommandRunner,
 ) -> Result<(), String> {
     let wifi_file_mount_path = format!("{}wifi_folder", status_of_rradio.startup_folder);
 
@@ -110,19 +411,33 @@ pub fn set_up_wifi_password(
     };
 
     let passfile = format!("{}//pass.toml", &wifi_file_mount_path);
-    if !std::path::Path::new(&passfile).exists() {
-        return Err(format!(
-            "Wi-Fi does not seem to be working and cannot find the Wi-Fi password file {}",
-            passfile
-        ));
-    }
+    let encrypted_passfile = format!("{passfile}.enc");
 
-    let wifi_data_as_result = std::fs::read_to_string(&passfile).map_err(|toml_file_read_error| {
-        format!(
-            "Program {} Couldn't read file {passfile}. Got {toml_file_read_error}",
-            env!("CARGO_PKG_NAME")
-        )
-    });
+    // the encrypted file is used in preference to the plain text one
+    let wifi_data_as_result = if std::path::Path::new(&encrypted_passfile).exists() {
+        std::fs::read(&encrypted_passfile)
+            .map_err(|read_error| {
+                format!(
+                    "Program {} Couldn't read file {encrypted_passfile}. Got {read_error}",
+                    env!("CARGO_PKG_NAME")
+                )
+            })
+            .and_then(|encrypted_wifi_data| {
+                decrypt_pass_file(&encrypted_wifi_data, config.wifi_pass_passphrase.as_deref())
+            })
+    } else if std::path::Path::new(&passfile).exists() {
+        std::fs::read_to_string(&passfile).map_err(|toml_file_read_error| {
+            format!(
+                "Program {} Couldn't read file {passfile}. Got {toml_file_read_error}",
+                env!("CARGO_PKG_NAME")
+            )
+        })
+    } else {
+        Err(format!(
+            "Wi-Fi does not seem to be working and cannot find the Wi-Fi password file {} or {}",
+            passfile, encrypted_passfile
+        ))
+    };
 
     // next unmount the USB stick as we have read the file before any other errors might happen
     if let Err(error_message) =
@@ -151,31 +466,44 @@ pub fn set_up_wifi_password(
                 });
             match parsed_wifi_data_as_result {
                 Ok(parsed_wifi_data) => {
-                    //  use the command "nmcli device password wifi connect the_ssid password thepassword"  (with no quotes)
-                    let args = [
-                        "device",
-                        "wifi",
-                        "connect",
+                    let args = nmcli_connect_args(
                         parsed_wifi_data.ssid.as_str(),
-                        "password",
                         parsed_wifi_data.pass.as_str(),
-                    ];
+                    );
 
                     let output2_as_result =
-                        std::process::Command::new("/bin/nmcli").args(args).output();
+                        run_nmcli_with_retries(&args, config, command_runner, |attempt| {
+                            status_of_rradio.running_status =
+                                crate::lcd::RunningStatus::LongMessageOnAll4Lines;
+                            status_of_rradio.all_4lines.update_if_changed(
+                                format!(
+                                    "Connecting to Wi-Fi. Attempt {attempt} of {}",
+                                    config.wifi_connect_attempts
+                                )
+                                .as_str(),
+                            );
+                            if let Err(lcd_error) = lcd.write_rradio_status_to_lcd(
+                                status_of_rradio,
+                                config,
+                                command_runner,
+                            ) {
+                                eprintln!("{lcd_error}\r");
+                            }
+                        })
+                        .await;
                     match output2_as_result {
                         Ok(result_as_bytes) => {
                             if !result_as_bytes.stdout.is_empty() {
                                 // the command gave an output, possibly an error output
                                 let result_of_setting_wifi_password =
-                                    unsafe { String::from_utf8_unchecked(result_as_bytes.stdout) };
+                                    String::from_utf8_lossy(&result_as_bytes.stdout);
                                 // the return string should be similar to "Device 'wlan0' successfully activated with '7c9b9098-88a2-4593-b541-5ef496f3781f'." with a trailing new line
                                 // next we need to get the IP address, assuming it worked OK
                                 if result_of_setting_wifi_password
                                     .contains("successfully activated with ")
                                 {
                                     // not only did we get an output, but the SSID & password were accepted
-                                    match try_once_to_get_wifi_network_data() {
+                                    match try_once_to_get_wifi_network_data(command_runner) {
                                         // so next get the network data
                                         Ok(network_data) => {
                                             status_of_rradio.network_data = network_data;
@@ -193,20 +521,17 @@ pub fn set_up_wifi_password(
                                 }
                             } else if !result_as_bytes.stderr.is_empty() {
                                 let stderr_output =
-                                    unsafe { String::from_utf8_unchecked(result_as_bytes.stderr) };
+                                    String::from_utf8_lossy(&result_as_bytes.stderr);
                                 Err(format!(
                                     "When trying to set the SSID & Wi-Fi password got error {}",
-                                    stderr_output
-                                        .chars()
-                                        .take(stderr_output.len() - 1)
-                                        .collect::<String>()
+                                    stderr_output.trim_end() // remove the trailing new line
                                 ))
                             } else {
                                 Err("Failed to set the SSID & password for an unknown reason"
                                     .to_string())
                             }
                         }
-                        Err(error) => Err(format!("Failed to set IP address; got error {}", error)),
+                        Err(error) => Err(error),
                     }
                 }
                 Err(error) => Err(format!(
@@ -221,3 +546,95 @@ pub fn set_up_wifi_password(
         )),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::MockCommandRunner;
+
+    #[tokio::test]
+    async fn nmcli_is_asked_to_connect_with_the_ssid_and_password() {
+        let command_runner = MockCommandRunner::new(
+            "Device 'wlan0' successfully activated with '7c9b9098-88a2-4593-b541-5ef496f3781f'.\n",
+        );
+        let mut attempts_shown = Vec::new();
+        let output = run_nmcli_with_retries(
+            &nmcli_connect_args("MyNetwork", "secret"),
+            &crate::read_config::Config::default(),
+            &command_runner,
+            |attempt| attempts_shown.push(attempt),
+        )
+        .await
+        .expect("the mock always succeeds");
+
+        assert!(output.status.success());
+        assert_eq!(attempts_shown, vec![1]); // as it worked first time
+        assert_eq!(
+            *command_runner.commands_run.borrow(),
+            vec![vec![
+                "/bin/nmcli",
+                "device",
+                "wifi",
+                "connect",
+                "MyNetwork",
+                "password",
+                "secret"
+            ]]
+        );
+    }
+
+    #[test]
+    fn wifi_state_is_read_from_nmcli() {
+        let command_runner = MockCommandRunner::new("wifi:connecting (getting IP configuration)\n");
+        assert_eq!(
+            get_wifi_connection_state(&command_runner),
+            NetworkState::Connecting
+        );
+        assert_eq!(
+            *command_runner.commands_run.borrow(),
+            vec![vec![
+                "/bin/nmcli",
+                "--terse",
+                "--fields",
+                "TYPE,STATE",
+                "device"
+            ]]
+        );
+    }
+
+    #[test]
+    fn the_wifi_device_is_found_from_nmcli() {
+        let output = "eth0:ethernet
+wlan0:wifi
+p2p-dev-wlan0:wifi-p2p
+lo:loopback
+";
+        assert_eq!(wifi_device_from_nmcli(output), Some("wlan0"));
+        assert_eq!(wifi_device_from_nmcli("eth0:ethernet\n"), None);
+    }
+
+    #[test]
+    fn wifi_state_is_taken_from_the_wifi_device() {
+        let output = "ethernet:unavailable
+wifi:connecting (getting IP configuration)
+wifi-p2p:disconnected
+loopback:connected (externally)
+";
+        assert_eq!(
+            wifi_connection_state_from_nmcli(output),
+            NetworkState::Connecting
+        );
+        assert_eq!(
+            wifi_connection_state_from_nmcli("wifi:connected\n"),
+            NetworkState::Connected
+        );
+        assert_eq!(
+            wifi_connection_state_from_nmcli("wifi:disconnected\n"),
+            NetworkState::NotConfigured
+        );
+        assert_eq!(
+            wifi_connection_state_from_nmcli("loopback:connected (externally)\n"),
+            NetworkState::NotConfigured
+        );
+    }
+}
@@ -1,5 +1,5 @@
-use crate::get_channel_details::ChannelFileDataDecoded;
-use crate::lcd::{LineNum, TextBuffer};
+use crate::get_channel_details::{ChannelFileDataDecoded, Equalizer};
+use crate::read_config::AuthenticationData;
 use crate::unmount::unmount_if_needed;
 use crate::{PlayerStatus, mount_media};
 use crate::{
@@ -10,9 +10,16 @@ use crate::{
 use glib::object::{Cast, ObjectExt};
 use gstreamer::{
     SeekFlags, glib,
-    prelude::{ElementExt, ElementExtManual},
+    prelude::{
+        DeviceExt, DeviceMonitorExt, DeviceMonitorExtManual, ElementExt, ElementExtManual,
+        GstBinExt, GstBinExtManual, PluginFeatureExt,
+    },
 };
 use gstreamer_audio::prelude::StreamVolumeExt;
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+};
 
 /// The normal maximum for gstreamer that will not overload
 pub const VOLUME_ZERO_DB: i32 = 100;
@@ -20,11 +27,42 @@ pub const VOLUME_ZERO_DB: i32 = 100;
 pub const VOLUME_MIN: i32 = 0;
 /// The maximum possible gstreamer volume
 pub const VOLUME_MAX: i32 = 120;
+/// The number of dB below the wanted volume that a fade in starts at
+const FADE_IN_START_OFFSET_DB: i32 = 30;
+/// The time between each increase in volume during a fade in
+const FADE_IN_STEP_MS: u64 = 50;
+/// The time we wait for gstreamer to have a position, which it must have before we can seek, after a CD or USB track starts
+const SEEK_READY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Debug)]
+/// A track that has been given to gstreamer, but which is paused until its channel's pause_before_playing_ms has elapsed
+pub struct DeferredPlay {
+    /// the time the track is to start playing
+    pub play_at: std::time::Instant,
+    pub channel_number: usize,
+    pub seek_wanted_if_possible: bool,
+}
+
+#[derive(Debug)]
+/// A CD or USB track that is to be seeked to its stored position as soon as gstreamer has a position
+pub struct PendingSeek {
+    /// the position in the track to seek to
+    pub seek_time: gstreamer::ClockTime,
+    pub channel_number: usize,
+    /// the time after which we stop waiting for a position & try to seek anyway
+    pub give_up_at: std::time::Instant,
+}
 
 #[derive(Debug)] // we must not enable clone, as, if we do, the previous version is closed and stops playing
 /// The interface used to connect to gstreamer
 pub struct PlaybinElement {
     pub playbin_element: gstreamer::Element,
+    /// incremented every time a fade in starts or is cancelled, so that an old fade in knows to stop
+    fade_in_generation: Arc<AtomicU64>,
+    /// true while the volume is being ramped up
+    fade_in_active: Arc<AtomicBool>,
+    /// the username & password that the source-setup callback gives to the HTTP source of the track being played, if it needs them
+    http_credentials: Arc<Mutex<Option<AuthenticationData>>>,
 }
 
 impl std::ops::Drop for PlaybinElement {
@@ -46,14 +84,51 @@ impl std::ops::Drop for PlaybinElement {
 impl PlaybinElement {
     /// Sets the volume; returns an error string if it fails
     pub fn set_volume(&mut self, volume_wanted: i32) -> Result<(), String> {
-        self.playbin_element
-            .dynamic_cast_ref::<gstreamer_audio::StreamVolume>()
-            .ok_or("Could not get the stream volume")? // return the string. no panick
-            .set_volume(
-                gstreamer_audio::StreamVolumeFormat::Db,
-                f64::from(volume_wanted - VOLUME_ZERO_DB),
-            );
-        Ok(())
+        set_stream_volume(&self.playbin_element, volume_wanted)
+    }
+
+    /// true if the volume is currently being ramped up after a track started
+    pub fn is_fading_in(&self) -> bool {
+        self.fade_in_active.load(Ordering::SeqCst)
+    }
+
+    /// Stops any fade in that is in progress, so that a manual volume change takes precedence
+    pub fn cancel_fade_in(&self) {
+        self.fade_in_generation.fetch_add(1, Ordering::SeqCst);
+        self.fade_in_active.store(false, Ordering::SeqCst);
+    }
+
+    /// Starts a task that ramps the volume up from FADE_IN_START_OFFSET_DB below target_volume to target_volume
+    /// over fade_in_ms milliseconds. The task stops early if cancel_fade_in is called or another fade in starts.
+    fn start_fade_in(&self, target_volume: i32, fade_in_ms: u64) {
+        let generation = self.fade_in_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.fade_in_active.store(true, Ordering::SeqCst);
+
+        let start_volume = (target_volume - FADE_IN_START_OFFSET_DB).max(VOLUME_MIN);
+        let number_of_steps = (fade_in_ms / FADE_IN_STEP_MS).max(1) as i64;
+        if let Err(error_message) = set_stream_volume(&self.playbin_element, start_volume) {
+            eprintln!("When starting the fade in got error {error_message}\r");
+        }
+
+        let playbin_element = self.playbin_element.clone(); // cloning the element is OK; it is PlaybinElement that must not be cloned
+        let fade_in_generation = self.fade_in_generation.clone();
+        let fade_in_active = self.fade_in_active.clone();
+        tokio::spawn(async move {
+            for step in 1..=number_of_steps {
+                tokio::time::sleep(std::time::Duration::from_millis(FADE_IN_STEP_MS)).await;
+                if fade_in_generation.load(Ordering::SeqCst) != generation {
+                    return; // the user has changed the volume or a new track has started
+                }
+                let volume = start_volume
+                    + ((i64::from(target_volume - start_volume) * step) / number_of_steps) as i32;
+                if let Err(error_message) = set_stream_volume(&playbin_element, volume) {
+                    eprintln!("When fading in got error {error_message}\r");
+                }
+            }
+            if fade_in_generation.load(Ordering::SeqCst) == generation {
+                fade_in_active.store(false, Ordering::SeqCst);
+            }
+        });
     }
 
     /// Sets up the playbin.
@@ -65,6 +140,7 @@ impl PlaybinElement {
         gstreamer::init() // returns a Result which is either OK with no data, or an error of type glib::error
         .map_err(|error_message| format!("When trying to initialize gstreamer got error {error_message:?}"))    // in this case map_err returns OK or maps it a different type of error, in the case a string
         ?; // returns early with the error as a string
+        prefer_cd_source_that_reads_cd_text();
 
         let playbin_element = gstreamer::ElementFactory::make("playbin") // ::make will panic if we have not yet called gstreamer::init
             .build()
@@ -110,12 +186,171 @@ impl PlaybinElement {
             }
         }
 
+        // playbin creates a new source element for every URI it plays; if it is an HTTP source (souphttpsrc)
+        // & the channel specifies a username & password, they are given to it so it can do basic authentication
+        let http_credentials: Arc<Mutex<Option<AuthenticationData>>> = Arc::default();
+        let http_credentials_for_source_setup = Arc::clone(&http_credentials);
+        playbin_element.connect("source-setup", false, move |values| {
+            // the values are the playbin & the source it has just created
+            if let Some(Ok(source)) = values.get(1).map(|value| value.get::<gstreamer::Element>())
+                && source.has_property("user-id")
+                && let Ok(http_credentials) = http_credentials_for_source_setup.lock()
+                && let Some(http_credentials) = http_credentials.as_ref()
+            {
+                source.set_property("user-id", &http_credentials.username);
+                source.set_property("user-pw", &http_credentials.password);
+            }
+            None
+        });
+
+        if let Some(audio_sink_description) = &config.audio_sink {
+            // if the sink cannot be created, we return the error rather than let playbin fall back to a sink that may not make any sound
+            let audio_sink =
+                match gstreamer::parse::bin_from_description(audio_sink_description, true) {
+                    Ok(audio_sink) => audio_sink,
+                    Err(error) => {
+                        return Err(format!(
+                            "Could not create the audio sink {audio_sink_description}; got {error}"
+                        ));
+                    }
+                };
+            // a sink such as "alsasink device=hw:1" only opens its device when it starts, so it is opened now,
+            // so that a missing device is reported at startup rather than as silence when a channel is played
+            if audio_sink
+                .iterate_recurse()
+                .into_iter()
+                .filter_map(Result::ok)
+                .any(|element| is_audio_sink(&element) && name_of_sink_device(&element).is_some())
+            {
+                let opened = audio_sink.set_state(gstreamer::State::Ready);
+                let _ = audio_sink.set_state(gstreamer::State::Null); // playbin starts it when it is needed
+                if let Err(error) = opened {
+                    return Err(format!(
+                        "Could not open the device of the audio sink {audio_sink_description}; got {error}"
+                    ));
+                }
+            }
+            playbin_element.set_property("audio-sink", &audio_sink);
+        }
+
+        // log the sink that is actually used, as on a misconfigured Pi gstreamer can choose one that plays silently;
+        // audio_sink_name holds the name last logged, so it is only logged again if it changes
+        let audio_sink_name: Mutex<Option<String>> = Mutex::default();
+        playbin_element
+            .dynamic_cast_ref::<gstreamer::Bin>()
+            .ok_or("The gstreamer playbin is not a bin")?
+            .connect_deep_element_added(move |_playbin, _bin, element| {
+                // autoaudiosink is itself a bin that contains the sink it chooses, so we only log the elements that are not bins;
+                // the klass of most sinks is "Sink/Audio", but that of fakeaudiosink is "Audio/Sink"
+                if !is_audio_sink(element) {
+                    return;
+                }
+                let Some(factory) = element.factory() else {
+                    return;
+                };
+                let name = factory.name().to_string();
+                if let Ok(mut audio_sink_name) = audio_sink_name.lock()
+                    && audio_sink_name.as_deref() != Some(name.as_str())
+                {
+                    if name.starts_with("fake") {
+                        eprintln!(
+                            "gstreamer is playing through {name}, so there will be no sound\r"
+                        );
+                    } else {
+                        println!("gstreamer is playing through the audio sink {name}\r");
+                    }
+                    *audio_sink_name = Some(name);
+                }
+            });
+
         let bus = playbin_element
             .bus()
             .ok_or("The gstreamer playbin's message bus is missing")?
             .stream();
 
-        Ok((PlaybinElement { playbin_element }, bus))
+        Ok((
+            PlaybinElement {
+                playbin_element,
+                fade_in_generation: Arc::default(),
+                fade_in_active: Arc::default(),
+                http_credentials,
+            },
+            bus,
+        ))
+    }
+
+    /// Builds the bin used as playbin's "audio-filter" when a channel specifies force_mono and/or an equalizer;
+    /// returns None if neither is wanted, so there is no filter to use CPU.
+    /// The audio is converted, then equalized if wanted; if force_mono is wanted, only a single channel is allowed out,
+    /// which makes audioconvert downmix to mono, so the equalizer also only has to process one channel.
+    fn make_audio_filter(
+        force_mono: bool,
+        equalizer: Option<&Equalizer>,
+    ) -> Result<Option<gstreamer::Element>, String> {
+        if !force_mono && equalizer.is_none() {
+            return Ok(None);
+        }
+        let mut elements = vec![
+            gstreamer::ElementFactory::make("audioconvert")
+                .build()
+                .map_err(|error| format!("When trying to get audioconvert got error {error:?}"))?,
+        ];
+        if let Some(equalizer) = equalizer {
+            let element_name = equalizer.element_name();
+            let equalizer_element = gstreamer::ElementFactory::make(element_name)
+                .build()
+                .map_err(|error| {
+                    format!("When trying to get {element_name} got error {error:?}")
+                })?;
+            for (band, gain_db) in equalizer.gains_db.iter().enumerate() {
+                equalizer_element.set_property(&format!("band{band}"), *gain_db);
+            }
+            elements.push(equalizer_element);
+        }
+        if force_mono {
+            elements.push(
+                gstreamer::ElementFactory::make("capsfilter")
+                    .property(
+                        "caps",
+                        gstreamer::Caps::builder("audio/x-raw")
+                            .field("channels", 1)
+                            .build(),
+                    )
+                    .build()
+                    .map_err(|error| {
+                        format!("When trying to get a capsfilter got error {error:?}")
+                    })?,
+            );
+        }
+
+        let bin = gstreamer::Bin::new();
+        bin.add_many(&elements)
+            .map_err(|error| format!("When building the audio filter got error {error:?}"))?;
+        gstreamer::Element::link_many(&elements)
+            .map_err(|error| format!("When linking the audio filter got error {error:?}"))?;
+
+        // the bin needs pads of its own, which pass the data to & from the elements inside it
+        for (element, pad_name) in [
+            (
+                elements.first().expect("there is always an audioconvert"),
+                "sink",
+            ),
+            (
+                elements.last().expect("there is always an audioconvert"),
+                "src",
+            ),
+        ] {
+            let pad = element
+                .static_pad(pad_name)
+                .ok_or_else(|| format!("The audio filter is missing its {pad_name} pad"))?;
+            let ghost_pad = gstreamer::GhostPad::with_target(&pad).map_err(|error| {
+                format!("When making the audio filter's {pad_name} pad got error {error:?}")
+            })?;
+            bin.add_pad(&ghost_pad).map_err(|error| {
+                format!("When adding the audio filter's {pad_name} pad got error {error:?}")
+            })?;
+        }
+        Ok(Some(bin.upcast()))
     }
 
     /// set the state of gstreamer to be the one specified; we use Paused, Playing or Null
@@ -126,6 +361,31 @@ impl PlaybinElement {
         self.playbin_element.set_state(new_state)
     }
 
+    /// Returns true if the audio output the device monitor reported is the one playbin plays through,
+    /// so that other outputs, such as HDMI, being added or removed are ignored.
+    /// If the sink in use does not name its device (eg it plays through "default") we cannot tell, so we assume that it is.
+    pub fn plays_through(&self, device: &gstreamer::Device) -> bool {
+        let Some(device_in_use) = self
+            .playbin_element
+            .dynamic_cast_ref::<gstreamer::Bin>()
+            .and_then(|bin| {
+                bin.iterate_recurse()
+                    .into_iter()
+                    .filter_map(Result::ok)
+                    .find(is_audio_sink)
+            })
+            .and_then(|sink| name_of_sink_device(&sink))
+            .filter(|device_in_use| device_in_use != "default")
+        else {
+            return true;
+        };
+        device
+            .create_element(None)
+            .ok()
+            .and_then(|element| name_of_sink_device(&element))
+            .is_none_or(|device_name| device_name == device_in_use)
+    }
+
     /// Plays the first track aka station specified by player_status
     /// seeks to the previous position if the media is seekable
     /// if status is channel not found, it plays a ding, if one has been specified
@@ -136,16 +396,34 @@ impl PlaybinElement {
         config: &crate::read_config::Config,
         lcd: &mut crate::lcd::Lc,
         seek_wanted_if_possible: bool,
+        command_runner: &impl crate::command_runner::CommandRunner,
     ) -> Result<(), String> {
-        if status_of_rradio.running_status != RunningStatus::Startingup
+        status_of_rradio.station_announcement_playing = false; // whatever we play next replaces any announcement
+        status_of_rradio.deferred_play = None; // & any track that is waiting to be played
+        status_of_rradio.stream_reconnect_due = None; // & any stream that is waiting to be reconnected
+        status_of_rradio.pending_seek = None; // & any seek that is waiting for gstreamer to be ready
+        status_of_rradio.paused_as_audio_output_removed = false; // & any wait for the audio output to return
+        let starting_up = status_of_rradio.running_status == RunningStatus::Startingup;
+        let current_channel_number = status_of_rradio.channel_number;
+        // all the other indexing uses either this channel or the ding channel, so is in range once this check is passed
+        let Some(current_channel) = status_of_rradio.channel_mut(current_channel_number) else {
+            return Err(format!("Invalid channel {current_channel_number}"));
+        };
+        if !starting_up {
+            mount_media::write_mounting_message_to_lcd(
+                &current_channel.channel_data.media_details,
+                lcd,
+            );
+        }
+        if !starting_up
             && let Err(error) = mount_media::mount_memory_stick_option(
-                &mut status_of_rradio.position_and_duration[status_of_rradio.channel_number]
-                    .channel_data
-                    .media_details,
+                &mut current_channel.channel_data.media_details,
+                command_runner,
             )
         {
             return Err(error.to_lcd_screen());
         }
+        let index_to_current_channels_track = current_channel.index_to_current_track;
 
         // we must stop gstreamer before we can change it
         if let Err(error) = self.set_state(gstreamer::State::Null) {
@@ -169,67 +447,59 @@ impl PlaybinElement {
             _ => {}
         }
         // next we might have to play a startup ding, but before we try, check that one has been specified
-        if (status_of_rradio.channel_number == player_status::START_UP_DING_CHANNEL_NUMBER)
-            && (status_of_rradio.position_and_duration[player_status::START_UP_DING_CHANNEL_NUMBER]
-                .channel_data
-                .station_url
-                .is_empty())
+        if status_of_rradio.channel_number == player_status::START_UP_DING_CHANNEL_NUMBER
+            && status_of_rradio
+                .channel(player_status::START_UP_DING_CHANNEL_NUMBER)
+                .is_none_or(|ding_channel| ding_channel.channel_data.station_url.is_empty())
         {
             return Ok(()); // we ought to play a ding, but one has not been specified, so return OK as we cannot
         }
-        let mut index_to_current_track = status_of_rradio.position_and_duration
-            [status_of_rradio.channel_number]
-            .index_to_current_track;
-        let channel_number = match status_of_rradio.running_status {
+        let (channel_number, index_to_current_track) = match status_of_rradio.running_status {
             RunningStatus::NoChannel
             | RunningStatus::NoChannelRepeated
             | RunningStatus::LongMessageOnAll4Lines => {
-                index_to_current_track = 0; //there is only one track
-                player_status::START_UP_DING_CHANNEL_NUMBER
+                (player_status::START_UP_DING_CHANNEL_NUMBER, 0) //there is only one track
             }
-            _ => status_of_rradio.channel_number,
+            _ => (current_channel_number, index_to_current_channels_track),
         };
+        let Some(channel) = status_of_rradio.channel(channel_number) else {
+            return Err(format!("Invalid channel {channel_number}"));
+        };
+        let channel_data = &channel.channel_data;
 
         //next check that the index is in bounds to stop panics occuring if there is a bug
-        if status_of_rradio.position_and_duration[channel_number].index_to_current_track
-            >= status_of_rradio.position_and_duration[channel_number]
-                .channel_data
-                .station_url
-                .len()
-        {
-            // as index_to_current_track is a usize, there is no need to check it it is not negative
-            eprintln!(
-                "On channel {} Index to tracks out of bounds; it is {} and the list has {} elements\r",
-                channel_number,
-                status_of_rradio.position_and_duration[channel_number].index_to_current_track,
-                status_of_rradio.position_and_duration[channel_number]
-                    .channel_data
-                    .station_url
-                    .len()
-            );
-            return Err(format!(
+        // as index_to_current_track is a usize, there is no need to check it it is not negative
+        let Some(station_url) = channel_data.station_url.get(index_to_current_track) else {
+            let error_message = format!(
                 "On channel {} Index to tracks out of bounds; it is {} and the list has {} elements",
                 channel_number,
                 index_to_current_track,
-                status_of_rradio.position_and_duration[channel_number]
-                    .channel_data
-                    .station_url
-                    .len()
-            ));
+                channel_data.station_url.len()
+            );
+            eprintln!("{error_message}\r");
+            return Err(error_message);
+        };
+        // the source-setup callback uses these credentials when gstreamer creates the source for this uri
+        if let Ok(mut http_credentials) = self.http_credentials.lock() {
+            *http_credentials = channel_data.http_authentication.clone(); // so credentials from a previous channel are not sent to this one
         }
-        self.playbin_element.set_property(
-            "uri",
-            // if "uri" does not exist, it panics, but that does not seem to be anything that can be done about it.
-            &status_of_rradio.position_and_duration[channel_number]
-                .channel_data
-                .station_url[index_to_current_track],
-        );
+        // if "uri" does not exist, it panics, but that does not seem to be anything that can be done about it.
+        self.playbin_element.set_property("uri", station_url);
 
-        if let Some(pause_before_playing_ms) = status_of_rradio.position_and_duration
-            [channel_number]
-            .channel_data
-            .pause_before_playing_ms
-        {
+        // the filter can only be changed while gstreamer is stopped, which it is at this point
+        // if no filter is wanted, it is set to None, so a filter from a previous channel is not left in place
+        let audio_filter = Self::make_audio_filter(
+            channel_data.force_mono,
+            channel_data
+                .eq
+                .as_ref()
+                .filter(|_| config.equalizer_enabled),
+        )?;
+        self.playbin_element
+            .set_property("audio-filter", audio_filter);
+
+        let organisation = channel_data.organisation.clone();
+        if let Some(pause_before_playing_ms) = channel_data.pause_before_playing_ms {
             if self
                 .playbin_element
                 .set_state(gstreamer::State::Paused)
@@ -238,62 +508,128 @@ impl PlaybinElement {
                 eprintln!("gsteamer pause failed\r"); // if it fails, there is not much we can do about it; but at least the message might be seen
             }
 
-            let mut text_buffer = TextBuffer::new();
-            text_buffer.write_text_to_lines("Filling buffer".bytes(), LineNum::Line1, 1);
-            text_buffer.write_text_to_lines(
-                format!("for channel {}", channel_number).bytes(),
-                LineNum::Line2,
-                3,
-            );
-            lcd.write_text_buffer_to_lcd(&text_buffer);
-            std::thread::sleep(std::time::Duration::from_millis(pause_before_playing_ms));
+            // the main loop returns to RunningNormally once gstreamer says the buffer is full
+            status_of_rradio.running_status = RunningStatus::Buffering;
+            status_of_rradio.buffering_percent = 0;
+            status_of_rradio
+                .line_2_data
+                .update_if_changed(organisation.as_str());
+            if let Err(lcd_error) =
+                lcd.write_rradio_status_to_lcd(status_of_rradio, config, command_runner)
+            {
+                eprintln!("{lcd_error}\r");
+            }
+            // we must not sleep here, as that would stop the screen & the keyboard working,
+            // so the main loop calls play_deferred_track_if_due, which plays the track once the pause has elapsed
+            status_of_rradio.deferred_play = Some(DeferredPlay {
+                play_at: std::time::Instant::now()
+                    + std::time::Duration::from_millis(pause_before_playing_ms),
+                channel_number,
+                seek_wanted_if_possible,
+            });
+            return Ok(());
         }
+        self.start_playing(
+            status_of_rradio,
+            config,
+            channel_number,
+            seek_wanted_if_possible,
+        )
+    }
+
+    /// Called from the main loop; if a track is waiting for its channel's pause_before_playing_ms to elapse & it has,
+    /// starts playing it, unless it has been stopped meanwhile
+    pub fn play_deferred_track_if_due(
+        &self,
+        status_of_rradio: &mut PlayerStatus,
+        config: &crate::read_config::Config,
+    ) -> Result<(), String> {
+        let Some(deferred_play) = status_of_rradio
+            .deferred_play
+            .take_if(|deferred_play| std::time::Instant::now() >= deferred_play.play_at)
+        else {
+            return Ok(());
+        };
+        if status_of_rradio.gstreamer_state == gstreamer::State::Null {
+            return Ok(()); // something has stopped gstreamer while we were waiting, so we must not start it again
+        }
+        self.start_playing(
+            status_of_rradio,
+            config,
+            deferred_play.channel_number,
+            deferred_play.seek_wanted_if_possible,
+        )
+    }
+
+    /// Called from the main loop; if a track is waiting to be seeked to its stored position, seeks once gstreamer has a position.
+    /// If gstreamer has still not got a position after SEEK_READY_TIMEOUT, tries to seek anyway.
+    pub fn seek_if_ready(&self, status_of_rradio: &mut PlayerStatus) -> Result<(), String> {
+        let Some(pending_seek) = &status_of_rradio.pending_seek else {
+            return Ok(());
+        };
+        if pending_seek.channel_number != status_of_rradio.channel_number {
+            status_of_rradio.pending_seek = None; // the user has changed channel, so the seek is not wanted
+            return Ok(());
+        }
+        let can_seek = self
+            .playbin_element
+            .query_position::<gstreamer::ClockTime>()
+            .is_some();
+        if !can_seek && std::time::Instant::now() < pending_seek.give_up_at {
+            return Ok(()); // try again next time
+        }
+        let seek_time = pending_seek.seek_time;
+        status_of_rradio.pending_seek = None;
+        self.playbin_element
+            .seek_simple(
+                SeekFlags::FLUSH | SeekFlags::KEY_UNIT | SeekFlags::SNAP_NEAREST,
+                seek_time,
+            )
+            .map_err(|_seek_error| {
+                if can_seek {
+                    "Failed to seek"
+                } else {
+                    "failed to seek, probably because could not get a gstreamer position"
+                }
+                .to_string()
+            })
+    }
+
+    /// Sets gstreamer playing the track it has been given, fading it in & seeking to the stored position if wanted
+    fn start_playing(
+        &self,
+        status_of_rradio: &mut PlayerStatus,
+        config: &crate::read_config::Config,
+        channel_number: usize,
+        seek_wanted_if_possible: bool,
+    ) -> Result<(), String> {
         match self
             .playbin_element //clone here makes it stop working
             .set_state(gstreamer::State::Playing)
         {
             Ok(_playing_worked_okf) => {
-                if seek_wanted_if_possible {
-                    match status_of_rradio.position_and_duration[channel_number]
-                        .channel_data
-                        .source_type
-                    {
+                if let Some(fade_in_ms) = config.fade_in_ms
+                    && channel_number != player_status::START_UP_DING_CHANNEL_NUMBER
+                // dings are short, so there is no point fading them in
+                {
+                    self.start_fade_in(status_of_rradio.playing_volume(config), fade_in_ms);
+                }
+
+                if seek_wanted_if_possible
+                    && let Some(channel) = status_of_rradio.channel(channel_number)
+                {
+                    match channel.channel_data.source_type {
                         SourceType::Cd | SourceType::Usb => {
-                            let seek_time =
-                                status_of_rradio.position_and_duration[channel_number].position; // the position we will seek to in the units needed.
-                            // we use seconds as the unit as that is directly avaialble AND without an "Option"
-                            let mut can_seek = false; // note we cannot perform a gstreamer seek yet 
-
-                            for _count in 0..100 {
-                                // wait till gstreamer is ready for a seek, or time out if eventually we do not get a position
-                                if self
-                                    .playbin_element
-                                    .query_position::<gstreamer::ClockTime>()
-                                    .is_some()
-                                {
-                                    can_seek = true; // as we got a postion, we can now seek
-                                    break;
-                                }
-                                std::thread::sleep(std::time::Duration::from_millis(50));
-                            }
-
-                            if self
-                                .playbin_element
-                                .seek_simple(
-                                    SeekFlags::FLUSH
-                                        | SeekFlags::KEY_UNIT
-                                        | SeekFlags::SNAP_NEAREST,
-                                    seek_time,
-                                )
-                                .is_err()
-                            {
-                                return Err(if can_seek  {"Failed to seek"} 
-                                            else {
-                                                "failed to seek, probably because could not get a gstreamer position"}.to_string());
-                            }
+                            // we cannot seek until gstreamer has a position, but we must not wait for one here,
+                            // as that would stop the screen & the keyboard working, so the main loop calls seek_if_ready
+                            status_of_rradio.pending_seek = Some(PendingSeek {
+                                seek_time: channel.position,
+                                channel_number,
+                                give_up_at: std::time::Instant::now() + SEEK_READY_TIMEOUT,
+                            });
                             return Ok(());
                         }
-                        SourceType::UnknownSource | SourceType::UrlList => {
+                        SourceType::UnknownSource | SourceType::UrlList | SourceType::Playlist => {
                             return Ok(()); // we are playing OK & not seeking , so there is nothing to do.
                         }
                     }
@@ -308,6 +644,132 @@ impl PlaybinElement {
     }
 }
 
+/// Tries to decode the start of each notification sound & returns a message for each one that cannot be played,
+/// so the user finds out at startup rather than when the sound is first needed
+pub fn find_unplayable_notification_files(
+    aural_notifications: &crate::read_config::AuralNotifications,
+) -> Vec<String> {
+    [
+        &aural_notifications.filename_startup,
+        &aural_notifications.filename_sound_at_end_of_playlist,
+        &aural_notifications.filename_error,
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|filename| {
+        check_file_can_be_decoded(filename)
+            .err()
+            .map(|error_message| format!("Cannot play {filename}; {error_message}"))
+    })
+    .collect()
+}
+
+/// Decodes the start of the file, without playing it, to see if gstreamer can play it; returns an error string if it cannot
+fn check_file_can_be_decoded(filename: &str) -> Result<(), String> {
+    /// the time we wait for gstreamer to decode the start of the file; a local file normally takes a small fraction of this
+    const DECODE_TIMEOUT: gstreamer::ClockTime = gstreamer::ClockTime::from_seconds(5);
+    let fake_sink = gstreamer::ElementFactory::make("fakesink")
+        .build()
+        .map_err(|error| format!("could not create a fakesink; got {error}"))?;
+    // a file such as an MP3 with cover art has a video stream too, which must not open a window or fail for want of a display
+    let fake_video_sink = gstreamer::ElementFactory::make("fakesink")
+        .build()
+        .map_err(|error| format!("could not create a fakesink; got {error}"))?;
+    let checker = gstreamer::ElementFactory::make("playbin")
+        .build()
+        .map_err(|error| format!("could not create a playbin; got {error}"))?;
+    checker.set_property("uri", format!("file://{filename}"));
+    checker.set_property("audio-sink", &fake_sink); // so nothing is heard
+    checker.set_property("video-sink", &fake_video_sink); // & nothing is seen
+    let bus = checker.bus().ok_or("could not get the bus")?;
+    // pausing decodes just enough to fill the pipeline, which fails if the file is not audio gstreamer understands
+    let result = match checker.set_state(gstreamer::State::Paused) {
+        Err(error) => Err(format!("gstreamer could not decode it; got {error}")),
+        Ok(_state_change_success) => match bus
+            .timed_pop_filtered(
+                DECODE_TIMEOUT,
+                &[
+                    gstreamer::MessageType::AsyncDone,
+                    gstreamer::MessageType::Error,
+                ],
+            )
+            .as_ref()
+            .map(|message| message.view())
+        {
+            Some(gstreamer::MessageView::AsyncDone(_)) => Ok(()),
+            Some(gstreamer::MessageView::Error(error)) => {
+                Err(format!("gstreamer got error {}", error.error()))
+            }
+            _ => Err("gstreamer took too long to decode it".to_string()),
+        },
+    };
+    let _ = checker.set_state(gstreamer::State::Null); // we have finished with it, so if it fails there is nothing to do
+    result
+}
+
+/// Watches for audio outputs, such as a USB DAC, being added or removed, so that playing can be paused while the output is missing.
+/// Returns the monitor, which must be kept for as long as the messages are wanted, & the stream of its messages.
+pub fn setup_audio_output_monitor()
+-> Result<(gstreamer::DeviceMonitor, gstreamer::bus::BusStream), String> {
+    let device_monitor = gstreamer::DeviceMonitor::new();
+    device_monitor
+        .add_filter(Some("Audio/Sink"), None)
+        .ok_or("Could not add the audio output filter to the device monitor")?;
+    let bus_stream = device_monitor.bus().stream();
+    device_monitor
+        .start()
+        .map_err(|error| format!("Could not start the audio output monitor; got {error}"))?;
+    Ok((device_monitor, bus_stream))
+}
+
+/// Returns true if the element is an audio sink that is not a bin, as autoaudiosink is a bin that contains the sink it chooses
+fn is_audio_sink(element: &gstreamer::Element) -> bool {
+    element.factory().is_some_and(|factory| {
+        let klass = factory.klass();
+        klass.contains("Sink") && klass.contains("Audio")
+    }) && !element.is::<gstreamer::Bin>()
+}
+
+/// Returns the device the sink plays through, eg "hw:1", if it has a device property that is set
+fn name_of_sink_device(sink: &gstreamer::Element) -> Option<String> {
+    sink.find_property("device")?;
+    sink.property_value("device")
+        .get::<Option<String>>()
+        .ok()
+        .flatten()
+}
+
+/// Makes playbin use cdiocddasrc for cdda:// URIs, as it is the CD source that reads the CD-TEXT, which gives the album name.
+/// Otherwise playbin would choose cdparanoiasrc, if it is installed, as it has the higher rank.
+/// Does nothing if cdiocddasrc is not installed, so the CD plays without its CD-TEXT.
+fn prefer_cd_source_that_reads_cd_text() {
+    let registry = gstreamer::Registry::get();
+    let Some(cd_text_source) = registry.lookup_feature("cdiocddasrc") else {
+        println!("cdiocddasrc is not installed, so the CD-TEXT will not be read\r");
+        return;
+    };
+    if let Some(other_cd_source) = registry.lookup_feature("cdparanoiasrc")
+        && cd_text_source.rank() <= other_cd_source.rank()
+    {
+        cd_text_source.set_rank(other_cd_source.rank() + 1);
+    }
+}
+
+/// Sets the volume of the given playbin element; returns an error string if it fails
+fn set_stream_volume(
+    playbin_element: &gstreamer::Element,
+    volume_wanted: i32,
+) -> Result<(), String> {
+    playbin_element
+        .dynamic_cast_ref::<gstreamer_audio::StreamVolume>()
+        .ok_or("Could not get the stream volume")? // return the string. no panick
+        .set_volume(
+            gstreamer_audio::StreamVolumeFormat::Db,
+            f64::from(volume_wanted - VOLUME_ZERO_DB),
+        );
+    Ok(())
+}
+
 pub fn unmount_if_usb(
     channel_file_data_decoded: &mut ChannelFileDataDecoded,
 ) -> Result<(), String> {
@@ -60,7 +60,10 @@ pub fn write_status_to_web_page(
         secondary = format!("{} {}", status_of_rradio.all_4lines.text, secondary);
     }
 
-    if status_of_rradio.running_status == RunningStatus::RunningNormally {
+    if matches!(
+        status_of_rradio.running_status,
+        RunningStatus::RunningNormally | RunningStatus::Buffering | RunningStatus::Reconnecting
+    ) {
         match status_of_rradio.channel_number {
             NUMBER_OF_POSSIBLE_CHANNELS => write_message_to_web_page(
                 format!("Podcast {}", status_of_rradio.line_2_data.text),
@@ -3,28 +3,61 @@ use std::time::Duration;
 use futures_util::{FutureExt, StreamExt};
 use tokio::sync::mpsc;
 
+mod rotary_encoder;
+
 #[derive(Debug)]
 /// An enum of all possible outputs from the keyboard
 pub enum Event {
     PlayPause,
     EjectCD,
+    ReloadCD, // re-read the CD after the user has changed the disk & play it from the first track
     VolumeUp,
     VolumeDown,
     PreviousTrack,
     NextTrack,
+    SeekForward,  // move forward within the current CD or USB track by config.keyboard_seek_seconds
+    SeekBackward, // move backward within the current CD or USB track by config.keyboard_seek_seconds
+    RestartTrack, // go back to the start of the current CD or USB track, however long it has been playing
+    ShowCurrentUrl, // show the URL that gstreamer is playing on the LCD screen
+    ScrollFaster,   // scroll long text more often, until rradio is restarted
+    ScrollSlower,   // scroll long text less often, until rradio is restarted
+    SpeakTime,      // speak the time, then carry on playing the current channel
+    ToggleClockFormat, // swap the clock on the screen between the 24 hour & the 12 hour clock
+    ToggleTimeDisplay, // swap between showing the elapsed & the remaining time of a CD or USB track
+    StopAtEnd,      // stop when the last track of the current CD or USB album ends, rather than starting again
+    SelectCdTrack,  // the next number typed selects a track on the current CD rather than a channel; pressing it again cancels
+    ReconnectWifi,  // look for the IP address again &, if there is not one, reconnect the Wi-Fi
+    UnmuteSystem,   // unmute the ALSA mixer, & the DigiAMP+ amplifier if playing, in case something else has muted them
+    StationPreview, // start previewing the channels typed at a reduced volume; pressing it again keeps the channel being previewed
     OutputStatusDebug,                     // output the status of rradio
     OutputConfigDebug,                     // output the config info
+    DumpStatus,                            // write the whole status as JSON to a file in the startup folder
     NewLineOnScreen,                       // output a blank line on the screen 
-    PlayStation { channel_number: usize }, // channel_number will be  in the range "00" to "99", giving us the number of the station to play
+    ListStations,                          // show the channels found in the stations directory on the LCD screen
+    LcdTestPattern,                        // cycle through test patterns on the LCD screen until another key is pressed
+    SelfTest,                              // check each subsystem, such as the network & the CD drive, & show which passed
+    ShowLastError,                         // show the most recent error that was shown on the LCD screen again, with the time it was shown
+    PlayStation { channel_number: usize }, // channel_number will have config.channel_digits digits, eg in the range "00" to "99", giving us the number of the station to play
+    PlayFavorite { channel_number: usize }, // a function key was pressed, so play the channel even if a CD track is being selected
 }
 
 /// puts the keyboard into raw mode & prepares it to return a series of keyboard events
+/// channel_digits is the number of digits the user types to select a channel.
+/// favorites gives the channels played by the function keys, F1 playing the first entry.
+/// If a rotary encoder is specified, its events are sent in the same stream as those of the keyboard.
 pub fn setup_keyboard(
     input_timeout: Duration,
+    channel_digits: usize,
+    favorites: Vec<usize>,
+    rotary_encoder: Option<crate::read_config::RotaryEncoder>,
 ) -> tokio_stream::wrappers::UnboundedReceiverStream<Event> {
     let (events_tx, events_rx) = mpsc::unbounded_channel(); 
     // Create both ends of a message queue. The sender can be cloned, but the receiver cannot, hence MPSC (Multi-Producer, Single Consumer)
 
+    if let Some(rotary_encoder) = rotary_encoder {
+        tokio::spawn(rotary_encoder::read_rotary_encoder(rotary_encoder, events_tx.clone()));
+    }
+
     tokio::spawn(
         async move {
             match crossterm::terminal::enable_raw_mode() {
@@ -35,7 +68,7 @@ pub fn setup_keyboard(
                 }
             }
 
-            let mut stored_previous_digit_and_time: Option<(char, tokio::time::Instant)> = None; // store the previous digit entered;
+            let mut stored_previous_digits_and_time: Option<(String, tokio::time::Instant)> = None; // store the previous digits entered & when the last of them was entered
             let mut keyboard_events = crossterm::event::EventStream::new();
             loop {
                 // this loop matches keyboard events; other events are matched in a different task (& a different source file)
@@ -57,42 +90,66 @@ pub fn setup_keyboard(
                             | crossterm::event::KeyCode::Backspace => break, // alternative termination key (crossterm intercepts Control C so we cannot use that to terminate)
                             crossterm::event::KeyCode::Enter => Event::PlayPause,
                             crossterm::event::KeyCode::Char('.') => Event::EjectCD,
+                            crossterm::event::KeyCode::Char('%') => Event::ReloadCD,
                             crossterm::event::KeyCode::Char('*') => Event::VolumeUp,
                             crossterm::event::KeyCode::Char('/') => Event::VolumeDown,
                             crossterm::event::KeyCode::Char('-') => Event::PreviousTrack,
                             crossterm::event::KeyCode::Char('+') => Event::NextTrack,
+                            crossterm::event::KeyCode::Char(']') => Event::SeekForward,
+                            crossterm::event::KeyCode::Char('[') => Event::SeekBackward,
+                            crossterm::event::KeyCode::Char('=') => Event::RestartTrack,
+                            crossterm::event::KeyCode::Char('@') => Event::ShowCurrentUrl,
+                            crossterm::event::KeyCode::Char('>') => Event::StopAtEnd,
+                            crossterm::event::KeyCode::Char('t') => Event::SelectCdTrack,
+                            crossterm::event::KeyCode::Char('p') => Event::StationPreview,
+                            crossterm::event::KeyCode::Char('u') => Event::UnmuteSystem,
+                            crossterm::event::KeyCode::Char('w') => Event::ReconnectWifi,
+                            crossterm::event::KeyCode::Char(':') => Event::SpeakTime,
+                            crossterm::event::KeyCode::Char('h') => Event::ToggleClockFormat,
+                            crossterm::event::KeyCode::Char('r') => Event::ToggleTimeDisplay,
+                            crossterm::event::KeyCode::Char('}') => Event::ScrollFaster,
+                            crossterm::event::KeyCode::Char('{') => Event::ScrollSlower,
                             crossterm::event::KeyCode::Char('!') => Event::OutputStatusDebug,
                             crossterm::event::KeyCode::Char('£') => Event::OutputConfigDebug,
+                            crossterm::event::KeyCode::Char('&') => Event::DumpStatus,
                               crossterm::event::KeyCode::Char('^') => Event::NewLineOnScreen,
+                            crossterm::event::KeyCode::Char('$') => Event::ListStations,
+                            crossterm::event::KeyCode::Char('#') => Event::LcdTestPattern,
+                            crossterm::event::KeyCode::Char('?') => Event::SelfTest,
+                            crossterm::event::KeyCode::Char('~') => Event::ShowLastError,
+                            crossterm::event::KeyCode::F(function_key_number) => {
+                                // F1 is the first favorite; keys without a favorite are ignored
+                                match favorites.get(usize::from(function_key_number).wrapping_sub(1)) {
+                                    Some(&channel_number) => Event::PlayFavorite { channel_number },
+                                    None => continue,
+                                }
+                            }
                          
                             
                             crossterm::event::KeyCode::Char(current_digit @ '0'..='9') => {
                                 //the "@" symbol means make current_digit equal to the character that matched
-                                match stored_previous_digit_and_time {
-                                    //match if there is a previous digit & the elpased time is short enough
-                                    Some((previous_digit, previous_digit_pressed_time))
+                                let mut digits = match stored_previous_digits_and_time.take() {
+                                    //match if there are previous digits & the elpased time is short enough
+                                    Some((previous_digits, previous_digit_pressed_time))
                                         if previous_digit_pressed_time.elapsed()
-                                            < input_timeout =>
-                                    {
-                                        let new_channel =
-                                            format!("{}{}", previous_digit, current_digit)
-                                                .parse::<usize>();
-                                        Event::PlayStation {
-                                            channel_number: new_channel.expect("When trying to turn 2 characters into a u8 it failed"),
-                                        }
-                                    }
-                                    _ => {
-                                        stored_previous_digit_and_time =
-                                            Some((current_digit, tokio::time::Instant::now())); // Store both the current digit and the time it was pressed
+                                            < input_timeout => previous_digits,
+                                    _ => String::new(), // start again as the user was too slow
+                                };
+                                digits.push(current_digit);
+                                if digits.len() < channel_digits {
+                                    stored_previous_digits_and_time =
+                                        Some((digits, tokio::time::Instant::now())); // Store both the digits so far and the time the last was pressed
 
-                                        continue; // exit the current match statement & ignore all code in the rest of the loop & go round the loop again
-                                    }
+                                    continue; // exit the current match statement & ignore all code in the rest of the loop & go round the loop again
+                                }
+                                Event::PlayStation {
+                                    channel_number: digits.parse::<usize>().expect("When trying to turn the digits into a usize it failed"),
                                 }
                             }
                             _ => continue,
                         };
 
-                        stored_previous_digit_and_time = None; // sets both the previous digit & time to none
+                        stored_previous_digits_and_time = None; // sets both the previous digits & time to none
 
                         match events_tx.send(keyboard_event) {
                             Ok(()) => (),
@@ -0,0 +1,229 @@
+// reads a rotary encoder connected to the GPIO pins & sends the same events as the keyboard
+use super::Event;
+use crate::read_config::RotaryEncoder;
+use rppal::gpio::{Gpio, InputPin, Trigger};
+use tokio::sync::mpsc;
+
+/// The time for which changes of the push button are ignored after a change, so that its contacts bouncing is ignored
+const PUSH_BUTTON_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// The change in position for each change in the state of pins A & B, indexed by (previous state << 2) | new state,
+/// where the state is (pin A << 1) | pin B; invalid changes, where both pins change at once, are 0
+const QUADRATURE_STEPS: [i8; 16] = [0, -1, 1, 0, 1, 0, 0, -1, -1, 0, 0, 1, 0, 1, -1, 0];
+
+#[derive(Debug)]
+/// A change of one of the encoder's pins, sent from rppal's interrupt thread to the task that decodes it
+enum PinChange {
+    /// pin A is now high (true) or low (false)
+    PinA(bool),
+    /// pin B is now high (true) or low (false)
+    PinB(bool),
+    PushButtonPressed,
+}
+
+#[derive(Debug, PartialEq)]
+/// The direction of a click of the encoder
+enum Rotation {
+    /// from pin A towards pin B; if the volume changes the wrong way, swap pin_a & pin_b in the config file
+    Clockwise,
+    AntiClockwise,
+}
+
+impl Rotation {
+    /// The event sent for a click in this direction
+    fn into_event(self) -> Event {
+        match self {
+            Rotation::Clockwise => Event::VolumeUp,
+            Rotation::AntiClockwise => Event::VolumeDown,
+        }
+    }
+}
+
+/// Turns the changes of pins A & B into clicks
+struct QuadratureDecoder {
+    previous_state: u8,
+    /// the sum of the steps since the last click
+    steps: i8,
+    transitions_per_click: i8,
+}
+
+impl QuadratureDecoder {
+    fn new(pin_a_is_high: bool, pin_b_is_high: bool, transitions_per_click: u8) -> Self {
+        QuadratureDecoder {
+            previous_state: (u8::from(pin_a_is_high) << 1) | u8::from(pin_b_is_high),
+            steps: 0,
+            transitions_per_click: i8::try_from(transitions_per_click.max(1)).unwrap_or(i8::MAX),
+        }
+    }
+
+    /// Given the new levels of the pins, returns the direction if the encoder has moved by a whole click
+    fn update(&mut self, pin_a_is_high: bool, pin_b_is_high: bool) -> Option<Rotation> {
+        let state = (u8::from(pin_a_is_high) << 1) | u8::from(pin_b_is_high);
+        self.steps += QUADRATURE_STEPS[usize::from((self.previous_state << 2) | state)];
+        self.previous_state = state;
+        if self.steps >= self.transitions_per_click {
+            self.steps = 0;
+            Some(Rotation::Clockwise)
+        } else if self.steps <= -self.transitions_per_click {
+            self.steps = 0;
+            Some(Rotation::AntiClockwise)
+        } else {
+            None
+        }
+    }
+
+    /// Given the new level of pin A, returns the direction if the encoder has moved by a whole click
+    fn update_pin_a(&mut self, pin_a_is_high: bool) -> Option<Rotation> {
+        self.update(pin_a_is_high, self.previous_state & 0b01 != 0)
+    }
+
+    /// Given the new level of pin B, returns the direction if the encoder has moved by a whole click
+    fn update_pin_b(&mut self, pin_b_is_high: bool) -> Option<Rotation> {
+        self.update(self.previous_state & 0b10 != 0, pin_b_is_high)
+    }
+}
+
+/// Gets the pin as an input with its pull-up resistor enabled, as the encoder connects its pins to ground
+fn get_input_pin(gpio: &Gpio, pin_number: u8) -> Result<InputPin, String> {
+    gpio.get(pin_number)
+        .map(|pin| pin.into_input_pullup())
+        .map_err(|error| format!("When trying to get GPIO pin {pin_number} got error {error}"))
+}
+
+/// Asks rppal to call back, on its interrupt thread, whenever the pin changes, sending the change made by pin_change
+/// from the new level, if there is one, to the task that decodes it. The interrupt is cleared when the pin is dropped.
+fn send_changes_of_pin(
+    pin: &mut InputPin,
+    debounce: Option<std::time::Duration>,
+    pin_changes_tx: &mpsc::UnboundedSender<PinChange>,
+    pin_change: fn(bool) -> Option<PinChange>,
+) -> Result<(), String> {
+    let pin_number = pin.pin();
+    let pin_changes_tx = pin_changes_tx.clone();
+    pin.set_async_interrupt(Trigger::Both, debounce, move |event| {
+        if let Some(pin_change) = pin_change(event.trigger == Trigger::RisingEdge) {
+            let _ = pin_changes_tx.send(pin_change); // if the receiver has closed, the encoder is no longer being read
+        }
+    })
+    .map_err(|error| format!("When trying to watch GPIO pin {pin_number} got error {error}"))
+}
+
+/// Reads the rotary encoder until the receiver of the events closes, sending VolumeUp or VolumeDown for each click
+/// as it is turned & PlayPause when its push button is pressed. The pins interrupt when they change, so no change
+/// is missed however quickly the encoder is turned, & nothing is done while it is not touched.
+/// If the GPIO pins cannot be got, the error is printed & nothing is sent, so the keyboard still works.
+pub async fn read_rotary_encoder(
+    rotary_encoder: RotaryEncoder,
+    events_tx: mpsc::UnboundedSender<Event>,
+) {
+    let (pin_changes_tx, mut pin_changes_rx) = mpsc::unbounded_channel();
+    let pins = Gpio::new()
+        .map_err(|error| format!("When trying to get the GPIO pins got error {error}"))
+        .and_then(|gpio| {
+            let mut pin_a = get_input_pin(&gpio, rotary_encoder.pin_a)?;
+            let mut pin_b = get_input_pin(&gpio, rotary_encoder.pin_b)?;
+            let mut push_button = rotary_encoder
+                .push_button_pin
+                .map(|pin_number| get_input_pin(&gpio, pin_number))
+                .transpose()?;
+            let decoder = QuadratureDecoder::new(
+                pin_a.is_high(),
+                pin_b.is_high(),
+                rotary_encoder.transitions_per_click,
+            );
+            send_changes_of_pin(&mut pin_a, None, &pin_changes_tx, |is_high| {
+                Some(PinChange::PinA(is_high))
+            })?;
+            send_changes_of_pin(&mut pin_b, None, &pin_changes_tx, |is_high| {
+                Some(PinChange::PinB(is_high))
+            })?;
+            if let Some(push_button) = &mut push_button {
+                // the button connects the pin to ground, so it is pressed when the pin goes low
+                send_changes_of_pin(
+                    push_button,
+                    Some(PUSH_BUTTON_DEBOUNCE),
+                    &pin_changes_tx,
+                    |is_high| (!is_high).then_some(PinChange::PushButtonPressed),
+                )?;
+            }
+            Ok((decoder, (pin_a, pin_b, push_button)))
+        });
+    drop(pin_changes_tx); // only the interrupt callbacks send changes
+    // the pins must be kept, as dropping them clears their interrupts
+    let (mut decoder, _watched_pins) = match pins {
+        Ok(decoder_and_pins) => decoder_and_pins,
+        Err(error_message) => {
+            eprintln!("Cannot use the rotary encoder: {error_message}\r");
+            return;
+        }
+    };
+
+    while let Some(pin_change) = pin_changes_rx.recv().await {
+        let event = match pin_change {
+            PinChange::PinA(pin_a_is_high) => decoder
+                .update_pin_a(pin_a_is_high)
+                .map(Rotation::into_event),
+            PinChange::PinB(pin_b_is_high) => decoder
+                .update_pin_b(pin_b_is_high)
+                .map(Rotation::into_event),
+            PinChange::PushButtonPressed => Some(Event::PlayPause),
+        };
+        if let Some(event) = event
+            && events_tx.send(event).is_err()
+        {
+            return; // The receiver (IE the main program) has closed.
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The states of pins A & B, starting from both high, as the encoder is turned clockwise by one click
+    const ONE_CLICK_CLOCKWISE: [(bool, bool); 4] =
+        [(false, true), (false, false), (true, false), (true, true)];
+
+    #[test]
+    fn a_whole_click_is_decoded_in_each_direction() {
+        let mut decoder = QuadratureDecoder::new(true, true, 4);
+        let rotations: Vec<Option<Rotation>> = ONE_CLICK_CLOCKWISE
+            .iter()
+            .map(|&(pin_a, pin_b)| decoder.update(pin_a, pin_b))
+            .collect();
+        assert_eq!(rotations, vec![None, None, None, Some(Rotation::Clockwise)]);
+
+        let rotations: Vec<Option<Rotation>> = ONE_CLICK_CLOCKWISE
+            .iter()
+            .rev()
+            .skip(1)
+            .chain(std::iter::once(&(true, true)))
+            .map(|&(pin_a, pin_b)| decoder.update(pin_a, pin_b))
+            .collect();
+        assert_eq!(
+            rotations,
+            vec![None, None, None, Some(Rotation::AntiClockwise)]
+        );
+    }
+
+    #[test]
+    fn changes_of_single_pins_are_decoded() {
+        let mut decoder = QuadratureDecoder::new(true, true, 4);
+        let rotations = [
+            decoder.update_pin_a(false),
+            decoder.update_pin_b(false),
+            decoder.update_pin_a(true),
+            decoder.update_pin_b(true),
+        ];
+        assert_eq!(rotations, [None, None, None, Some(Rotation::Clockwise)]);
+    }
+
+    #[test]
+    fn bouncing_contacts_do_not_make_a_click() {
+        let mut decoder = QuadratureDecoder::new(true, true, 4);
+        for _ in 0..10 {
+            assert_eq!(decoder.update(true, false), None);
+            assert_eq!(decoder.update(true, true), None);
+        }
+    }
+}
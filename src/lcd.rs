@@ -5,22 +5,31 @@ The lines below instruct the Pi to use the hd44780 driver & load it
     dtparam=pin_d4=24,pin_d5=23,pin_d6=25,pin_d7=9
 */
 
-use std::{
-    io::Write,
-    time::{Duration, Instant},
-};
+use std::{io::Write, time::Instant};
 
 use crate::{
-    get_channel_details::{self, SourceType}, ping::PingTimeAndDestination, player_status
+    command_runner::CommandRunner,
+    get_channel_details::{self, SourceType},
+    get_local_ip_address::NetworkState,
+    gstreamer_interfaces::{VOLUME_MAX, VOLUME_MIN},
+    ping::PingTimeAndDestination,
+    player_status,
+    read_config::{BufferBarStyle, StartupLine1},
 };
-use anyhow::Context;
-use itertools::Itertools;
 
-mod character_pattern;
+pub mod character_pattern;
 pub mod get_mute_state;
-mod get_temperature;
+pub mod get_temperature;
 pub mod get_throttled;
 mod get_wifi_strength;
+#[cfg(feature = "mock-lcd")]
+pub mod mock_lcd;
+
+/// What the screen is written to; /dev/lcd, or the terminal when built with the mock-lcd feature
+#[cfg(not(feature = "mock-lcd"))]
+pub type LcdDevice = std::fs::File;
+#[cfg(feature = "mock-lcd")]
+pub type LcdDevice = mock_lcd::MockLcd;
 
 
 #[derive(PartialEq, Debug)]
@@ -31,7 +40,7 @@ pub enum LineNum {
     Line3,
     Line4,
 }
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
 /// Specifies if we are starting up, in which case we want to see the startup message, shutting down or running normally.
 /// or there is a long message to display
 pub enum RunningStatus {
@@ -46,6 +55,10 @@ pub enum RunningStatus {
     /// there is a long error message that uses all 4 lines & probably needs to scroll
     LongMessageOnAll4Lines,
     ShuttingDown,
+    /// a stream is filling its buffer, so the screen shows the organisation & how full the buffer is
+    Buffering,
+    /// a stream dropped out & is waiting to be, or is being, reconnected; the screen shows the organisation & the attempt number
+    Reconnecting,
 }
 
 /// The display is visually 20 * 4 characters
@@ -57,6 +70,25 @@ pub const VOLUME_CHAR_COUNT: usize = 7;
 /// Number of chacters to one first line less the characters needed to display the volume
 pub const LINE1_DATA_CHAR_COUNT: usize = NUM_CHARACTERS_PER_LINE - VOLUME_CHAR_COUNT;
 
+/// The screen contents are shifted by up to this number of columns less one to prevent burn in
+const MAXIMUM_BURN_IN_SHIFT: u64 = 4;
+
+/// A character that fills the whole of its position on the screen, as all its pixels are on (see the look up table in
+/// GDM2004D.pdf page 9/9); used to draw the buffering progress bar & the checkerboard test pattern
+const FULL_BLOCK_CHARACTER: u8 = 0xFF;
+
+/// How long a brief message (status_of_rradio.line_1_brief_message) is shown on line 1
+const BRIEF_MESSAGE_DISPLAY_TIME_MS: i64 = 3000;
+
+/// The number of times in a row that writing to the screen must fail before we try to reopen it
+const WRITE_FAILURES_BEFORE_REOPENING: u32 = 5;
+
+/// The number of different screens shown by fill_text_buffer_with_test_pattern
+pub const NUMBER_OF_TEST_PATTERNS: usize = 5;
+
+/// The number of different pieces of information that get_rotating_info_text cycles through
+pub const NUMBER_OF_INFO_ITEMS: usize = 4;
+
 /// encodes the line numbers on the LCD screen
 impl LineNum {
     fn into_usize(self) -> usize {
@@ -92,48 +124,91 @@ impl std::fmt::Debug for LcdScreenEncodedText {
     }
 }
 
-#[derive(Debug)]
+/// The most display cells that a single character can be transliterated to, eg "(c)" for ©; longer transliterations are cut short
+/// so that a character such as a Chinese one, which is transliterated to a whole syllable, does not fill the line.
+const MAXIMUM_CELLS_PER_CHARACTER: usize = 4;
+
+/// Returns the ASCII transliteration of one_char, which is at most MAXIMUM_CELLS_PER_CHARACTER characters long,
+/// so every character takes a predictable number of display cells. Characters that are not seen on their own,
+/// such as combining accents & zero width joiners, take no cells; characters unidecode cannot transliterate become "?".
+fn transliterate_char(one_char: char) -> &'static str {
+    // unidecode adds a trailing space to Chinese syllables
+    match unidecode::unidecode_char(one_char).trim_end() {
+        "" if is_invisible_char(one_char) => "",
+        "" | "[?]" => "?", // "[?]" is how unidecode marks a character it has no transliteration for
+        // unidecode only returns ASCII, so this cannot split a character
+        transliteration => {
+            &transliteration[..transliteration.len().min(MAXIMUM_CELLS_PER_CHARACTER)]
+        }
+    }
+}
+
+/// Returns true if one_char modifies the character next to it, or joins characters, rather than being seen on its own
+fn is_invisible_char(one_char: char) -> bool {
+    matches!(
+        one_char,
+        '\u{0300}'..='\u{036F}' // combining accents
+            | '\u{200B}'..='\u{200F}' // zero width spaces, joiners & direction marks
+            | '\u{20D0}'..='\u{20FF}' // combining marks for symbols
+            | '\u{FE00}'..='\u{FE0F}' // variation selectors, eg the one that asks for an emoji to be drawn in colour
+            | '\u{FEFF}' // zero width no-break space
+    )
+}
+
+/// Encodes the text into the LCD screen character set. Characters below '~' are sent unchanged; accented characters
+/// that the screen can display are mapped to their codes, new lines & carriage returns are replaced by visible characters
+/// & anything else is transliterated to ASCII.
+/// Every byte returned takes exactly one display cell, so the scrolling & line fitting can count bytes.
+pub fn encode_to_lcd_bytes(text: &str) -> Vec<u8> {
+    let mut text_bytes = Vec::new();
+
+    for one_char in text.chars() {
+        if one_char < '~' && (one_char != '\n') && (one_char != '\r') {
+            text_bytes.push(one_char as u8);
+        } else {
+            text_bytes.extend_from_slice(match one_char {
+                'é' => &[5], // e accute fifth bespoke character defined starting with the zeroeth bespoke character
+                'è' => &[6], // e grave
+                'à' => &[7], // a grave
+                'ä' => &[0xE1], // a umlaut            // see look up table in GDM2004D.pdf page 9/9
+                'ñ' => &[0xEE], // n tilde
+                'ö' => &[0xEF], // o umlaut
+                'ü' => &[0xF5], // u umlaut
+                'π' => &[0xE4], // pi
+                'µ' => &[0xF7], // mu
+                '~' => &[0xF3], // cannot display tilde using the standard character set in GDM2004D.pdf. This is the best we can do.
+                '' => &[0xFF], // <Control>  = 0x80 replaced by splodge
+                '\n' => &[0xCD], // new line & line feed do not display well, so replace them with a different character
+                '\r' => &[0xCF], // new line & line feed do not display well, so replace them with a different character
+                _ => transliterate_char(one_char).as_bytes(),
+            });
+        }
+    }
+
+    text_bytes
+}
+
+#[derive(Debug, serde::Serialize)]
 /// Holds the text, and information on how to display it, namely the scroll position,
 /// the number of lines & the time the text was last scrolled.
 pub struct ScrollData {
+    #[serde(skip)] // it is the same as text, but encoded for the LCD screen
     pub lcd_encoded_text: LcdScreenEncodedText,
     pub text: String,
     pub scroll_position: usize,
     pub num_lines: usize,
+    #[serde(skip)] // an Instant cannot be serialised
     pub last_update_time: Instant,
 }
 
 impl ScrollData {
-    /// encodes the new text into the LCD screen character set & stores that in text_bytes.
+    /// encodes the new text into the LCD screen character set & stores that in lcd_encoded_text.
     /// Also initialises the scrolling state.
     pub fn new(text: &str, num_lines: usize) -> Self {
-        let mut text_bytes = Vec::new();
-
-        for one_char in text.chars() {
-            if one_char < '~' && (one_char != '\n') && (one_char != '\r') {
-                text_bytes.push(one_char as u8);
-            } else {
-                text_bytes.extend_from_slice(match one_char {
-                    'é' => &[5], // e accute fifth bespoke character defined starting with the zeroeth bespoke character
-                    'è' => &[6], // e grave
-                    'à' => &[7], // a grave
-                    'ä' => &[0xE1], // a umlaut            // see look up table in GDM2004D.pdf page 9/9
-                    'ñ' => &[0xEE], // n tilde
-                    'ö' => &[0xEF], // o umlaut
-                    'ü' => &[0xF5], // u umlaut
-                    'π' => &[0xE4], // pi
-                    'µ' => &[0xF7], // mu
-                    '~' => &[0xF3], // cannot display tilde using the standard character set in GDM2004D.pdf. This is the best we can do.
-                    '' => &[0xFF], // <Control>  = 0x80 replaced by splodge
-                    '\n' => &[0xCD], // new line & line feed do not display well, so replace them with a different character
-                    '\r' => &[0xCF], // new line & line feed do not display well, so replace them with a different character
-                    _ => unidecode::unidecode_char(one_char).as_bytes(),
-                });
-            }
-        }
-
         Self {
-            lcd_encoded_text: LcdScreenEncodedText { bytes: text_bytes },
+            lcd_encoded_text: LcdScreenEncodedText {
+                bytes: encode_to_lcd_bytes(text),
+            },
             text: text.to_string(),
             scroll_position: 0,
             num_lines,
@@ -142,15 +217,17 @@ impl ScrollData {
     }
 
     /// Given a scrollable line, if it is time to scroll, updates the scroll position & last_update_time.
-    /// or does nothing if it is not yet time to scroll
+    /// or does nothing if it is not yet time to scroll.
+    /// scroll_period_ms is passed separately from the config, as the user can change it while rradio is running.
     pub fn update_scroll(
         &mut self,
         config: &crate::read_config::Config, // the data read from rradio's config.toml
+        scroll_period_ms: u64,
         number_of_available_characters: usize, // in some cases some characters at the end of the line are reserved for other strings
     ) {
         if (self.lcd_encoded_text.bytes.len() <= number_of_available_characters)
             || (self.last_update_time.elapsed()
-                < tokio::time::Duration::from_millis(config.scroll.scroll_period_ms))
+                < tokio::time::Duration::from_millis(scroll_period_ms))
         {
             return; // we do not need to scroll
         }
@@ -284,13 +361,180 @@ impl TextBuffer {
     }
 }
 
-/// Used to interface to the LCD screen
-pub struct Lc {
-    lcd_file: std::fs::File,
+#[derive(Debug)]
+/// The ways in which opening or writing to the LCD screen can fail
+pub enum LcdError {
+    /// Could not open /dev/lcd for a reason other than those below; the string contains the reason
+    OpenFailed(String),
+    /// Could not write to the screen; the string contains the reason returned by the operating system.
+    /// This might be transient, so it is worth trying again
+    WriteFailed(String),
+    /// Another copy of the program is using the screen & could not be stopped; the string contains the reason
+    AlreadyInUse(String),
+    /// The program is not running with enough privilege to open /dev/lcd
+    InsufficientPrivilege,
+}
+
+impl std::fmt::Display for LcdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LcdError::OpenFailed(error_message) => {
+                write!(f, "Failed to open the LCD screen: {error_message}")
+            }
+            LcdError::WriteFailed(error_message) => {
+                write!(f, "Failed to write to the LCD screen: {error_message}")
+            }
+            LcdError::AlreadyInUse(error_message) => write!(
+                f,
+                "The LCD screen is in use by another copy of the program: {error_message}"
+            ),
+            LcdError::InsufficientPrivilege => write!(
+                f,
+                "Failed to open LCD file. Are you running with root privilege"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LcdError {}
+
+/// Used to interface to the LCD screen.
+/// lcd_file is normally /dev/lcd (see LcdDevice), but can be anything that implements Write, eg a Vec<u8> when testing
+pub struct Lc<W: std::io::Write = LcdDevice> {
+    lcd_file: W,
+    /// the number of times in a row write_rradio_status_to_lcd has failed; used to decide when to reopen the screen
+    consecutive_write_failures: u32,
+    /// the bitmaps of the 8 user defined characters; character_pattern::BITMAPS unless config.custom_chars is specified
+    character_bitmaps: [[u8; 8]; 8],
+}
+#[cfg(not(feature = "mock-lcd"))]
+impl Lc {
+    /// returns a handle to the LCD screen or panics & explains why.
+    /// if it fails, that will typically either be because the caller is not running with enough priviledge
+    /// or the program has already been started. In the latter case, if take_over_from_other_instance is true, the program tries to kill the other program
+    /// & tries once more to get the screen; otherwise it returns LcdError::AlreadyInUse.
+    pub fn new(
+        take_over_from_other_instance: bool,
+        command_runner: &impl CommandRunner,
+    ) -> Result<Self, LcdError> {
+        use itertools::Itertools;
+        use std::time::Duration;
+
+        const LCD_ALREADY_IN_USE: i32 = 16; // another version of the program is probably using it
+        const INSUFFICIENT_PRIVILEGE: i32 = 13;
+
+        if let Err(error) = std::fs::File::options().write(true).open("/dev/lcd") {
+            if let Some(INSUFFICIENT_PRIVILEGE) = error.raw_os_error() {
+                return Err(LcdError::InsufficientPrivilege);
+            } else if let Some(LCD_ALREADY_IN_USE) = error.raw_os_error() {
+                if !take_over_from_other_instance {
+                    return Err(LcdError::AlreadyInUse(
+                        "The LCD screen is in use by another copy of the program, which was not stopped as --no-takeover was specified".to_string(),
+                    ));
+                }
+                //the error is that a copy of the program is already running so get its PID & then kill it
+                // command is ps -C program_name // where program_name is the name of the program 
+                match command_runner.output("/bin/ps", &["-C", env!("CARGO_PKG_NAME")])
+                    // output.stdout should be three lines, the first, the column headers, 
+                    //& then two lines, one is our PID & the other is the PID of the program we are trying to kill
+                {
+                    Ok(output) => {
+                        let output_as_string = String::from_utf8_lossy(&output.stdout);
+                        let output_as_a_vec_of_lines : Vec<&str>= output_as_string.lines().collect();
+                        let my_pid_as_string= std::process::id().to_string();
+                        let my_pid_as_str = my_pid_as_string.as_str() ;
+
+                        for line in output_as_a_vec_of_lines.iter().dropping(1){ // drop the title line
+                            let (pid, _) = line.trim_start().split_once(" ").unwrap_or_default();
+                            if  pid != my_pid_as_str {
+                                // we have found the PID to kill
+                                match command_runner.output("/bin/kill", &[pid])   {
+                                Ok(_success_message)=> {std::thread::sleep(Duration::from_millis(500) ); //wait for the other program to be killed
+                                    let lcd_file = std::fs::File::options().write(true).open("/dev/lcd").
+                                    map_err(|error| LcdError::AlreadyInUse(format!("Failed to open LCD file after succesfully stopping a previous version of rradio. Got {error}")))?;
+                                    Self::clear_screen(&lcd_file, &character_pattern::BITMAPS);
+                                    return Ok(Lc {lcd_file, consecutive_write_failures: 0, character_bitmaps: character_pattern::BITMAPS})}
+                                Err(failure_message)=> {
+                                    return Err(LcdError::AlreadyInUse(format!(
+                                        "Probably failed to kill the previous process that was using the screen {}.\r", failure_message)))}
+                                }                                  
+                            }
+                        }
+                    }
+                    Err(error) => {
+                        return Err(LcdError::AlreadyInUse(format!("When trying to get the PIDs in order to stop the previous version of the program got {}",error)))
+                    }
+                };
+            } else {
+                return Err(LcdError::OpenFailed(format!("Could not access the LCD screen; got {error}")))
+            }
+        }
+
+        let lcd_file = std::fs::File::options()
+            .write(true)
+            .open("/dev/lcd")
+            .map_err(|error| LcdError::OpenFailed(error.to_string()))?;
+
+        Self::clear_screen(&lcd_file, &character_pattern::BITMAPS);
+        Ok(Lc {
+            lcd_file,
+            consecutive_write_failures: 0,
+            character_bitmaps: character_pattern::BITMAPS,
+        })
+    }
+
+    /// Opens /dev/lcd again & initialises it, eg after the screen has been unplugged & plugged back in.
+    /// The failure count is reset whether or not it works, so the caller tries again only after another
+    /// WRITE_FAILURES_BEFORE_REOPENING failures. The next write_rradio_status_to_lcd restores the display.
+    pub fn reopen(&mut self) -> Result<(), LcdError> {
+        self.consecutive_write_failures = 0;
+        let lcd_file = std::fs::File::options()
+            .write(true)
+            .open("/dev/lcd")
+            .map_err(|error| LcdError::OpenFailed(format!("Could not reopen the LCD screen; got {error}")))?;
+        Self::clear_screen(&lcd_file, &self.character_bitmaps);
+        self.lcd_file = lcd_file;
+        Ok(())
+    }
 }
+
+#[cfg(feature = "mock-lcd")]
 impl Lc {
+    /// returns a handle to a pretend screen that is drawn in the terminal; it cannot fail
+    pub fn new(
+        _take_over_from_other_instance: bool,
+        _command_runner: &impl CommandRunner,
+    ) -> Result<Self, LcdError> {
+        let mut lcd = Lc::from_writer(mock_lcd::MockLcd::new());
+        lcd.clear();
+        Ok(lcd)
+    }
+
+    /// There is nothing to reopen, so this just resets the failure count
+    pub fn reopen(&mut self) -> Result<(), LcdError> {
+        self.consecutive_write_failures = 0;
+        Ok(())
+    }
+}
+
+impl<W: std::io::Write> Lc<W> {
+    /// Uses lcd_file instead of /dev/lcd; unlike new, it does not initialise the screen.
+    /// Used by the tests to capture exactly what would have been sent to the screen.
+    pub fn from_writer(lcd_file: W) -> Self {
+        Lc {
+            lcd_file,
+            consecutive_write_failures: 0,
+            character_bitmaps: character_pattern::BITMAPS,
+        }
+    }
+
+    /// true if writing to the screen has failed often enough in a row that it is worth calling reopen
+    pub fn needs_reopening(&self) -> bool {
+        self.consecutive_write_failures >= WRITE_FAILURES_BEFORE_REOPENING
+    }
+
     /// Initialises the screen & stops the cursor blinking & turns the cursor off
-    fn clear_screen(mut lcd_file: impl std::io::Write) {
+    fn clear_screen(mut lcd_file: impl std::io::Write, character_bitmaps: &[[u8; 8]; 8]) {
         if let Err(err) = write!(lcd_file, "\x1b[LI\x1b[Lb\x1b[Lc") {
             eprintln!("Failed to initialise the screen : {err}");
         }
@@ -299,7 +543,7 @@ impl Lc {
         for char_count in 0..8 {
             let mut out_string = format!("\x1b[LG{:01x}", char_count);
             for col_count in 0..8 {
-                let s = format!("{:02x}", character_pattern::BITMAPS[char_count][col_count]);
+                let s = format!("{:02x}", character_bitmaps[char_count][col_count]);
                 out_string = out_string + &s;
             }
             out_string.push(';');
@@ -337,86 +581,89 @@ impl Lc {
         }
     }
 
-    /// returns a handle to the LCD screen or panics & explains why.
-    /// if it fails, that will typically either be because the caller is not running with enough priviledge
-    /// or the program has already been started. In the latter case, the program tries to kill the other program
-    /// & tries once more to get the screen.
-    pub fn new() -> anyhow::Result<Self> {
-        const LCD_ALREADY_IN_USE: i32 = 16; // another version of the program is probably using it
-        const INSUFFICIENT_PRIVILEGE: i32 = 13;
-
-        if let Err(error) = std::fs::File::options().write(true).open("/dev/lcd") {
-            if let Some(INSUFFICIENT_PRIVILEGE) = error.raw_os_error() {
-                anyhow::bail!("Failed to open LCD file. Are you running with root privilege");
-            } else if let Some(LCD_ALREADY_IN_USE) = error.raw_os_error() {
-                //the error is that a copy of the program is already running so get its PID & then kill it
-                match std::process::Command::new("/bin/ps") 
-                // command is ps -C program_name // where program_name is the name of the program 
-                    .args(["-C", env!("CARGO_PKG_NAME")])
-                    .output()// output.stdout should be three lines, the first, the column headers, 
-                    //& then two lines, one is our PID & the other is the PID of the program we are trying to kill
-                {
-                    Ok(output) => {
-                        let output_as_a_vec_of_lines : Vec<&str>= std::str::from_utf8(&output.stdout).unwrap_or_default().lines().collect();
-                        let my_pid_as_string= std::process::id().to_string();
-                        let my_pid_as_str = my_pid_as_string.as_str() ;
+    /// Turns the backlight on or off by sending "\x1b[L+" or "\x1b[L-" respectively, which are the sequences
+    /// understood by the Linux HD44780 driver. Users with a backpack that uses different sequences should change them here.
+    pub fn set_backlight(&mut self, on: bool) -> Result<(), LcdError> {
+        let escape_sequence = if on { "\x1b[L+" } else { "\x1b[L-" };
+        write!(self.lcd_file, "{escape_sequence}").map_err(|err| {
+            LcdError::WriteFailed(format!("Failed to set the backlight : {err}"))
+        })
+    }
 
-                        for line in output_as_a_vec_of_lines.iter().dropping(1){ // drop the title line
-                            let (pid, _) = line.trim_start().split_once(" ").unwrap_or_default();
-                            if  pid != my_pid_as_str {
-                                // we have found the PID to kill
-                                match std::process::Command::new("/bin/kill").arg(pid).output()   {
-                                Ok(_success_message)=> {std::thread::sleep(Duration::from_millis(500) ); //wait for the other program to be killed
-                                    let lcd_file = std::fs::File::options().write(true).open("/dev/lcd").
-                                    context("Failed to open LCD file after succesfully stopping a previous version of rradio.")?;
-                                    Self::clear_screen(&lcd_file);
-                                    return Ok(Lc {lcd_file})}
-                                Err(failure_message)=> {
-                                    anyhow::bail!(format!(
-                                        "Probably failed to kill the previous process that was using the screen{:?}.\r", failure_message))}
-                                }                                  
-                            }
-                        }
-                    }
-                    Err(error) => {
-                        anyhow::bail!("When trying to get the PIDs in order to stop the previous version of the program got {:?}",error)
-                    }
-                };
-            } else {
-                anyhow::bail!("Could not access the LCD screen")
-            }
-        }
+    /// Clears the LCD screen, but not any associated text buffers
+    pub fn clear(&mut self) {
+        Self::clear_screen(&mut self.lcd_file, &self.character_bitmaps);
+    }
 
-        let lcd_file = std::fs::File::options()
-            .write(true)
-            .open("/dev/lcd")
-            .context("Failed to open LCD file. Are you running with root privilege")?;
+    /// Replaces the bitmaps of the 8 user defined characters, eg with config.custom_chars, & sends them to the screen
+    pub fn set_character_bitmaps(&mut self, character_bitmaps: [[u8; 8]; 8]) {
+        self.character_bitmaps = character_bitmaps;
+        self.clear();
+    }
 
-        Self::clear_screen(&lcd_file);
-        Ok(Lc { lcd_file })
+    /// If config.wifi_bars_character is specified, redefines that user defined character to show the current Wi-Fi signal as bars.
+    /// The character is only sent to the screen when the number of bars changes.
+    pub fn update_wifi_bars_character(
+        &mut self,
+        config: &crate::read_config::Config,
+    ) -> Result<(), LcdError> {
+        let (Some(character_number), Some(percent)) = (
+            config.wifi_bars_character,
+            get_wifi_strength::get_wifi_signal_percent(),
+        ) else {
+            return Ok(()); // there is nothing to show, so get_wifi_signal_text shows the text instead
+        };
+        let bitmap = character_pattern::WIFI_SIGNAL_BARS
+            [get_wifi_strength::signal_bars_from_percent(percent)];
+        let Some(current_bitmap) = self.character_bitmaps.get_mut(character_number) else {
+            return Ok(()); // cannot happen, as config.validate checks the character number
+        };
+        if *current_bitmap == bitmap {
+            return Ok(());
+        }
+        *current_bitmap = bitmap; // so that the bars are kept if the screen is cleared
+        let rows_as_hex: String = bitmap.iter().map(|row| format!("{row:02x}")).collect();
+        write!(self.lcd_file, "\x1b[LG{character_number:01x}{rows_as_hex};").map_err(|err| {
+            LcdError::WriteFailed(format!("Failed to define the Wi-Fi bars character : {err}"))
+        })
     }
 
-    /// Clears the LCD screen, but not any associated text buffers
-    pub fn clear(&mut self) {
-        Self::clear_screen(&mut self.lcd_file);
+    /// writes all 4 lines of the LCD screen, extracting the data needed from status_of_rradio.
+    /// Counts the consecutive failures, so the caller can use needs_reopening to find out if the screen has gone away.
+    pub fn write_rradio_status_to_lcd(
+        &mut self,
+        status_of_rradio: &player_status::PlayerStatus,
+        config: &crate::read_config::Config,
+        command_runner: &impl CommandRunner,
+    ) -> Result<(), LcdError> {
+        let result = self
+            .update_wifi_bars_character(config)
+            .and_then(|()| self.write_status_lines(status_of_rradio, config, command_runner));
+        if result.is_ok() {
+            self.consecutive_write_failures = 0;
+        } else {
+            self.consecutive_write_failures = self.consecutive_write_failures.saturating_add(1);
+        }
+        result
     }
 
     /// writes all 4 lines of the LCD screen, extracting the data needed from status_of_rradio
-    pub fn write_rradio_status_to_lcd(
+    fn write_status_lines(
         &mut self,
         status_of_rradio: &player_status::PlayerStatus,
         config: &crate::read_config::Config,
-    ) {
+        command_runner: &impl CommandRunner,
+    ) -> Result<(), LcdError> {
         if let Some(toml_error) = status_of_rradio.toml_error.clone() {          
             let mut text_buffer = TextBuffer::new();
             text_buffer.write_text_to_lines(toml_error.bytes(), LineNum::Line1, 4);
-            self.write_text_buffer_to_lcd(&text_buffer);
+            self.write_text_buffer_to_lcd(&text_buffer)
         } else {
             let mut text_buffer = TextBuffer::new();
 
             match status_of_rradio.running_status {
                 RunningStatus::Startingup => {
-                    Lc::fill_text_buffer_when_starting(&mut text_buffer, status_of_rradio)
+                    Lc::fill_text_buffer_when_starting(&mut text_buffer, status_of_rradio, config)
                 }
                 RunningStatus::RunningNormally => Lc::fill_text_buffer_when_running_normally(
                     &mut text_buffer,
@@ -424,50 +671,122 @@ impl Lc {
                     config,
                 ),
                 RunningStatus::NoChannel => {
-                    Lc::fill_text_buffer_channel_not_found(&mut text_buffer, status_of_rradio)
-                }
-                RunningStatus::NoChannelRepeated => {
-                    Lc::fill_text_buffer_channel_not_found_twice(&mut text_buffer, status_of_rradio)
+                    Lc::fill_text_buffer_channel_not_found(&mut text_buffer, status_of_rradio, config)
                 }
+                RunningStatus::NoChannelRepeated => Lc::fill_text_buffer_channel_not_found_twice(
+                    &mut text_buffer,
+                    status_of_rradio,
+                    config,
+                    command_runner,
+                ),
                 RunningStatus::ShuttingDown => {
                     Lc::fill_text_buffer_when_shutting_down(&mut text_buffer)
                 }
                 RunningStatus::LongMessageOnAll4Lines => {
                     Lc::long_message(&mut text_buffer, status_of_rradio)
                 }
+                RunningStatus::Buffering => {
+                    Lc::fill_text_buffer_when_buffering(&mut text_buffer, status_of_rradio, config)
+                }
+                RunningStatus::Reconnecting => {
+                    Lc::fill_text_buffer_when_reconnecting(&mut text_buffer, status_of_rradio, config)
+                }
             };
 
+            let shift = Lc::get_burn_in_shift(status_of_rradio, config);
             for (line_number, line) in text_buffer // for each line
                 .buffer
                 .chunks(NUM_CHARACTERS_PER_LINE)
                 .enumerate()
             {
-                // move to the start of the specified line
-                if let Err(err) = write!(self.lcd_file, "\x1b[Lx0y{line_number};") {
-                    // move the cursor to the start of the specified line
-                    eprintln!(
-                        "In write_rradio_status_to_lcd, Failed to write move the cursor : {err}\r"
-                    );
-                    return;
-                }
+                // the text is written shift columns to the right, & what falls off the end of the line
+                // wraps round to the start of the line. Normally shift is zero.
+                let (start_of_line, end_of_line) = line.split_at(NUM_CHARACTERS_PER_LINE - shift);
+                // move to the start of the specified line (plus the shift)
+                write!(self.lcd_file, "\x1b[Lx{shift}y{line_number};").map_err(|err| {
+                    LcdError::WriteFailed(format!("Failed to move the cursor : {err}"))
+                })?;
                 // & then write the text
-                if let Err(err) = self.lcd_file.write_all(line) {
-                    eprintln!("In write_rradio_status_to_lcd, Failed to write text : {err}\r");
-                    return;
+                self.lcd_file.write_all(start_of_line).map_err(|err| {
+                    LcdError::WriteFailed(format!("Failed to write text : {err}"))
+                })?;
+                if !end_of_line.is_empty() {
+                    write!(self.lcd_file, "\x1b[Lx0y{line_number};").map_err(|err| {
+                        LcdError::WriteFailed(format!("Failed to move the cursor : {err}"))
+                    })?;
+                    self.lcd_file.write_all(end_of_line).map_err(|err| {
+                        LcdError::WriteFailed(format!("Failed to write text : {err}"))
+                    })?;
                 }
             }
+            Ok(())
+        }
+
+    }
+
+    /// Writes text_buffer's contents to the LCD without translation, starting at line 0; it does not scroll
+    pub fn write_text_buffer_to_lcd(&mut self, text_buffer: &TextBuffer) -> Result<(), LcdError> {
+        for (line_number, line) in text_buffer
+            .buffer
+            .chunks(NUM_CHARACTERS_PER_LINE)
+            .enumerate()
+        {
+            // move the cursor to the start of the specified line
+            write!(self.lcd_file, "\x1b[Lx0y{line_number};").map_err(|err| {
+                LcdError::WriteFailed(format!("Failed to move the cursor : {err}"))
+            })?;
+            self.lcd_file
+                .write_all(line)
+                .map_err(|err| LcdError::WriteFailed(format!("Failed to write text : {err}")))?;
         }
+        Ok(())
+    }
+}
 
+impl Lc {
+    /// Returns the number of columns the screen contents should be shifted right to prevent burn in.
+    /// This is only non-zero if config.burn_in_shift_minutes is specified & we have been starting up,
+    /// ie idle, for at least that many minutes; the shift increases by 1 column every burn_in_shift_minutes.
+    fn get_burn_in_shift(
+        status_of_rradio: &player_status::PlayerStatus,
+        config: &crate::read_config::Config,
+    ) -> usize {
+        match config.burn_in_shift_minutes {
+            Some(burn_in_shift_minutes)
+                if burn_in_shift_minutes > 0
+                    && status_of_rradio.running_status == RunningStatus::Startingup =>
+            {
+                let idle_minutes = (chrono::Utc::now()
+                    - status_of_rradio.time_started_playing_current_station)
+                    .num_minutes()
+                    .max(0) as u64;
+                ((idle_minutes / burn_in_shift_minutes) % MAXIMUM_BURN_IN_SHIFT) as usize
+            }
+            _ => 0,
+        }
     }
 
-    /// Fills the text buffer with the start up text before any channel has been selected
+    /// Fills the text buffer with the start up text before any channel has been selected;
+    /// line 1 is as specified by config.startup_line1
     pub fn fill_text_buffer_when_starting(
         text_buffer: &mut TextBuffer,
         status_of_rradio: &player_status::PlayerStatus,
+        config: &crate::read_config::Config,
     ) {
-        if status_of_rradio.network_data.is_valid {
-            text_buffer
-                .write_text_to_single_line(status_of_rradio.line_1_data.bytes(), LineNum::Line1);
+        match (&config.startup_line1, &status_of_rradio.network_data.state) {
+            (StartupLine1::IpAndVolume, NetworkState::Connected) => text_buffer
+                .write_text_to_single_line(status_of_rradio.line_1_data.bytes(), LineNum::Line1),
+            (StartupLine1::IpAndVolume, NetworkState::Connecting) => text_buffer
+                .write_text_to_single_line("Connecting to Wi-Fi".bytes(), LineNum::Line1),
+            (StartupLine1::IpAndVolume, NetworkState::NotConfigured) => text_buffer
+                .write_text_to_single_line("No Wi-Fi configured".bytes(), LineNum::Line1),
+            (StartupLine1::Hostname | StartupLine1::Custom(_), _) => text_buffer
+                .write_text_to_single_line(
+                    status_of_rradio.startup_line_1_data.bytes(),
+                    LineNum::Line1,
+                ),
+            (StartupLine1::CompileTime, _) => text_buffer
+                .write_text_to_single_line(compile_time::datetime_str!().bytes(), LineNum::Line1),
         }
 
         let ping_message = if status_of_rradio.ping_data.number_of_pings_to_this_channel > 1 {
@@ -479,12 +798,12 @@ impl Lc {
         text_buffer.write_text_to_single_line(ping_message.bytes(), LineNum::Line2);
 
         text_buffer.write_text_to_single_line(
-            Lc::get_current_date_and_time_text().bytes(),
+            Lc::get_current_date_and_time_text(config, status_of_rradio.clock_24h).bytes(),
             LineNum::Line3,
         );
 
         text_buffer.write_text_to_single_line(
-            Lc::get_temperature_and_wifi_strength_text().bytes(),
+            Lc::get_temperature_and_wifi_strength_text(config.wifi_bars_character).bytes(),
             LineNum::Line4,
         );
     }
@@ -498,7 +817,14 @@ impl Lc {
         // if playng a CD or a USB mem stick we have a position & a duration
         // if playing a stream we have a position but the duration is none
         // if the position is less than x seconds, we display the media type
-        let start_line1 = if status_of_rradio.position_and_duration[status_of_rradio.channel_number]
+        let start_line1 = if status_of_rradio.gstreamer_state == gstreamer::State::Paused {
+            // the position no longer changes, so we make it clear why
+            Lc::format_paused_position(
+                status_of_rradio.position_and_duration[status_of_rradio.channel_number]
+                    .position
+                    .seconds(),
+            )
+        } else if status_of_rradio.position_and_duration[status_of_rradio.channel_number]
             .position
             < config.time_initial_message_displayed_after_channel_change
         {
@@ -527,56 +853,38 @@ impl Lc {
                 .source_type
             {
                 SourceType::Cd | SourceType::Usb  => {
-                    let position_secs = status_of_rradio.position_and_duration
-                        [status_of_rradio.channel_number]
-                        .position
-                        .seconds();
                     if let Some(duration) = status_of_rradio.position_and_duration
                         [status_of_rradio.channel_number]
                         .duration
                     {
-                        let duration_secs = duration.seconds();
                         let track_index = status_of_rradio.position_and_duration
                             [status_of_rradio.channel_number]
                             .index_to_current_track
                             + 1; // humans count from 1
-                        let track_index_digit_count = if track_index < 10 { 1 } else { 2 };
-                        let position_secs_digit_count = match position_secs {
-                            0..=9 => 1,
-                            10..=99 => 2,
-                            100..=999 => 3,
-                            _ => 4,
-                        };
-
-                        let duration_secs_digit_count = match duration_secs {
-                            0..=9 => 1,
-                            10..=99 => 2,
-                            100..=999 => 3,
-                            _ => 4,
-                        };
-                        let number_of_digits = track_index_digit_count
-                            + position_secs_digit_count
-                            + duration_secs_digit_count;
-
-                        match number_of_digits {
-                            0..=7 => {
-                                format!("{track_index}: {position_secs} of {duration_secs}",)
-                            }
-                            8 => format!("{track_index}:{position_secs} of {duration_secs}",),
-                            9 => {
-                                format!("{track_index}:{position_secs}of {duration_secs}",)
-                            }
-                            10 => {
-                                format!("{track_index}: {position_secs}of{duration_secs}")
+                        let position_secs = status_of_rradio.position_and_duration
+                            [status_of_rradio.channel_number]
+                            .position
+                            .seconds();
+                        match status_of_rradio.time_display {
+                            crate::read_config::TimeDisplay::Elapsed => Lc::format_track_position(
+                                track_index,
+                                position_secs,
+                                duration.seconds(),
+                            ),
+                            crate::read_config::TimeDisplay::Remaining => {
+                                Lc::format_track_time_remaining(
+                                    track_index,
+                                    position_secs,
+                                    duration.seconds(),
+                                )
                             }
-                            _ => format!("{track_index}: {position_secs}"),
                         }
                     } else {
                         "source error".to_string()
                     }
                 }
 
-                SourceType::UrlList => {
+                SourceType::UrlList | SourceType::Playlist => {
                     if (status_of_rradio.ping_data.number_of_pings_to_this_channel
                         <= config.max_number_of_remote_pings)
                         || (status_of_rradio.ping_data.number_of_pings_to_this_channel & 1 != 0)
@@ -593,41 +901,74 @@ impl Lc {
             }
         };
 
+        // a brief message, such as one saying the user cannot seek, replaces the usual text for a few seconds
+        let start_line1 = match &status_of_rradio.line_1_brief_message {
+            Some((brief_message, time_set))
+                if (chrono::Utc::now() - *time_set).num_milliseconds()
+                    < BRIEF_MESSAGE_DISPLAY_TIME_MS =>
+            {
+                brief_message.clone()
+            }
+            // otherwise the user cannot tell why they cannot hear anything
+            _ if status_of_rradio.system_muted => "SYSTEM MUTED".to_string(),
+            _ => start_line1,
+        };
+
         text_buffer.write_text_to_buffer(start_line1.bytes(), 0, LINE1_DATA_CHAR_COUNT);
 
         text_buffer.write_text_to_buffer(
-            Lc::get_vol_string(status_of_rradio).bytes(),
+            Lc::get_vol_string(status_of_rradio, config).bytes(),
             LINE1_DATA_CHAR_COUNT,
             VOLUME_CHAR_COUNT,
         ); // line 1 is now written
 
-        text_buffer.write_text_to_lines(status_of_rradio.line_2_data.bytes(), LineNum::Line2, 1);
-        text_buffer.write_text_to_lines(status_of_rradio.line_34_data.bytes(), LineNum::Line3, 2);
-
-        if status_of_rradio.position_and_duration[status_of_rradio.channel_number]
-            .channel_data
-            .source_type
-            == get_channel_details::SourceType::UrlList
-        {
-            // output the buffer state as we are playing a stream
-            if status_of_rradio.line_34_data.lcd_encoded_text.bytes.len() <= NUM_CHARACTERS_PER_LINE {
-                let trimmed_buffer: u8 = (status_of_rradio.buffering_percent)
-                    .clamp(0, 99)
-                    .try_into()
-                    .unwrap(); // 0 to 100 is 101 values, & the screen only handles 100 values, so trim downwards
-                               // the unwrap cannot be called as the min value is 0 & the max is 99 which a U8 can handle
-
-                let column = usize::from(trimmed_buffer / 5);
+        if let Some(icon) = status_of_rradio.line_2_icon() {
+            // the icon takes the first cell, so the organisation starts one cell later
+            text_buffer.write_character_to_single_position(LineNum::Line2, 0, icon);
+            text_buffer.write_text_to_buffer(
+                status_of_rradio.line_2_data.bytes(),
+                LineNum::Line2.into_usize() * NUM_CHARACTERS_PER_LINE + 1,
+                NUM_CHARACTERS_PER_LINE - 1,
+            );
+        } else {
+            text_buffer.write_text_to_lines(status_of_rradio.line_2_data.bytes(), LineNum::Line2, 1);
+        }
 
-                let character: u8 = trimmed_buffer % 5;
+        // work out if line 4 (& line 3) are free to display the buffer bar or the time
+        let (line_3_is_empty, line_4_is_free) = if config.split_title_artist {
+            text_buffer
+                .write_text_to_single_line(status_of_rradio.line_3_data.bytes(), LineNum::Line3);
+            text_buffer
+                .write_text_to_single_line(status_of_rradio.line_4_data.bytes(), LineNum::Line4);
+            (
+                status_of_rradio.line_3_data.lcd_encoded_text.bytes.is_empty(),
+                status_of_rradio.line_4_data.lcd_encoded_text.bytes.is_empty(),
+            )
+        } else {
+            text_buffer.write_text_to_lines(status_of_rradio.line_34_data.bytes(), LineNum::Line3, 2);
+            (
+                status_of_rradio.line_34_data.lcd_encoded_text.bytes.is_empty(),
+                status_of_rradio.line_34_data.lcd_encoded_text.bytes.len() <= NUM_CHARACTERS_PER_LINE,
+            )
+        };
 
-                text_buffer
-                    .write_text_to_single_line("                    ".bytes(), LineNum::Line4);
-                text_buffer.write_character_to_single_position(LineNum::Line4, column, character);
+        if matches!(
+            status_of_rradio.position_and_duration[status_of_rradio.channel_number]
+                .channel_data
+                .source_type,
+            get_channel_details::SourceType::UrlList | get_channel_details::SourceType::Playlist
+        ) {
+            // output the buffer state as we are playing a stream
+            if line_4_is_free {
+                Lc::write_buffer_bar(
+                    text_buffer,
+                    status_of_rradio.buffering_percent,
+                    config.buffer_bar_style,
+                );
 
-                if status_of_rradio.line_34_data.lcd_encoded_text.bytes.is_empty() {
+                if line_3_is_empty {
                     text_buffer.write_text_to_single_line(
-                        Lc::get_current_date_and_time_text().bytes(),
+                        Lc::get_rotating_info_text(status_of_rradio, config).bytes(),
                         LineNum::Line3,
                     );
                 }
@@ -645,14 +986,44 @@ impl Lc {
             };
         }
         // it is pointless to output the buffer state for CD drives & USB sticks as it is always 100% or 0%
-        else if status_of_rradio.line_34_data.lcd_encoded_text.bytes.len() <= NUM_CHARACTERS_PER_LINE {
+        else if line_4_is_free {
             text_buffer.write_text_to_single_line(
-                Lc::get_current_date_and_time_text().bytes(),
+                Lc::get_rotating_info_text(status_of_rradio, config).bytes(),
                 LineNum::Line4,
             );
         }
     }
 
+    /// Writes the buffer state on line 4 as specified by buffer_bar_style; each of the 20 cells represents 5%,
+    /// & the user defined characters 0 to 4, which are vertical lines, show the remainder within the last cell
+    fn write_buffer_bar(
+        text_buffer: &mut TextBuffer,
+        buffering_percent: i32,
+        buffer_bar_style: BufferBarStyle,
+    ) {
+        let trimmed_buffer: u8 = buffering_percent
+            .clamp(0, 99)
+            .try_into()
+            .unwrap(); // 0 to 100 is 101 values, & the screen only handles 100 values, so trim downwards
+                       // the unwrap cannot be called as the min value is 0 & the max is 99 which a U8 can handle
+
+        let column = usize::from(trimmed_buffer / 5);
+
+        let character: u8 = trimmed_buffer % 5;
+
+        text_buffer.write_text_to_single_line("                    ".bytes(), LineNum::Line4);
+        if buffer_bar_style == BufferBarStyle::FullWidth {
+            for filled_column in 0..column {
+                text_buffer.write_character_to_single_position(
+                    LineNum::Line4,
+                    filled_column,
+                    FULL_BLOCK_CHARACTER,
+                );
+            }
+        }
+        text_buffer.write_character_to_single_position(LineNum::Line4, column, character);
+    }
+
     /// Fills the entire LCD screen with the long message stored in status_of_rradio.all_4lines
     /// & copies to stderr
     pub fn long_message(
@@ -666,6 +1037,60 @@ impl Lc {
         );
     }
 
+    /// Formats a number of seconds as minutes & seconds, eg 456 seconds becomes "7:36"
+    fn format_minutes_and_seconds(seconds: u64) -> String {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+
+    /// Formats the track number, position & duration so they fit in LINE1_DATA_CHAR_COUNT characters, eg "1: 2:03/7:36".
+    /// If that is too long, the duration is dropped; if that is still too long, only the position is shown.
+    fn format_track_position(track_index: usize, position_secs: u64, duration_secs: u64) -> String {
+        Lc::fit_track_time(
+            track_index,
+            Lc::format_minutes_and_seconds(position_secs),
+            duration_secs,
+        )
+    }
+
+    /// Formats the time left in the track in the same way as format_track_position, but with a minus sign, eg "1: -5:33/7:36"
+    fn format_track_time_remaining(
+        track_index: usize,
+        position_secs: u64,
+        duration_secs: u64,
+    ) -> String {
+        Lc::fit_track_time(
+            track_index,
+            format!(
+                "-{}",
+                Lc::format_minutes_and_seconds(duration_secs.saturating_sub(position_secs))
+            ),
+            duration_secs,
+        )
+    }
+
+    /// Lays out the track number, the time & the duration of the track so they fit in LINE1_DATA_CHAR_COUNT characters,
+    /// as format_track_position describes
+    fn fit_track_time(track_index: usize, time: String, duration_secs: u64) -> String {
+        let duration = Lc::format_minutes_and_seconds(duration_secs);
+        [
+            format!("{track_index}: {time}/{duration}"),
+            format!("{track_index}: {time}"),
+        ]
+        .into_iter()
+        .find(|layout| layout.len() <= LINE1_DATA_CHAR_COUNT)
+        .unwrap_or(time)
+    }
+
+    /// Formats the position at which playing was paused so it fits in LINE1_DATA_CHAR_COUNT characters, eg "Paused 2:03".
+    /// If that is too long, "Paused" is shortened to "P"; if that is still too long, only the position is shown.
+    fn format_paused_position(position_secs: u64) -> String {
+        let position = Lc::format_minutes_and_seconds(position_secs);
+        [format!("Paused {position}"), format!("P {position}")]
+            .into_iter()
+            .find(|layout| layout.len() <= LINE1_DATA_CHAR_COUNT)
+            .unwrap_or(position)
+    }
+
     /// formats the time so that it fits the LCD screen
     fn format_ping_time(
         ping_time_and_destination: &PingTimeAndDestination,
@@ -680,7 +1105,8 @@ impl Lc {
             match ping_time_in_ms {
                 f32::MIN..0.0 => "NegTime".to_string(),
 
-                0.0.. => {
+                // 99.95ms or more would be rounded up to "100.0", so is shown without a decimal place
+                0.0..99.95 => {
                     format!("{}{:.width$}ms", destination, ping_time_in_ms, width = 1)
                 }
                 _ => {
@@ -688,8 +1114,9 @@ impl Lc {
                 }
             }
         } else if long_string_wanted {
+            // the short string already says "Ping", eg "RemPing"
             format!(
-                "{}Ping NoReply",
+                "{} NoReply",
                 ping_time_and_destination.destination.to_short_string()
             )
         } else {
@@ -705,6 +1132,7 @@ impl Lc {
     pub fn fill_text_buffer_channel_not_found(
         text_buffer: &mut TextBuffer,
         status_of_rradio: &player_status::PlayerStatus,
+        config: &crate::read_config::Config,
     ) {
         text_buffer.write_text_to_buffer(
             format!("No station {}", status_of_rradio.channel_number).bytes(),
@@ -712,7 +1140,7 @@ impl Lc {
             LINE1_DATA_CHAR_COUNT,
         );
         text_buffer.write_text_to_buffer(
-            Lc::get_vol_string(status_of_rradio).bytes(),
+            Lc::get_vol_string(status_of_rradio, config).bytes(),
             LINE1_DATA_CHAR_COUNT,
             VOLUME_CHAR_COUNT,
         );
@@ -723,12 +1151,12 @@ impl Lc {
         );
 
         text_buffer.write_text_to_single_line(
-            Lc::get_current_date_and_time_text().bytes(),
+            Lc::get_current_date_and_time_text(config, status_of_rradio.clock_24h).bytes(),
             LineNum::Line3,
         );
 
         text_buffer.write_text_to_single_line(
-            Lc::get_temperature_and_wifi_strength_text().bytes(),
+            Lc::get_temperature_and_wifi_strength_text(config.wifi_bars_character).bytes(),
             LineNum::Line4,
         );
     }
@@ -738,6 +1166,8 @@ impl Lc {
     pub fn fill_text_buffer_channel_not_found_twice(
         text_buffer: &mut TextBuffer,
         status_of_rradio: &player_status::PlayerStatus,
+        config: &crate::read_config::Config,
+        command_runner: &impl CommandRunner,
     ) {
         let mut show_compile_time_and_ssid = false;
 
@@ -769,8 +1199,11 @@ impl Lc {
             }
         }
 
-        text_buffer
-            .write_text_to_single_line(Lc::get_throttled_status_and_time().bytes(), LineNum::Line3);
+        text_buffer.write_text_to_single_line(
+            Lc::get_throttled_status_and_time(config, status_of_rradio.clock_24h, command_runner)
+                .bytes(),
+            LineNum::Line3,
+        );
         text_buffer.write_text_to_single_line(
             //"\x00 \x01 \x02 \x03 \x04\x05\x06\x07ñäöü~ÆÇ",
             ScrollData::new("\x00 \x01 \x02 \x03 \x04\x05\x06\x07ñäöüÆÇç", 1).bytes(),
@@ -779,20 +1212,91 @@ impl Lc {
     }
 
     /// Gets the throttled status & time; if the Pi is not throttled it returns "NotThrottled" followed by the time of day,
-    /// otherwise it returns the throttled code followed by time of day
-    pub fn get_throttled_status_and_time() -> String {
-        let throttled_status = get_throttled::is_throttled();
+    /// otherwise it returns the throttled code followed by time of day.
+    /// The 12 hour clock omits the seconds, so that it fits on one line
+    pub fn get_throttled_status_and_time(
+        config: &crate::read_config::Config,
+        clock_24h: bool,
+        command_runner: &impl CommandRunner,
+    ) -> String {
+        let time_format = if clock_24h { "%H:%M:%S" } else { "%I:%M%p" };
+        let throttled_status = get_throttled::is_throttled(command_runner);
         if !throttled_status.pi_is_throttled {
-            format!("NotThrottled{}", chrono::Local::now().format("%H:%M:%S"))
+            format!("NotThrottled{}", config.clock_now().format(time_format))
         } else {
             format!(
                 "{}{} ",
                 throttled_status.result,
-                chrono::Local::now().format("%H:%M:%S")
+                config.clock_now().format(time_format)
             )
         }
     }
 
+    /// Fills the text buffer with test pattern number pattern_number (0 to NUMBER_OF_TEST_PATTERNS - 1), used to check a new screen.
+    /// Patterns 0 to 2 contain incrementing byte values from 0x20 upwards, pattern 3 contains the 8 custom characters
+    /// defined by character_pattern::BITMAPS repeated across the screen & pattern 4 is a checkerboard.
+    pub fn fill_text_buffer_with_test_pattern(text_buffer: &mut TextBuffer, pattern_number: usize) {
+        const FIRST_PRINTABLE_BYTE: usize = 0x20;
+        for (position, cell) in text_buffer.buffer.iter_mut().enumerate() {
+            *cell = match pattern_number {
+                0..=2 => {
+                    ((FIRST_PRINTABLE_BYTE + pattern_number * NUM_CHARACTERS_PER_SCREEN + position) % 0x100)
+                        as u8
+                }
+                3 => (position % character_pattern::BITMAPS.len()) as u8,
+                _ => {
+                    let (row, column) = (position / NUM_CHARACTERS_PER_LINE, position % NUM_CHARACTERS_PER_LINE);
+                    if (row + column) % 2 == 0 { FULL_BLOCK_CHARACTER } else { b' ' }
+                }
+            };
+        }
+    }
+
+    /// Fills the supplied text buffer with the percentage the buffer is full, the organisation (which scrolls if it is long),
+    /// the channel number & a progress bar, drawn as set by config.buffer_bar_style, showing how full the buffer is
+    pub fn fill_text_buffer_when_buffering(
+        text_buffer: &mut TextBuffer,
+        status_of_rradio: &player_status::PlayerStatus,
+        config: &crate::read_config::Config,
+    ) {
+        let buffering_percent = status_of_rradio.buffering_percent.clamp(0, 100);
+        text_buffer.write_text_to_single_line(
+            format!("Buffering {buffering_percent:>3}%").bytes(),
+            LineNum::Line1,
+        );
+        text_buffer.write_text_to_single_line(status_of_rradio.line_2_data.bytes(), LineNum::Line2);
+        text_buffer.write_text_to_single_line(
+            format!("for channel {}", status_of_rradio.channel_number).bytes(),
+            LineNum::Line3,
+        );
+        Lc::write_buffer_bar(text_buffer, buffering_percent, config.buffer_bar_style);
+    }
+
+    /// Fills the text buffer when a stream that dropped out is being reconnected,
+    /// showing the attempt number out of config.max_stream_reconnect_attempts & the organisation
+    pub fn fill_text_buffer_when_reconnecting(
+        text_buffer: &mut TextBuffer,
+        status_of_rradio: &player_status::PlayerStatus,
+        config: &crate::read_config::Config,
+    ) {
+        let stream_reconnect_attempts = status_of_rradio.position_and_duration
+            [status_of_rradio.channel_number]
+            .stream_reconnect_attempts;
+        text_buffer.write_text_to_single_line(
+            format!(
+                "Reconnecting {stream_reconnect_attempts}/{}",
+                config.max_stream_reconnect_attempts
+            )
+            .bytes(),
+            LineNum::Line1,
+        );
+        text_buffer.write_text_to_single_line(status_of_rradio.line_2_data.bytes(), LineNum::Line2);
+        text_buffer.write_text_to_single_line(
+            format!("for channel {}", status_of_rradio.channel_number).bytes(),
+            LineNum::Line3,
+        );
+    }
+
     /// Fills the supplied text buffer with text to say that the program is shutting down
     pub fn fill_text_buffer_when_shutting_down(text_buffer: &mut TextBuffer) {
         text_buffer.write_text_to_single_line("Ending screen driver".bytes(), LineNum::Line1);
@@ -800,22 +1304,33 @@ impl Lc {
         text_buffer.write_text_to_single_line("down".bytes(), LineNum::Line4);
     }
 
-    /// returns the volume as a String if playing, if not the gstreamer state as a String
-    pub fn get_vol_string(status_of_rradio: &player_status::PlayerStatus) -> String {
+    /// returns the volume as a String if playing, if not the gstreamer state as a String.
+    /// The volume is shown in dB or as a percentage as specified by config.volume_display
+    /// & always fits in VOLUME_CHAR_COUNT characters
+    pub fn get_vol_string(
+        status_of_rradio: &player_status::PlayerStatus,
+        config: &crate::read_config::Config,
+    ) -> String {
         match status_of_rradio.gstreamer_state {
-            gstreamer::State::Playing | gstreamer::State::Null => {
-                let number_of_digits = match status_of_rradio.current_volume {
-                    99.. => 4,
-                    9.. => 3,
-                    _ => 2,
-                };
+            gstreamer::State::Playing | gstreamer::State::Null => match config.volume_display {
+                crate::read_config::VolumeDisplay::Db => {
+                    let number_of_digits = match status_of_rradio.current_volume {
+                        99.. => 4,
+                        9.. => 3,
+                        _ => 2,
+                    };
 
-                format!(
-                    "Vol{:>Width$.Width$}",
-                    status_of_rradio.current_volume,
-                    Width = number_of_digits
-                )
-            }
+                    format!(
+                        "Vol{:>Width$.Width$}",
+                        status_of_rradio.current_volume,
+                        Width = number_of_digits
+                    )
+                }
+                crate::read_config::VolumeDisplay::Percent => {
+                    // "Vol" + 3 digits + "%" is exactly VOLUME_CHAR_COUNT characters
+                    format!("Vol{:>3}%", Lc::volume_as_percent(status_of_rradio.current_volume))
+                }
+            },
             //} else {
             _ => {
                 match status_of_rradio.gstreamer_state {
@@ -829,36 +1344,57 @@ impl Lc {
         }
     }
 
-    /// gets the current date & time
-    pub fn get_current_date_and_time_text() -> String {
-        chrono::Local::now().format("%d %b %y %H:%M:%S").to_string()
+    /// Maps a volume in the range VOLUME_MIN to VOLUME_MAX to the range 0 to 100
+    fn volume_as_percent(volume: i32) -> i32 {
+        (volume.clamp(VOLUME_MIN, VOLUME_MAX) - VOLUME_MIN) * 100 / (VOLUME_MAX - VOLUME_MIN)
+    }
+
+    /// gets the current date & time, in config.timezone if it is specified, using the 24 hour clock if clock_24h is true.
+    /// With the 12 hour clock there is no space before AM or PM, so it still fits on one line
+    pub fn get_current_date_and_time_text(
+        config: &crate::read_config::Config,
+        clock_24h: bool,
+    ) -> String {
+        let format = if clock_24h {
+            "%d %b %y %H:%M:%S"
+        } else {
+            "%d %b %y %I:%M:%S%p"
+        };
+        config.clock_now().format(format).to_string()
+    }
+
+    /// Returns the date & time, CPU temperature, Wi-Fi signal strength or local IP address,
+    /// as selected by status_of_rradio.info_rotation_index
+    pub fn get_rotating_info_text(
+        status_of_rradio: &player_status::PlayerStatus,
+        config: &crate::read_config::Config,
+    ) -> String {
+        match status_of_rradio.info_rotation_index % NUMBER_OF_INFO_ITEMS {
+            1 => format!("CPU Temp {}C", get_temperature::get_cpu_temperature()),
+            2 => format!("WiFi {}", Lc::get_wifi_signal_text(config.wifi_bars_character)),
+            3 => format!("IP {}", status_of_rradio.network_data.local_ip_address),
+            _ => Lc::get_current_date_and_time_text(config, status_of_rradio.clock_24h),
+        }
     }
 
     /// Returns the temperature of the CPU followed by Wi-Fi signal strength.
-    pub fn get_temperature_and_wifi_strength_text() -> String {
+    /// If wifi_bars_character is specified, the signal strength is shown as that character, which shows the bars.
+    pub fn get_temperature_and_wifi_strength_text(wifi_bars_character: Option<usize>) -> String {
         format!(
             "CPU Temp {}C WiFi{}",
             get_temperature::get_cpu_temperature(),
-            get_wifi_strength::get_wifi_signal_strength()
+            Lc::get_wifi_signal_text(wifi_bars_character)
         )
     }
 
-    /// Writes text_buffer's contents to the LCD without translation, starting at line 0; it does not scroll
-    pub fn write_text_buffer_to_lcd(&mut self, text_buffer: &TextBuffer) {
-        for (line_number, line) in text_buffer
-            .buffer
-            .chunks(NUM_CHARACTERS_PER_LINE)
-            .enumerate()
-        {
-            if let Err(err) = write!(self.lcd_file, "\x1b[Lx0y{line_number};") {
-                // move the cursor to the start of the specified line
-                println!("in write_text_buffer, Failed to write move the cursor : {err}");
-                return;
-            }
-            if let Err(err) = self.lcd_file.write_all(line) {
-                println!("in write_text_buffer, Failed to write text : {err}");
-                return;
+    /// Returns the Wi-Fi signal strength in dB, or, if wifi_bars_character is specified & the strength can be read,
+    /// that user defined character, which update_wifi_bars_character defines to show the bars
+    fn get_wifi_signal_text(wifi_bars_character: Option<usize>) -> String {
+        match (wifi_bars_character, get_wifi_strength::get_wifi_signal_percent()) {
+            (Some(wifi_bars_character), Some(_percent)) => {
+                char::from(wifi_bars_character as u8).to_string()
             }
+            _ => get_wifi_strength::get_wifi_signal_strength(),
         }
     }
 }
@@ -872,6 +1408,7 @@ impl Lc {
 "\x1b[LL" will shift the display left. "\x1b[LR" will shift it right.
 "\x1b[Ll" will shift the cursor left. "\x1b[Lr" will shift it right.
 "\x1b[Lk" will erase the rest of the line.
+"\x1b[L+" will turn the backlight on, "\x1b[L-" will turn it off (used by set_backlight).
 "\x1b[LI" will initialise the display.
 "\x1b[Lx001y001;" will move the cursor to character 001 of line 001.
     Use any other numbers for different positions. You can also use "\001;" and "\x1b[Ly001;" on their own.
@@ -880,3 +1417,347 @@ impl Lc {
         The first "0" is the character number to define (0-7) and the next 16 characters are hex values for the 8 bytes to define.
 
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::MockCommandRunner;
+    use crate::ping::PingWhere;
+
+    /// The bytes that write_rradio_status_to_lcd should send for the given 4 lines, each padded to the width of the screen
+    fn expected_output(lines: [&str; 4]) -> Vec<u8> {
+        let mut expected = Vec::new();
+        for (line_number, line) in lines.iter().enumerate() {
+            expected.extend_from_slice(format!("\x1b[Lx0y{line_number};").as_bytes());
+            expected.extend_from_slice(format!("{line:<width$}", width = NUM_CHARACTERS_PER_LINE).as_bytes());
+        }
+        expected
+    }
+
+    fn render(status_of_rradio: &player_status::PlayerStatus) -> Vec<u8> {
+        let mut lcd = Lc::from_writer(Vec::new());
+        lcd.write_rradio_status_to_lcd(
+            status_of_rradio,
+            &crate::read_config::Config::default(),
+            &MockCommandRunner::new(""),
+        )
+        .expect("writing to a Vec cannot fail");
+        lcd.lcd_file
+    }
+
+    #[test]
+    fn ascii_is_unchanged() {
+        assert_eq!(encode_to_lcd_bytes("Radio 4 {FM}"), b"Radio 4 {FM}".to_vec());
+    }
+
+    #[test]
+    fn special_characters_use_the_lcd_codes() {
+        assert_eq!(encode_to_lcd_bytes("éèà"), vec![5, 6, 7]);
+        assert_eq!(encode_to_lcd_bytes("äñöü"), vec![0xE1, 0xEE, 0xEF, 0xF5]);
+        assert_eq!(encode_to_lcd_bytes("πµ"), vec![0xE4, 0xF7]);
+        assert_eq!(encode_to_lcd_bytes("<~>"), vec![b'<', 0xF3, b'>']);
+    }
+
+    #[test]
+    fn new_lines_are_replaced() {
+        assert_eq!(encode_to_lcd_bytes("a\nb\rc"), vec![b'a', 0xCD, b'b', 0xCF, b'c']);
+    }
+
+    #[test]
+    fn other_characters_are_transliterated() {
+        assert_eq!(encode_to_lcd_bytes("Straße"), b"Strasse".to_vec());
+        assert_eq!(encode_to_lcd_bytes("Ça"), b"Ca".to_vec());
+    }
+
+    #[test]
+    fn invisible_characters_take_no_cells() {
+        assert_eq!(encode_to_lcd_bytes("Cafe\u{301}"), b"Cafe".to_vec());
+        assert_eq!(encode_to_lcd_bytes("a\u{200D}b"), b"ab".to_vec());
+    }
+
+    #[test]
+    fn multi_cell_transliterations_are_predictable() {
+        assert_eq!(encode_to_lcd_bytes("©"), b"(c)".to_vec());
+        assert_eq!(encode_to_lcd_bytes("5€"), b"5EUR".to_vec());
+        for one_char in ['©', '€', '™', '½', '中', '😀'] {
+            let encoded = encode_to_lcd_bytes(one_char.to_string().as_str());
+            assert!(
+                (1..=MAXIMUM_CELLS_PER_CHARACTER).contains(&encoded.len()),
+                "{one_char} became {encoded:?}"
+            );
+            assert!(encoded.iter().all(|byte| (b' '..=b'~').contains(byte)));
+            assert_ne!(encoded.last(), Some(&b' '), "{one_char} became {encoded:?}");
+        }
+    }
+
+    #[test]
+    fn minutes_and_seconds_boundaries() {
+        assert_eq!(Lc::format_minutes_and_seconds(0), "0:00");
+        assert_eq!(Lc::format_minutes_and_seconds(59), "0:59");
+        assert_eq!(Lc::format_minutes_and_seconds(60), "1:00");
+        assert_eq!(Lc::format_minutes_and_seconds(600), "10:00");
+        assert_eq!(Lc::format_minutes_and_seconds(3600), "60:00");
+    }
+
+    #[test]
+    fn track_position_fits_line_1() {
+        assert_eq!(Lc::format_track_position(1, 123, 456), "1: 2:03/7:36");
+        assert_eq!(Lc::format_track_position(12, 723, 2856), "12: 12:03");
+        assert_eq!(Lc::format_track_position(12, 6000, 7200), "12: 100:00");
+        assert_eq!(Lc::format_track_position(123, 6000000, 7200000), "100000:00");
+    }
+
+    #[test]
+    fn remaining_time_counts_down_to_zero() {
+        assert_eq!(Lc::format_track_time_remaining(1, 123, 456), "1: -5:33/7:36");
+        assert_eq!(Lc::format_track_time_remaining(3, 0, 2856), "3: -47:36");
+        // the position can overshoot the duration
+        assert_eq!(Lc::format_track_time_remaining(1, 500, 456), "1: -0:00/7:36");
+        assert_eq!(Lc::format_track_time_remaining(12, 0, 720000), "12: -12000:00");
+    }
+
+    #[test]
+    fn paused_position_fits_line_1() {
+        assert_eq!(Lc::format_paused_position(123), "Paused 2:03");
+        assert_eq!(Lc::format_paused_position(6000), "Paused 100:00");
+        assert_eq!(Lc::format_paused_position(60000), "P 1000:00");
+    }
+
+    #[test]
+    fn shutting_down() {
+        let config = crate::read_config::Config::default();
+        let mut status_of_rradio = player_status::PlayerStatus::new(&config);
+        status_of_rradio.running_status = RunningStatus::ShuttingDown;
+
+        assert_eq!(
+            render(&status_of_rradio),
+            expected_output(["Ending screen driver", "", "Computer not shut", "down"])
+        );
+    }
+
+    #[test]
+    fn volume_can_be_shown_as_a_percentage() {
+        let mut config = crate::read_config::Config::default();
+        config.volume_display = crate::read_config::VolumeDisplay::Percent;
+        let mut status_of_rradio = player_status::PlayerStatus::new(&config);
+        for (volume, expected) in [
+            (VOLUME_MIN, "Vol  0%"),
+            (VOLUME_MAX / 2, "Vol 50%"),
+            (VOLUME_MAX, "Vol100%"),
+        ] {
+            status_of_rradio.current_volume = volume;
+            let vol_string = Lc::get_vol_string(&status_of_rradio, &config);
+            assert_eq!(vol_string, expected);
+            assert_eq!(vol_string.len(), VOLUME_CHAR_COUNT);
+        }
+    }
+
+    #[test]
+    fn buffering_shows_a_progress_bar() {
+        let config = crate::read_config::Config::default();
+        let mut status_of_rradio = player_status::PlayerStatus::new(&config);
+        status_of_rradio.running_status = RunningStatus::Buffering;
+        status_of_rradio.buffering_percent = 50;
+        status_of_rradio.channel_number = 5;
+        status_of_rradio.line_2_data = ScrollData::new("Tradcan", 1);
+
+        let expected: Vec<u8> =
+            expected_output(["Buffering  50%", "Tradcan", "for channel 5", "##########"])
+                .into_iter()
+                .map(|byte| if byte == b'#' { FULL_BLOCK_CHARACTER } else { byte })
+                .collect();
+        assert_eq!(render(&status_of_rradio), expected);
+    }
+
+    #[test]
+    fn reconnecting_shows_the_attempt_number() {
+        let config = crate::read_config::Config::default();
+        let mut status_of_rradio = player_status::PlayerStatus::new(&config);
+        status_of_rradio.running_status = RunningStatus::Reconnecting;
+        status_of_rradio.channel_number = 5;
+        status_of_rradio.position_and_duration[5].stream_reconnect_attempts = 2;
+        status_of_rradio.line_2_data = ScrollData::new("Tradcan", 1);
+
+        assert_eq!(
+            render(&status_of_rradio),
+            expected_output(["Reconnecting 2/3", "Tradcan", "for channel 5", ""])
+        );
+    }
+
+    #[test]
+    fn date_and_time_fit_on_a_line_with_either_clock() {
+        let config = crate::read_config::Config::default();
+        let date_and_time_24h = Lc::get_current_date_and_time_text(&config, true);
+        let date_and_time_12h = Lc::get_current_date_and_time_text(&config, false);
+        assert_eq!(date_and_time_24h.len(), 18, "{date_and_time_24h}");
+        assert_eq!(date_and_time_12h.len(), NUM_CHARACTERS_PER_LINE, "{date_and_time_12h}");
+        assert!(
+            date_and_time_12h.ends_with("AM") || date_and_time_12h.ends_with("PM"),
+            "{date_and_time_12h}"
+        );
+    }
+
+    /// Returns the ping time formatted both as the long & the short string
+    fn format_ping_time_both_ways(
+        time_in_ms: Option<f32>,
+        destination: PingWhere,
+    ) -> (String, String) {
+        let ping_time_and_destination = PingTimeAndDestination {
+            time_in_ms,
+            destination,
+        };
+        (
+            Lc::format_ping_time(&ping_time_and_destination, true),
+            Lc::format_ping_time(&ping_time_and_destination, false),
+        )
+    }
+
+    #[test]
+    fn ping_times_are_formatted_at_the_boundaries() {
+        for (time_in_ms, long_string, short_string) in [
+            (-1.0, "NegTime", "NegTime"),
+            (0.0, "Remote Ping 0.0ms", "RemPing0.0ms"),
+            (99.94, "Remote Ping 99.9ms", "RemPing99.9ms"),
+            (99.99, "Remote Ping 100ms", "RemPing100ms"), // rounded up, so shown like 100
+            (100.0, "Remote Ping 100ms", "RemPing100ms"),
+            (1234.4, "Remote Ping 1234ms", "RemPing1234ms"),
+        ] {
+            assert_eq!(
+                format_ping_time_both_ways(Some(time_in_ms), PingWhere::Remote),
+                (long_string.to_string(), short_string.to_string()),
+                "for {time_in_ms}ms"
+            );
+        }
+        assert_eq!(
+            format_ping_time_both_ways(Some(12.34), PingWhere::Local),
+            ("Local ping 12.3ms".to_string(), "LocPing12.3ms".to_string())
+        );
+    }
+
+    #[test]
+    fn ping_with_no_reply_fits_on_a_line() {
+        assert_eq!(
+            format_ping_time_both_ways(None, PingWhere::Remote),
+            ("RemPing NoReply".to_string(), "RPing Noreply".to_string())
+        );
+        assert_eq!(
+            format_ping_time_both_ways(None, PingWhere::Nothing),
+            ("No dest NoReply".to_string(), "NPing Noreply".to_string())
+        );
+        for destination in [PingWhere::Local, PingWhere::Remote, PingWhere::Nothing] {
+            let (long_string, _short_string) =
+                format_ping_time_both_ways(Some(9999.0), destination);
+            assert!(long_string.len() <= NUM_CHARACTERS_PER_LINE, "{long_string}");
+        }
+    }
+
+    #[test]
+    fn full_width_buffer_bar_fills_the_cells_before_the_remainder() {
+        let mut text_buffer = TextBuffer::new();
+        Lc::write_buffer_bar(&mut text_buffer, 52, BufferBarStyle::FullWidth);
+        let line_4 = &text_buffer.buffer[NUM_CHARACTERS_PER_LINE * 3..];
+        assert!(line_4[..10].iter().all(|&cell| cell == FULL_BLOCK_CHARACTER));
+        assert_eq!(line_4[10], 2); // the vertical line 2 pixels in shows the remaining 2%
+        assert!(line_4[11..].iter().all(|&cell| cell == b' '));
+
+        let mut text_buffer = TextBuffer::new();
+        Lc::write_buffer_bar(&mut text_buffer, 52, BufferBarStyle::Compact);
+        let line_4 = &text_buffer.buffer[NUM_CHARACTERS_PER_LINE * 3..];
+        assert!(line_4[..10].iter().all(|&cell| cell == b' '));
+        assert_eq!(line_4[10], 2);
+    }
+
+    #[test]
+    fn long_message_on_all_4_lines() {
+        let config = crate::read_config::Config::default();
+        let mut status_of_rradio = player_status::PlayerStatus::new(&config);
+        status_of_rradio.running_status = RunningStatus::LongMessageOnAll4Lines;
+        status_of_rradio.all_4lines = ScrollData::new("Channel 42 does not have a file", 4);
+
+        assert_eq!(
+            render(&status_of_rradio),
+            expected_output(["Channel 42 does not ", "have a file", "", ""])
+        );
+    }
+
+    #[test]
+    fn toml_error_overrides_the_running_status() {
+        let config = crate::read_config::Config::default();
+        let mut status_of_rradio = player_status::PlayerStatus::new(&config);
+        status_of_rradio.running_status = RunningStatus::ShuttingDown;
+        status_of_rradio.toml_error = Some("bad TOML".to_string());
+
+        assert_eq!(
+            render(&status_of_rradio),
+            expected_output(["bad TOML", "", "", ""])
+        );
+    }
+
+    #[test]
+    fn burn_in_shift_when_idle() {
+        let config = crate::read_config::Config {
+            burn_in_shift_minutes: Some(1),
+            ..Default::default()
+        };
+        let mut status_of_rradio = player_status::PlayerStatus::new(&config);
+        status_of_rradio.running_status = RunningStatus::Startingup;
+        status_of_rradio.time_started_playing_current_station =
+            chrono::Utc::now() - chrono::Duration::seconds(150); // so we shift 2 columns
+
+        let mut lcd = Lc::from_writer(Vec::new());
+        let mut text_buffer = TextBuffer::new();
+        Lc::fill_text_buffer_when_starting(&mut text_buffer, &status_of_rradio, &config);
+        lcd.write_rradio_status_to_lcd(&status_of_rradio, &config, &MockCommandRunner::new(""))
+            .expect("writing to a Vec cannot fail");
+
+        let mut expected = Vec::new();
+        for (line_number, line) in text_buffer.buffer.chunks(NUM_CHARACTERS_PER_LINE).enumerate() {
+            expected.extend_from_slice(format!("\x1b[Lx2y{line_number};").as_bytes());
+            expected.extend_from_slice(&line[..NUM_CHARACTERS_PER_LINE - 2]);
+            expected.extend_from_slice(format!("\x1b[Lx0y{line_number};").as_bytes());
+            expected.extend_from_slice(&line[NUM_CHARACTERS_PER_LINE - 2..]);
+        }
+        assert_eq!(lcd.lcd_file, expected);
+    }
+
+    #[test]
+    fn backlight_escape_sequences() {
+        let mut lcd = Lc::from_writer(Vec::new());
+        lcd.set_backlight(false).expect("writing to a Vec cannot fail");
+        lcd.set_backlight(true).expect("writing to a Vec cannot fail");
+        assert_eq!(lcd.lcd_file, b"\x1b[L-\x1b[L+");
+    }
+
+    /// A writer that always fails, as /dev/lcd does once the screen has been unplugged
+    struct UnpluggedScreen;
+
+    impl std::io::Write for UnpluggedScreen {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::from_raw_os_error(19)) // ENODEV
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn repeated_write_failures_ask_for_a_reopen() {
+        let config = crate::read_config::Config::default();
+        let status_of_rradio = player_status::PlayerStatus::new(&config);
+        let mut lcd = Lc::from_writer(UnpluggedScreen);
+        let command_runner = MockCommandRunner::new("");
+
+        for _ in 1..WRITE_FAILURES_BEFORE_REOPENING {
+            assert!(
+                lcd.write_rradio_status_to_lcd(&status_of_rradio, &config, &command_runner)
+                    .is_err()
+            );
+            assert!(!lcd.needs_reopening());
+        }
+        assert!(
+            lcd.write_rradio_status_to_lcd(&status_of_rradio, &config, &command_runner).is_err()
+        );
+        assert!(lcd.needs_reopening());
+    }
+}
@@ -81,3 +81,58 @@ pub const BITMAPS: [[u8; 8]; 8] = [
         0b00000,
     ],
 ];
+
+/// The bitmaps showing the Wi-Fi signal as 0 to 4 bars of increasing height in a single cell, indexed by the number of bars;
+/// the bottom row is always on so that no bars can be told apart from an empty cell
+pub const WIFI_SIGNAL_BARS: [[u8; 8]; 5] = [
+    [
+        0b00000, // 0 bars
+        0b00000, //
+        0b00000, //
+        0b00000, //
+        0b00000, //
+        0b00000, //
+        0b00000, //
+        0b01111,
+    ],
+    [
+        0b00000, // 1 bar
+        0b00000, //
+        0b00000, //
+        0b00000, //
+        0b00000, //
+        0b00000, //
+        0b01000, //
+        0b01111,
+    ],
+    [
+        0b00000, // 2 bars
+        0b00000, //
+        0b00000, //
+        0b00000, //
+        0b00100, //
+        0b00100, //
+        0b01100, //
+        0b01111,
+    ],
+    [
+        0b00000, // 3 bars
+        0b00000, //
+        0b00010, //
+        0b00010, //
+        0b00110, //
+        0b00110, //
+        0b01110, //
+        0b01111,
+    ],
+    [
+        0b00001, // 4 bars
+        0b00001, //
+        0b00011, //
+        0b00011, //
+        0b00111, //
+        0b00111, //
+        0b01111, //
+        0b01111,
+    ],
+];
@@ -2,6 +2,29 @@ use core::fmt;
 
 use rppal::gpio::Gpio;
 
+use crate::command_runner::CommandRunner;
+
+/// GPIO 22 controls whether or not the DigiAMP+ amplifier is muted, so nothing else can use it
+pub const MUTE_PORT: u8 = 22;
+
+/// The longest we wait for amixer, so that a hung sound system cannot stop the screen & the keyboard working
+const AMIXER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Set once a GPIO error has been output, so that the error is not output every time the mute pin is used,
+/// eg every SYSTEM_MUTE_CHECK_INTERVAL on a PC, which has no GPIO pins
+static GPIO_ERROR_REPORTED: std::sync::atomic::AtomicBool =
+    std::sync::atomic::AtomicBool::new(false);
+
+/// Outputs the GPIO error using eprintln!, unless a GPIO error has already been output
+fn report_gpio_error_once(error_message: String) {
+    if !GPIO_ERROR_REPORTED.swap(true, std::sync::atomic::Ordering::Relaxed) {
+        eprintln!("{error_message}\r");
+    }
+}
+
+/// The time between checks of whether the sound has been muted outside rradio's control
+pub const SYSTEM_MUTE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
 /// Specifies the state of the DigiAMP+ amplfier
 #[derive(PartialEq)]
 pub enum MuteState {
@@ -24,10 +47,11 @@ impl fmt::Display for MuteState {
 
 /// Gets the mute state of the DigiAMP+ amplfier if there is one, by reading GPIO pin 22
 pub fn get_mute_state() -> MuteState {
+    if cfg!(feature = "mock-lcd") {
+        return MuteState::NoAmplifier; // a PC has no GPIO pins, so there cannot be an amplifier
+    }
     // this command sets the port low  raspi-gpio set 22 op dl
     // this command sets the port high raspi-gpio set 22 op dh
-    /// GPIO 22 controls whether or not the DigiAMP+ amplifier is muted
-    const MUTE_PORT: u8 = 22;
     let all_gpios_and_errors = Gpio::new();
     match all_gpios_and_errors {
         Ok(gpios) => match gpios.get(MUTE_PORT) {
@@ -35,35 +59,37 @@ pub fn get_mute_state() -> MuteState {
                 // as it is Ok, we got GPIO 22; but is it in use to control the DigiAMP+ amplifier
                 if pin22.mode() == rppal::gpio::Mode::Input {
                     MuteState::NoAmplifier // there is no DigiAMP+ amplifier, or at least the kernal has not changed the pin to be an output pin
-                } else if pin22.into_input().is_low() {
+                } else if pin22.read() == rppal::gpio::Level::Low {
+                    // read the level without making it an input, which would stop it driving the amplifier's mute
                     MuteState::Muted // & the light on the amplifier board is on
                 } else {
                     MuteState::NotMuted // and the light is off
                 }
             }
             Err(pin22_err) => {
-                eprintln!("Got error {} when trying to get mute pin", pin22_err);
+                report_gpio_error_once(format!(
+                    "Got error {pin22_err} when trying to get mute pin"
+                ));
                 MuteState::ErrorFound
             }
         },
         Err(err_message) => {
-            eprintln!(
-                "When trying to get a GPIO pin got the error {}",
-                err_message
-            );
+            report_gpio_error_once(format!(
+                "When trying to get a GPIO pin got the error {err_message}"
+            ));
             MuteState::ErrorFound
         }
     }
 }
 
 /// Sets the mute state of the DigiAMP+ amplfier if there is one, by reading GPIO pin 22
-/// if there is an error outputs a message using eprintln!
+/// if there is an error outputs a message using eprintln!, but only for the first GPIO error
 pub fn set_mute_state(mute_state: gstreamer::State) {
+    if cfg!(feature = "mock-lcd") {
+        return; // a PC has no GPIO pins, so there is no amplifier to mute
+    }
     // this command sets the port low  raspi-gpio set 22 op dl
     // this command sets the port high raspi-gpio set 22 op dh
-    // GPIO 22 controls whether or not the DigiAMP+ amplifier is muted
-    //
-    const MUTE_PORT: u8 = 22;
     let all_gpios_and_errors = Gpio::new();
     match all_gpios_and_errors {
         Ok(gpios) => match gpios.get(MUTE_PORT) {
@@ -76,14 +102,95 @@ pub fn set_mute_state(mute_state: gstreamer::State) {
                 }
             }
             Err(pin22_err) => {
-                eprintln!("Got error {} when trying to set mute pin", pin22_err,);
+                report_gpio_error_once(format!(
+                    "Got error {pin22_err} when trying to set mute pin"
+                ));
             }
         },
         Err(err_message) => {
-            eprintln!(
-                "When trying to get a GPIO pin got the error {}",
-                err_message
-            );
+            report_gpio_error_once(format!(
+                "When trying to get a GPIO pin got the error {err_message}"
+            ));
+        }
+    }
+}
+
+/// Gets the mute state of the ALSA mixer control, eg "Master", by running amixer; it is muted if any of its channels is switched off.
+/// Returns ErrorFound, without outputting anything as this is called every few seconds, if amixer fails, eg as there is no such control,
+/// or does not finish within AMIXER_TIMEOUT.
+pub async fn get_mixer_mute_state(
+    command_runner: &impl CommandRunner,
+    mixer_control: &str,
+) -> MuteState {
+    match command_runner
+        .output_with_timeout("/bin/amixer", &["get", mixer_control], AMIXER_TIMEOUT)
+        .await
+    {
+        Ok(output) if output.status.success() => {
+            mixer_mute_state_from_amixer_output(&String::from_utf8_lossy(&output.stdout))
         }
+        _ => MuteState::ErrorFound,
+    }
+}
+
+/// Given the output of "amixer get", returns Muted if any of the playback channels is switched off, eg
+/// "  Front Left: Playback 52428 [80%] [off]"; a control without a switch cannot be muted
+fn mixer_mute_state_from_amixer_output(amixer_output: &str) -> MuteState {
+    if amixer_output
+        .lines()
+        .any(|line| line.contains("Playback") && line.contains("[off]"))
+    {
+        MuteState::Muted
+    } else {
+        MuteState::NotMuted
+    }
+}
+
+/// Returns true if the sound is muted outside rradio's control, so that playing appears to work but is silent;
+/// ie the ALSA mixer control is muted, or the DigiAMP+ amplifier is muted even though gstreamer is playing
+pub async fn system_is_muted(
+    command_runner: &impl CommandRunner,
+    mixer_control: &str,
+    gstreamer_state: gstreamer::State,
+) -> bool {
+    get_mixer_mute_state(command_runner, mixer_control).await == MuteState::Muted
+        || (gstreamer_state == gstreamer::State::Playing && get_mute_state() == MuteState::Muted)
+}
+
+/// Unmutes the ALSA mixer control using amixer; returns an error string if it fails
+pub async fn unmute_mixer(
+    command_runner: &impl CommandRunner,
+    mixer_control: &str,
+) -> Result<(), String> {
+    let output = command_runner
+        .output_with_timeout(
+            "/bin/amixer",
+            &["set", mixer_control, "unmute"],
+            AMIXER_TIMEOUT,
+        )
+        .await?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Could not unmute {mixer_control}; got {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixer_is_muted_if_any_channel_is_off() {
+        let unmuted = "Simple mixer control 'Master',0\n  Capabilities: pvolume pswitch\n  Playback channels: Front Left - Front Right\n  Front Left: Playback 52428 [80%] [on]\n  Front Right: Playback 52428 [80%] [on]\n";
+        assert!(mixer_mute_state_from_amixer_output(unmuted) == MuteState::NotMuted);
+        let muted = unmuted.replacen("[on]", "[off]", 1);
+        assert!(mixer_mute_state_from_amixer_output(&muted) == MuteState::Muted);
+        // a control with only a volume, so no switch, cannot be muted
+        let no_switch = "Simple mixer control 'PCM',0\n  Mono: Playback -2000 [77%] [-20.00dB]\n";
+        assert!(mixer_mute_state_from_amixer_output(no_switch) == MuteState::NotMuted);
     }
 }
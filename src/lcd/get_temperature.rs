@@ -1,8 +1,35 @@
 use std::fs::File;
 use std::io::prelude::Read; //needed for .read_to_string
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The temperature is read at most this often, as the LCD screen is refreshed far more often than the temperature changes
+const CACHE_LIFETIME: Duration = Duration::from_secs(2);
+
+/// the time of the last reading & the temperature read
+static CACHED_TEMPERATURE: Mutex<Option<(Instant, i32)>> = Mutex::new(None);
 
 /// gets the temperature in degrees Centrigrade; negative numbers mean that there was an error
+/// The value is cached, so the pseudo-file is read at most once every CACHE_LIFETIME
 pub fn get_cpu_temperature() -> i32 {
+    let mut cached_temperature = CACHED_TEMPERATURE
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let Some((time_read, temperature)) = *cached_temperature
+        && time_read.elapsed() < CACHE_LIFETIME
+    {
+        return temperature;
+    }
+    let temperature = read_cpu_temperature();
+    *cached_temperature = Some((Instant::now(), temperature));
+    temperature
+}
+
+/// reads the temperature in degrees Centrigrade from the pseudo-file; negative numbers mean that there was an error
+fn read_cpu_temperature() -> i32 {
+    if cfg!(feature = "mock-lcd") {
+        return 45; // a typical temperature, as a PC does not have the Pi's pseudo-file
+    }
     let mut file = match File::open("/sys/class/thermal/thermal_zone0/temp") {
         Ok(file) => file,
         Err(error) => {
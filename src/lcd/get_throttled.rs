@@ -1,3 +1,6 @@
+use std::sync::Mutex;
+
+use crate::command_runner::CommandRunner;
 
 /// A struct to allow us to return both the success as a bool & a String
 #[derive (Debug)]
@@ -9,11 +12,16 @@ pub struct ThrottledAsStruct {
 /// Returns true if the pi is throttled, false otherwise.
 /// Returns a 13 to 17 character string which is the result of vcgencmd get_throttled, or an error message as string of unknown length.
 /// For details see https://www.raspberrypi.com/documentation/computers/os.html and search for get_throttled
-pub fn is_throttled() -> ThrottledAsStruct {
+pub fn is_throttled(command_runner: &impl CommandRunner) -> ThrottledAsStruct {
+    if cfg!(feature = "mock-lcd") {
+        // a PC does not have vcgencmd, so pretend that all is well
+        return ThrottledAsStruct {
+            pi_is_throttled: false,
+            result: "throttled=0x0".to_string(),
+        };
+    }
     let mut return_string: String;
-    let output_as_result = std::process::Command::new("/bin/vcgencmd")
-        .arg("get_throttled")
-        .output();
+    let output_as_result = command_runner.output("/bin/vcgencmd", &["get_throttled"]);
     match output_as_result {
         Ok(output) => {
             if output.status.success() {
@@ -23,7 +31,7 @@ pub fn is_throttled() -> ThrottledAsStruct {
             }
         }
         Err(the_error) => {
-            return_string = format!("Throttle err{:?}", the_error);
+            return_string = format!("Throttle err{}", the_error);
         }
     }
 
@@ -35,3 +43,111 @@ pub fn is_throttled() -> ThrottledAsStruct {
         result: return_string,
     }
 }
+
+/// The number of consecutive readings that must agree before the debounced throttled state changes
+const READINGS_NEEDED_TO_CHANGE_STATE: u8 = 2;
+
+/// The state machine used by is_throttled_debounced so that a transient throttle does not make the text flicker
+#[derive(Debug)]
+struct DebouncedThrottleState {
+    /// true if the Pi is being reported as throttled
+    reported_as_throttled: bool,
+    /// the number of consecutive readings that disagree with reported_as_throttled
+    consecutive_disagreeing_readings: u8,
+    /// the result of the most recent reading in which the Pi was throttled
+    last_throttled_result: String,
+}
+
+impl DebouncedThrottleState {
+    /// Updates the state with a new reading & returns the debounced result
+    fn update(&mut self, reading: ThrottledAsStruct) -> ThrottledAsStruct {
+        if reading.pi_is_throttled {
+            self.last_throttled_result = reading.result.clone();
+        }
+        if reading.pi_is_throttled == self.reported_as_throttled {
+            self.consecutive_disagreeing_readings = 0;
+        } else {
+            self.consecutive_disagreeing_readings += 1;
+            if self.consecutive_disagreeing_readings >= READINGS_NEEDED_TO_CHANGE_STATE {
+                self.reported_as_throttled = reading.pi_is_throttled;
+                self.consecutive_disagreeing_readings = 0;
+            }
+        }
+
+        if self.reported_as_throttled {
+            ThrottledAsStruct {
+                pi_is_throttled: true,
+                result: self.last_throttled_result.clone(),
+            }
+        } else {
+            ThrottledAsStruct {
+                pi_is_throttled: false,
+                result: reading.result,
+            }
+        }
+    }
+}
+
+static DEBOUNCED_THROTTLE_STATE: Mutex<DebouncedThrottleState> =
+    Mutex::new(DebouncedThrottleState {
+        reported_as_throttled: false,
+        consecutive_disagreeing_readings: 0,
+        last_throttled_result: String::new(),
+    });
+
+/// As is_throttled, but the Pi is only reported as throttled once it has been throttled for READINGS_NEEDED_TO_CHANGE_STATE
+/// consecutive calls, & is only reported as no longer throttled once it has not been throttled for that many calls.
+/// While reported as throttled, result is the result of the most recent throttled reading.
+pub fn is_throttled_debounced(command_runner: &impl CommandRunner) -> ThrottledAsStruct {
+    let reading = is_throttled(command_runner);
+    DEBOUNCED_THROTTLE_STATE
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .update(reading)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(pi_is_throttled: bool) -> ThrottledAsStruct {
+        ThrottledAsStruct {
+            pi_is_throttled,
+            result: if pi_is_throttled {
+                "throttled=0x50005".to_string()
+            } else {
+                "throttled=0x0".to_string()
+            },
+        }
+    }
+
+    #[test]
+    fn single_throttled_reading_is_ignored() {
+        let mut state = DebouncedThrottleState {
+            reported_as_throttled: false,
+            consecutive_disagreeing_readings: 0,
+            last_throttled_result: String::new(),
+        };
+        assert!(!state.update(reading(true)).pi_is_throttled);
+        assert!(!state.update(reading(false)).pi_is_throttled);
+        assert!(!state.update(reading(true)).pi_is_throttled);
+    }
+
+    #[test]
+    fn throttling_is_reported_then_cleared_after_consecutive_readings() {
+        let mut state = DebouncedThrottleState {
+            reported_as_throttled: false,
+            consecutive_disagreeing_readings: 0,
+            last_throttled_result: String::new(),
+        };
+        assert!(!state.update(reading(true)).pi_is_throttled);
+        let throttled = state.update(reading(true));
+        assert!(throttled.pi_is_throttled);
+        assert_eq!(throttled.result, "throttled=0x50005");
+
+        let still_throttled = state.update(reading(false));
+        assert!(still_throttled.pi_is_throttled);
+        assert_eq!(still_throttled.result, "throttled=0x50005");
+        assert!(!state.update(reading(false)).pi_is_throttled);
+    }
+}
@@ -1,8 +1,51 @@
 use std::fs::File;
 use std::io::prelude::Read; //needed for .read_to_string
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The signal strength is read at most this often, as the LCD screen is refreshed far more often than that
+const CACHE_LIFETIME: Duration = Duration::from_secs(2);
+
+/// the time of the last reading & the signal strength read
+static CACHED_SIGNAL_STRENGTH: Mutex<Option<(Instant, String)>> = Mutex::new(None);
 
 /// returns the Wi-Fi signal strength as a string in dB relative to an arbitary level, or an error string
+/// The value is cached, so the pseudo-file is read at most once every CACHE_LIFETIME
 pub fn get_wifi_signal_strength() -> String {
+    let mut cached_signal_strength = CACHED_SIGNAL_STRENGTH
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let Some((time_read, signal_strength)) = cached_signal_strength.as_ref()
+        && time_read.elapsed() < CACHE_LIFETIME
+    {
+        return signal_strength.clone();
+    }
+    let signal_strength = read_wifi_signal_strength();
+    *cached_signal_strength = Some((Instant::now(), signal_strength.clone()));
+    signal_strength
+}
+
+/// returns the Wi-Fi signal strength as a percentage, or None if it could not be read
+pub fn get_wifi_signal_percent() -> Option<u8> {
+    signal_percent_from_level(&get_wifi_signal_strength())
+}
+
+/// Converts the signal level in dBm, eg "-55", to a percentage, taking -100 dBm or less as 0% & -50 dBm or more as 100%
+fn signal_percent_from_level(level: &str) -> Option<u8> {
+    let level = level.trim().parse::<i32>().ok()?;
+    u8::try_from(((level + 100) * 2).clamp(0, 100)).ok()
+}
+
+/// returns the number of bars, from 0 to 4, that show the signal strength given as a percentage; each bar is 20%
+pub fn signal_bars_from_percent(percent: u8) -> usize {
+    usize::from(percent / 20).min(super::character_pattern::WIFI_SIGNAL_BARS.len() - 1)
+}
+
+/// reads the Wi-Fi signal strength from the pseudo-file as a string in dB relative to an arbitary level, or an error string
+fn read_wifi_signal_strength() -> String {
+    if cfg!(feature = "mock-lcd") {
+        return "-55".to_string(); // a good signal, as a PC may not have Wi-Fi
+    }
     let mut file = match File::open("/proc/net/wireless") {
         Ok(file) => file,
         Err(error) => {
@@ -38,3 +81,22 @@ pub fn get_wifi_signal_strength() -> String {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signal_level_is_converted_to_bars() {
+        assert_eq!(signal_percent_from_level("-55"), Some(90));
+        assert_eq!(signal_percent_from_level("-120"), Some(0));
+        assert_eq!(signal_percent_from_level("-30"), Some(100));
+        assert_eq!(signal_percent_from_level("er2"), None);
+
+        let bars: Vec<usize> = [0, 19, 20, 59, 60, 80, 100]
+            .into_iter()
+            .map(signal_bars_from_percent)
+            .collect();
+        assert_eq!(bars, vec![0, 0, 1, 2, 3, 4, 4]);
+    }
+}
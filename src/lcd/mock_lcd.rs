@@ -0,0 +1,206 @@
+use std::io::Write;
+
+use crossterm::{cursor, queue, style, style::Stylize};
+
+use super::{NUM_CHARACTERS_PER_LINE, NUM_CHARACTERS_PER_SCREEN};
+
+/// The number of lines on the screen
+const NUM_LINES: usize = NUM_CHARACTERS_PER_SCREEN / NUM_CHARACTERS_PER_LINE;
+
+/// Where the parser is in the escape sequences that the LCD driver understands, eg "\x1b[Lx3y1;"
+enum ParseState {
+    /// the bytes are text to be shown at the cursor
+    Text,
+    /// got the ESC
+    Escape,
+    /// got "ESC ["
+    Bracket,
+    /// got "ESC [ L"
+    Command,
+    /// got a command that takes parameters, eg "x3y1" or "G0..." which are ended by a ';'
+    Parameters(String),
+}
+
+/// Used instead of /dev/lcd when the program is built with the mock-lcd feature, so that it can be run on a PC.
+/// It understands the same escape sequences as the LCD driver & draws the 20 * 4 screen in a box at the top of the terminal.
+pub struct MockLcd {
+    /// the characters on the screen, in the LCD screen character set
+    frame: [[u8; NUM_CHARACTERS_PER_LINE]; NUM_LINES],
+    /// the column the next character goes in
+    column: usize,
+    /// the line the next character goes on
+    line: usize,
+    parse_state: ParseState,
+    backlight_on: bool,
+}
+
+impl MockLcd {
+    pub fn new() -> Self {
+        MockLcd {
+            frame: [[b' '; NUM_CHARACTERS_PER_LINE]; NUM_LINES],
+            column: 0,
+            line: 0,
+            parse_state: ParseState::Text,
+            backlight_on: true,
+        }
+    }
+
+    /// Handles one byte written to the screen, which is either text or part of an escape sequence
+    fn parse_byte(&mut self, byte: u8) {
+        self.parse_state = match std::mem::replace(&mut self.parse_state, ParseState::Text) {
+            ParseState::Text if byte == 0x1b => ParseState::Escape,
+            ParseState::Text => {
+                // like the real screen, text that goes past the end of the line is lost
+                if self.column < NUM_CHARACTERS_PER_LINE {
+                    self.frame[self.line][self.column] = byte;
+                    self.column += 1;
+                }
+                ParseState::Text
+            }
+            ParseState::Escape if byte == b'[' => ParseState::Bracket,
+            ParseState::Bracket if byte == b'L' => ParseState::Command,
+            ParseState::Command => match byte {
+                b'I' => {
+                    self.frame = [[b' '; NUM_CHARACTERS_PER_LINE]; NUM_LINES];
+                    self.column = 0;
+                    self.line = 0;
+                    ParseState::Text
+                }
+                b'+' | b'-' => {
+                    self.backlight_on = byte == b'+';
+                    ParseState::Text
+                }
+                b'x' | b'G' => ParseState::Parameters(String::from(byte as char)),
+                _ => ParseState::Text, // the cursor & blink commands mean nothing here
+            },
+            ParseState::Parameters(parameters) if byte == b';' => {
+                self.obey_command(&parameters);
+                ParseState::Text
+            }
+            ParseState::Parameters(mut parameters) => {
+                parameters.push(byte as char);
+                ParseState::Parameters(parameters)
+            }
+            ParseState::Escape | ParseState::Bracket => ParseState::Text, // not a sequence we know, so ignore it
+        };
+    }
+
+    /// Obeys a command that takes parameters; only "x{column}y{line}" matters, as the bitmaps of the
+    /// user defined characters ("G...") are not drawn
+    fn obey_command(&mut self, parameters: &str) {
+        if let Some(position) = parameters.strip_prefix('x')
+            && let Some((column, line)) = position.split_once('y')
+            && let (Ok(column), Ok(line)) = (column.parse::<usize>(), line.parse::<usize>())
+        {
+            self.column = column.min(NUM_CHARACTERS_PER_LINE);
+            self.line = line.min(NUM_LINES - 1);
+        }
+    }
+
+    /// Converts a character in the LCD screen character set back to one the terminal can show
+    fn to_terminal_char(byte: u8) -> char {
+        match byte {
+            0x20..=0x7D => byte as char,
+            5 => 'é',
+            6 => 'è',
+            7 => 'à',
+            0..=4 => '▒', // a user defined character, eg part of the buffer bar
+            0xE1 => 'ä',
+            0xE4 => 'π',
+            0xEE => 'ñ',
+            0xEF => 'ö',
+            0xF3 => '~',
+            0xF5 => 'ü',
+            0xF7 => 'µ',
+            0xFF => '█',
+            _ => '?',
+        }
+    }
+
+    /// Draws the screen in a box at the top left of the terminal, leaving the cursor where it was
+    fn draw(&self) -> std::io::Result<()> {
+        let mut stdout = std::io::stdout();
+        let border = format!("+{}+", "-".repeat(NUM_CHARACTERS_PER_LINE));
+        queue!(
+            stdout,
+            cursor::SavePosition,
+            cursor::MoveTo(0, 0),
+            style::Print(&border)
+        )?;
+        for (line_number, line) in self.frame.iter().enumerate() {
+            let text: String = line
+                .iter()
+                .map(|&byte| Self::to_terminal_char(byte))
+                .collect();
+            // a dimmed screen stands in for the backlight being off
+            let text = if self.backlight_on {
+                text.stylize()
+            } else {
+                text.dim()
+            };
+            queue!(
+                stdout,
+                cursor::MoveTo(0, line_number as u16 + 1),
+                style::Print('|'),
+                style::PrintStyledContent(text),
+                style::Print('|')
+            )?;
+        }
+        queue!(
+            stdout,
+            cursor::MoveTo(0, NUM_LINES as u16 + 1),
+            style::Print(&border),
+            cursor::RestorePosition
+        )?;
+        stdout.flush()
+    }
+}
+
+impl Default for MockLcd {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write for MockLcd {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for &byte in buf {
+            self.parse_byte(byte);
+        }
+        self.draw()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses the bytes without drawing the screen, which writing them would do
+    fn parse(mock_lcd: &mut MockLcd, bytes: &[u8]) {
+        for &byte in bytes {
+            mock_lcd.parse_byte(byte);
+        }
+    }
+
+    #[test]
+    fn escape_sequences_clear_the_screen_and_move_the_cursor() {
+        let mut mock_lcd = MockLcd::new();
+        parse(&mut mock_lcd, b"Old text");
+        parse(&mut mock_lcd, b"\x1b[LI");
+        assert_eq!(mock_lcd.frame[0], [b' '; NUM_CHARACTERS_PER_LINE]);
+
+        parse(&mut mock_lcd, b"\x1b[Lx3y1;Hi");
+        assert_eq!(&mock_lcd.frame[1][..6], b"   Hi ");
+        assert_eq!(mock_lcd.frame[0], [b' '; NUM_CHARACTERS_PER_LINE]);
+
+        // like the real screen, text that goes past the end of the line is lost
+        parse(&mut mock_lcd, b"\x1b[Lx18y2;abcd");
+        assert_eq!(&mock_lcd.frame[2][18..], b"ab");
+        assert_eq!(mock_lcd.frame[3], [b' '; NUM_CHARACTERS_PER_LINE]);
+    }
+}
@@ -13,7 +13,9 @@ use gstreamer::{SeekFlags, prelude::ElementExtManual};
 use gstreamer_interfaces::PlaybinElement;
 //use libc::CLD_CONTINUED;/*
 
+mod cd_drive;
 mod cd_functions;
+mod command_runner;
 mod extract_html;
 mod get_channel_details;
 mod get_config_file_path;
@@ -24,12 +26,14 @@ mod html_helpers;
 mod keyboard;
 mod lcd;
 mod mount_media;
+mod now_playing_log;
 mod ping;
 mod play_channel;
 mod play_urls;
 mod player_status;
 mod previous_or_nextrack;
 mod read_config;
+mod self_test;
 mod unmount;
 mod web;
 
@@ -42,7 +46,10 @@ use crate::html_helpers::write_status_to_web_page;
 use crate::player_status::{PODCAST_CHANNEL_NUMBER, RealTimeDataOnOneChannel};
 use crate::unmount::unmount_all;
 use crate::web::{DataChanged, SeekTimes};
-use lcd::{RunningStatus, ScrollData, get_mute_state::set_mute_state};
+use lcd::{
+    RunningStatus, ScrollData,
+    get_mute_state::{self, set_mute_state},
+};
 use ping::{get_ping_time, see_if_there_is_a_ping_response};
 use player_status::NUMBER_OF_POSSIBLE_CHANNELS;
 use player_status::PlayerStatus;
@@ -78,7 +85,10 @@ enum Event {
     Keyboard(keyboard::Event),
     GStreamer(gstreamer::Message),
     Web(web::Event),
-    Ticker(tokio::time::Instant),
+    Ticker(tokio::time::Instant),       // fires every config.lcd_refresh_ms
+    PositionPoll(tokio::time::Instant), // fires every config.position_poll_ms
+    AudioOutput(gstreamer::Message), // an audio output, such as a USB DAC, has been added or removed
+    Shutdown, // SIGTERM or SIGINT has been received, eg from systemd
 }
 
 /// URL of RSS & title as stored in playlists.toml file entry
@@ -115,7 +125,10 @@ pub struct PodcastDataAllStations {
 async fn main() -> Result<(), String> {
     //    we need async as for example, we will need to wait for input from gstreamer or the keyboard
     let mut lcd;
-    match lcd::Lc::new() {
+    // the LCD screen is opened before the config file is read, so this is a command line option rather than a config option
+    let take_over_from_other_instance = !std::env::args().skip(1).any(|arg| arg == "--no-takeover");
+    let command_runner = command_runner::SystemCommandRunner;
+    match lcd::Lc::new(take_over_from_other_instance, &command_runner) {
         Ok(success) => lcd = success,
         Err(lcd_error) => {
             return Err(lcd_error.to_string());
@@ -151,8 +164,12 @@ async fn main() -> Result<(), String> {
         eprint!("{}\nUsing defaults values for the config\n", error);
         read_config::Config::default()
     });
+    if let Some(custom_chars) = config.custom_chars {
+        lcd.set_character_bitmaps(custom_chars);
+    }
 
     let mut status_of_rradio: PlayerStatus = PlayerStatus::new(&config);
+    let mut now_playing_log = now_playing_log::NowPlayingLog::new(&config);
     match get_stored_podcast_data::get_stored_podcast_data(&podcastlists_filename) {
         Ok(podcast_data) => {
             status_of_rradio.podcast_data_from_toml = podcast_data;
@@ -165,11 +182,16 @@ async fn main() -> Result<(), String> {
     }
 
     status_of_rradio.startup_folder = root_folder;
+    if let Err(error) = status_of_rradio.check_startup_folder_is_writable() {
+        eprintln!("{error}\r");
+        // shown on the LCD screen in the same way as a TOML error, until a channel is selected
+        status_of_rradio.toml_error = Some("SD card read-only or full".to_string());
+    }
     if let Some(toml_error_message) = toml_error {
         // if we got an error we should display it; hopefully, toml_error == none
         status_of_rradio.toml_error = Some(toml_error_message);
     }
-    match get_local_ip_address::try_once_to_get_wifi_network_data() {
+    match get_local_ip_address::try_once_to_get_wifi_network_data(&command_runner) {
         Ok(network_data) => status_of_rradio.network_data = network_data,
 
         Err(error) => {
@@ -181,12 +203,20 @@ async fn main() -> Result<(), String> {
             status_of_rradio
                 .all_4lines
                 .update_if_changed(error_string.as_str());
-            status_of_rradio.update_network_data(&mut lcd, &config);
+            status_of_rradio.update_network_data(&mut lcd, &config, &command_runner);
         }
     }
-    if !status_of_rradio.network_data.is_valid {
+    if status_of_rradio.network_data.state != get_local_ip_address::NetworkState::Connected {
         // get the IP address from the memory stick in /dev/sda1
-        if let Err(error) = get_local_ip_address::set_up_wifi_password(&mut status_of_rradio) {
+        if let Err(error) =
+            get_local_ip_address::set_up_wifi_password(
+                &mut status_of_rradio,
+                &mut lcd,
+                &config,
+                &command_runner,
+            )
+            .await
+        {
             status_of_rradio
                 .all_4lines
                 .update_if_changed(error.as_str());
@@ -199,46 +229,84 @@ async fn main() -> Result<(), String> {
         ScrollData::new("initialising gstreamer", 1).bytes(),
         lcd::LineNum::Line1,
     );
-    lcd.write_text_buffer_to_lcd(&text_buffer);
+    if let Err(lcd_error) = lcd.write_text_buffer_to_lcd(&text_buffer) {
+        eprintln!("{lcd_error}\r");
+    }
 
-    if gstreamer::init().is_err() {
-        status_of_rradio.all_4lines = ScrollData::new("Failed it to intialise gstreamer", 4);
-        status_of_rradio.running_status = lcd::RunningStatus::LongMessageOnAll4Lines;
-        lcd.write_rradio_status_to_lcd(&status_of_rradio, &config);
+    if let Err(error) = gstreamer::init() {
+        // nothing can be played, so there is no point going any further
+        show_no_audio_until_shutdown(
+            format!("Failed to initialise gstreamer: {error}").as_str(),
+            &mut lcd,
+            &config,
+        )
+        .await;
+        unmount_all(&mut status_of_rradio);
+        return Ok(());
     };
+    if config.check_notification_files {
+        let unplayable_files =
+            gstreamer_interfaces::find_unplayable_notification_files(&config.aural_notifications);
+        if !unplayable_files.is_empty() {
+            let warning = unplayable_files.join("; ");
+            eprintln!("{warning}\r");
+            // shown in the same way as a TOML error, as the config file names the files
+            status_of_rradio.toml_error = Some(match status_of_rradio.toml_error.take() {
+                Some(toml_error) => format!("{toml_error}; {warning}"),
+                None => warning,
+            });
+        }
+    }
     status_of_rradio.line_1_data = ScrollData::new(
         format!(
             "{} {}",
             status_of_rradio.network_data.local_ip_address,
-            lcd::Lc::get_vol_string(&status_of_rradio)
+            lcd::Lc::get_vol_string(&status_of_rradio, &config)
         )
         .as_str(),
         1,
     );
     match gstreamer_interfaces::PlaybinElement::setup(&config) {
         Ok((mut playbin, bus_stream)) => {
+            // if the startup ding is playing, the autoplay channel is played when it ends, so we do not cut it off
+            let mut autoplay_channel_pending = config.autoplay_channel;
+            let mut startup_ding_is_playing = false;
             if let Some(startup_filename) = config.aural_notifications.filename_startup.clone() {
                 status_of_rradio.channel_number = player_status::START_UP_DING_CHANNEL_NUMBER;
 
-                status_of_rradio.position_and_duration
-                    [player_status::START_UP_DING_CHANNEL_NUMBER]
-                    .channel_data
-                    .station_url = vec![format!("file://{startup_filename}")];
-                status_of_rradio.position_and_duration
-                    [player_status::START_UP_DING_CHANNEL_NUMBER]
-                    .channel_data
-                    .source_type = SourceType::UrlList;
-                if let Err(error_message) =
-                    playbin.play_track(&mut status_of_rradio, &config, &mut lcd, false)
+                if let Some(ding_channel) =
+                    status_of_rradio.channel_mut(player_status::START_UP_DING_CHANNEL_NUMBER)
                 {
+                    ding_channel.channel_data.station_url =
+                        vec![format!("file://{startup_filename}")];
+                    ding_channel.channel_data.source_type = SourceType::UrlList;
+                }
+                if let Err(error_message) = playbin.play_track(
+                    &mut status_of_rradio,
+                    &config,
+                    &mut lcd,
+                    false,
+                    &command_runner,
+                ) {
                     status_of_rradio.all_4lines = ScrollData::new(error_message.as_str(), 4);
-                    lcd.write_rradio_status_to_lcd(&status_of_rradio, &config);
+                    if let Err(lcd_error) =
+                        lcd.write_rradio_status_to_lcd(&status_of_rradio, &config, &command_runner)
+                    {
+                        eprintln!("{lcd_error}\r");
+                    }
+                } else {
+                    startup_ding_is_playing = true;
                 }
             } else {
                 println!("No startup ding wanted.");
             }
 
-            let keyboard_events = keyboard::setup_keyboard(config.input_timeout);
+            let keyboard_events = keyboard::setup_keyboard(
+                config.input_timeout,
+                config.channel_digits,
+                config.favorites.clone(),
+                config.rotary_encoder.clone(),
+            );
 
             //Map the different stream item types (such as `keyboard::Event` and `gstreamer::Message`) into a common stream item type (i.e. Event)
             //We need a common event type in order to merge several sources of events and handle whichever event occurs first, no matter the source.
@@ -252,11 +320,43 @@ async fn main() -> Result<(), String> {
                 tokio_stream::wrappers::UnboundedReceiverStream::new(web_events).map(Event::Web);
 
             let mut some_timer = tokio_stream::wrappers::IntervalStream::new(
-                tokio::time::interval(std::time::Duration::from_millis(300)),
+                tokio::time::interval(std::time::Duration::from_millis(
+                    config.lcd_refresh_ms.max(1), // interval panics if the period is zero
+                )),
                 // If this time is not significantly shorter than 1 second, the auto start at the requested time might not work.
                 // also wants to be short so that the program appears to respond instantly to commands
             )
             .map(Event::Ticker);
+            let mut position_poll_timer = tokio_stream::wrappers::IntervalStream::new(
+                tokio::time::interval(std::time::Duration::from_millis(
+                    config.position_poll_ms.max(1),
+                )),
+            )
+            .map(Event::PositionPoll);
+
+            // the monitor is kept so it carries on sending messages; without it, we just cannot pause when the audio output is unplugged
+            let (_audio_output_monitor, mut audio_output_events) =
+                match gstreamer_interfaces::setup_audio_output_monitor() {
+                    Ok((monitor, bus_stream)) => {
+                        (Some(monitor), Some(bus_stream.map(Event::AudioOutput)))
+                    }
+                    Err(error) => {
+                        eprintln!("{error}\r");
+                        (None, None)
+                    }
+                };
+
+            // systemd stops the program with SIGTERM, so we must catch it (& SIGINT) in order to unmount & clear the screen
+            let mut sigterm = tokio::signal::unix::signal(
+                tokio::signal::unix::SignalKind::terminate(),
+            )
+            .inspect_err(|error| eprintln!("Could not install the SIGTERM handler; got {error}\r"))
+            .ok();
+            let mut sigint = tokio::signal::unix::signal(
+                tokio::signal::unix::SignalKind::interrupt(),
+            )
+            .inspect_err(|error| eprintln!("Could not install the SIGINT handler; got {error}\r"))
+            .ok();
 
             change_volume(
                 0, // if direction == 0 it gets the volume, but does not change it
@@ -266,12 +366,38 @@ async fn main() -> Result<(), String> {
                 &web_data_changed_tx,
             );
 
-            let mut child_ping = ping::send_ping(&mut status_of_rradio, &config);
+            // if the Wi-Fi is still connecting, the autoplay channel is played once it has connected, rather than failing now
+            if !startup_ding_is_playing
+                && status_of_rradio.network_data.state
+                    != get_local_ip_address::NetworkState::Connecting
+                && let Some(autoplay_channel) = autoplay_channel_pending.take()
+            {
+                play_station(
+                    autoplay_channel,
+                    &mut status_of_rradio,
+                    &config,
+                    &mut playbin,
+                    &mut lcd,
+                    &web_data_changed_tx,
+                    &command_runner,
+                )
+                .await;
+            }
+
+            let mut child_ping = ping::send_ping(&mut status_of_rradio, &config, &command_runner);
 
             if let Some(toml_error) = status_of_rradio.toml_error {
                 status_of_rradio.line_1_data.update_if_changed(&toml_error); // convert to be a scrollable message
                 status_of_rradio.toml_error = None;
             }
+            let mut lcd_write_has_failed = false;
+            // used so that we do not run nmcli every time the ticker ticks while the Wi-Fi is connecting
+            let mut last_network_check = std::time::Instant::now();
+            // used so that we do not run amixer every time the ticker ticks
+            let mut last_system_mute_check = std::time::Instant::now();
+            // the time the LCD test pattern was started; None if it is not being shown
+            let mut lcd_test_pattern_start: Option<std::time::Instant> = None;
+            let mut backlight_is_on = true;
             let mut episode_data_for_one_podcast_downloaded = EpisodeDataForOnePodcastDownloaded {
                 channel_title: String::new(),
                 description: String::new(),
@@ -280,14 +406,15 @@ async fn main() -> Result<(), String> {
             loop {
                 if status_of_rradio.ping_data.can_send_ping {
                     //we must get the output
-                    if let Err(error) =
-                        get_ping_time(child_ping.wait_with_output(), &mut status_of_rradio)
+                    if let Some(child_ping) = child_ping.take()
+                        && let Err(error) =
+                            get_ping_time(child_ping.wait_with_output(), &mut status_of_rradio)
                     {
                         eprintln!("Got ping error {error}\r")
                     };
-                    child_ping = ping::send_ping(&mut status_of_rradio, &config);
+                    child_ping = ping::send_ping(&mut status_of_rradio, &config, &command_runner);
                 } else {
-                    see_if_there_is_a_ping_response(&mut status_of_rradio);
+                    see_if_there_is_a_ping_response(&mut status_of_rradio, &config);
                 }
 
                 let event = std::future::poll_fn(|cx| {
@@ -317,24 +444,62 @@ async fn main() -> Result<(), String> {
                         Poll::Pending => (),
                     }
 
+                    match position_poll_timer.poll_next_unpin(cx) {
+                        Poll::Ready(position_poll_event) => return Poll::Ready(position_poll_event),
+                        Poll::Pending => (),
+                    }
+
+                    if let Some(audio_output_events) = audio_output_events.as_mut()
+                        && let Poll::Ready(Some(audio_output_event)) =
+                            audio_output_events.poll_next_unpin(cx)
+                    {
+                        return Poll::Ready(Some(audio_output_event));
+                    }
+
+                    // Then poll for the signals that ask us to shut down
+                    for signal in [sigterm.as_mut(), sigint.as_mut()].into_iter().flatten() {
+                        if signal.poll_recv(cx).is_ready() {
+                            return Poll::Ready(Some(Event::Shutdown));
+                        }
+                    }
+
                     // No event sources are ready, notify we're awaiting, i.e. pending, incoming events.
                     // poll_fn calls this code block the next time it's awoken, not in a CPU intensive tight loop.
                     Poll::Pending //this is the return value event is made equal to Poll::Pending.
                 })
                 .await;
 
+                // any key other than the one that starts the test pattern stops it
+                if let Some(Event::Keyboard(keyboard_event)) = &event
+                    && !matches!(keyboard_event, keyboard::Event::LcdTestPattern)
+                {
+                    lcd_test_pattern_start = None;
+                }
+
+                let lcd_refresh_wanted = !matches!(event, Some(Event::PositionPoll(_)));
+
                 //Now that we have an event, work out what to do with it
                 match event {
-                    None => {
+                    None | Some(Event::Shutdown) => {
+                        now_playing_log.flush();
                         unmount_all(&mut status_of_rradio);
                         status_of_rradio.running_status = lcd::RunningStatus::ShuttingDown;
                         lcd.clear();
-                        lcd.write_rradio_status_to_lcd(&status_of_rradio, &config);
+                        if let Err(lcd_error) = lcd.write_rradio_status_to_lcd(
+                            &status_of_rradio,
+                            &config,
+                            &command_runner,
+                        ) {
+                            eprintln!("{lcd_error}\r");
+                        }
 
                         break; // if we get here, the program will terminate
-                    } //One of the streams has closed, signalling a shutdown of the program, so break out of the main loop
+                    } //One of the streams has closed, or we got SIGTERM or SIGINT, signalling a shutdown of the program, so break out of the main loop
                     Some(Event::Keyboard(keyboard_event)) => match keyboard_event {
                         keyboard::Event::PlayPause => {
+                            // the user has chosen when to play, so a track waiting for its
+                            // pause_before_playing_ms must not restart later
+                            status_of_rradio.deferred_play = None;
                             let new_state =
                                 if status_of_rradio.gstreamer_state == gstreamer::State::Playing {
                                     gstreamer::State::Paused
@@ -351,6 +516,20 @@ async fn main() -> Result<(), String> {
                         keyboard::Event::EjectCD => {
                             eprintln!("eject result {:?}\r", cd_functions::eject());
                         }
+                        keyboard::Event::ReloadCD => {
+                            if play_channel::reload_cd(
+                                &mut status_of_rradio,
+                                &config,
+                                &mut playbin,
+                                &mut lcd,
+                                &web_data_changed_tx,
+                                &command_runner,
+                            )
+                            .is_err()
+                            {
+                                let _ = playbin.set_state(gstreamer::State::Null);
+                            }
+                        }
                         keyboard::Event::VolumeUp => {
                             change_volume(
                                 1,
@@ -363,7 +542,7 @@ async fn main() -> Result<(), String> {
                                 format!(
                                     "{} {}",
                                     status_of_rradio.network_data.local_ip_address,
-                                    lcd::Lc::get_vol_string(&status_of_rradio)
+                                    lcd::Lc::get_vol_string(&status_of_rradio, &config)
                                 )
                                 .as_str(),
                             );
@@ -380,7 +559,7 @@ async fn main() -> Result<(), String> {
                                 format!(
                                     "{} {}",
                                     status_of_rradio.network_data.local_ip_address,
-                                    lcd::Lc::get_vol_string(&status_of_rradio)
+                                    lcd::Lc::get_vol_string(&status_of_rradio, &config)
                                 )
                                 .as_str(),
                             );
@@ -391,6 +570,7 @@ async fn main() -> Result<(), String> {
                                 &playbin,
                                 &config,
                                 &mut lcd,
+                                &command_runner,
                             );
                         }
                         keyboard::Event::NextTrack => {
@@ -399,34 +579,131 @@ async fn main() -> Result<(), String> {
                                 &playbin,
                                 &config,
                                 &mut lcd,
+                                &command_runner,
+                            );
+                        }
+                        keyboard::Event::SeekForward => previous_or_nextrack::seek_within_track(
+                            &mut status_of_rradio,
+                            &playbin,
+                            config.keyboard_seek_seconds,
+                        ),
+                        keyboard::Event::SeekBackward => previous_or_nextrack::seek_within_track(
+                            &mut status_of_rradio,
+                            &playbin,
+                            -config.keyboard_seek_seconds,
+                        ),
+                        keyboard::Event::RestartTrack => {
+                            previous_or_nextrack::restart_track(&mut status_of_rradio, &playbin)
+                        }
+                        keyboard::Event::ShowCurrentUrl => {
+                            let message = status_of_rradio
+                                .current_url_for_display()
+                                .unwrap_or("Nothing is playing")
+                                .to_string();
+                            status_of_rradio.all_4lines.update_if_changed(message.as_str());
+                            status_of_rradio.running_status = RunningStatus::LongMessageOnAll4Lines;
+                        }
+                        keyboard::Event::ScrollFaster => {
+                            status_of_rradio.change_scroll_period(-player_status::SCROLL_PERIOD_STEP_MS)
+                        }
+                        keyboard::Event::ScrollSlower => {
+                            status_of_rradio.change_scroll_period(player_status::SCROLL_PERIOD_STEP_MS)
+                        }
+                        keyboard::Event::SpeakTime => {
+                            play_channel::speak_time(
+                                &mut status_of_rradio,
+                                &config,
+                                &playbin,
+                                &mut lcd,
+                                &command_runner,
+                            )
+                            .await
+                        }
+                        keyboard::Event::ToggleClockFormat => status_of_rradio.toggle_clock_format(),
+                        keyboard::Event::ToggleTimeDisplay => status_of_rradio.toggle_time_display(),
+                        keyboard::Event::StopAtEnd => {
+                            previous_or_nextrack::toggle_stop_at_end_of_album(&mut status_of_rradio)
+                        }
+                        keyboard::Event::SelectCdTrack => previous_or_nextrack::toggle_cd_track_select(
+                            &mut status_of_rradio,
+                            config.channel_digits,
+                        ),
+                        keyboard::Event::PlayStation { channel_number: track_number }
+                            if status_of_rradio.cd_track_select =>
+                        {
+                            // the number typed is a track on the current CD, not a channel
+                            previous_or_nextrack::play_cd_track(
+                                track_number,
+                                &mut status_of_rradio,
+                                &playbin,
+                                &config,
+                                &mut lcd,
+                                &command_runner,
                             );
                         }
-                        keyboard::Event::PlayStation { channel_number } => {
-                            if play_channel::play_channel(
+                        keyboard::Event::ReconnectWifi => {
+                            match status_of_rradio
+                                .reconnect_wifi(&mut lcd, &config, &command_runner)
+                                .await
+                            {
+                                Ok(()) => {
+                                    status_of_rradio.line_1_data.update_if_changed(
+                                        format!(
+                                            "{} {}",
+                                            status_of_rradio.network_data.local_ip_address,
+                                            lcd::Lc::get_vol_string(&status_of_rradio, &config)
+                                        )
+                                        .as_str(),
+                                    );
+                                    status_of_rradio.line_1_brief_message =
+                                        Some(("Wi-Fi reconnected".to_string(), chrono::Utc::now()));
+                                }
+                                Err(error_message) => status_of_rradio
+                                    .show_error_on_all_4lines(error_message.as_str()),
+                            }
+                        }
+                        keyboard::Event::UnmuteSystem => {
+                            unmute_system(&mut status_of_rradio, &config, &command_runner).await
+                        }
+                        keyboard::Event::StationPreview => play_channel::toggle_station_preview(
+                            &mut status_of_rradio,
+                            &config,
+                            &mut playbin,
+                        ),
+                        keyboard::Event::PlayStation { channel_number }
+                        | keyboard::Event::PlayFavorite { channel_number } => {
+                            // a favorite is a channel even in track select mode, so it cancels the mode
+                            status_of_rradio.cd_track_select = false;
+                            play_station(
                                 channel_number,
                                 &mut status_of_rradio,
                                 &config,
                                 &mut playbin,
                                 &mut lcd,
                                 &web_data_changed_tx,
+                                &command_runner,
                             )
-                            .is_err()
-                            {
-                                let _ = playbin.set_state(gstreamer::State::Null);
-                                // even if it does not stop is does matter much & we do not want to hide the error message
-                            }
+                            .await;
+                            play_channel::restart_station_preview_time(
+                                &mut status_of_rradio,
+                                &config,
+                            );
                         }
                         keyboard::Event::OutputStatusDebug => {
                             println!("\r");
 
                             for line in status_of_rradio
-                                .generate_rradio_report()
+                                .generate_rradio_report(&command_runner)
                                 .expect("Formatting error while gererating report")
                                 .lines()
                             {
                                 println!("{line}\r");
                             }
                         }
+                        keyboard::Event::DumpStatus => match status_of_rradio.dump_status_to_file() {
+                            Ok(file_path) => println!("Dumped the status to {file_path}\r"),
+                            Err(error_message) => eprintln!("{error_message}\r"),
+                        },
                         keyboard::Event::OutputConfigDebug => {
                             status_of_rradio.output_config_information(&config);
                         }
@@ -434,13 +711,84 @@ async fn main() -> Result<(), String> {
                         keyboard::Event::NewLineOnScreen => {
                             println!("\r")
                         } // output a blank line on the screen to aid debugging clarity
+
+                        keyboard::Event::LcdTestPattern => {
+                            lcd_test_pattern_start = Some(std::time::Instant::now());
+                        }
+
+                        keyboard::Event::ShowLastError => {
+                            let message = status_of_rradio.last_error_text();
+                            status_of_rradio.all_4lines.update_if_changed(message.as_str());
+                            status_of_rradio.running_status = RunningStatus::LongMessageOnAll4Lines;
+                        }
+
+                        keyboard::Event::SelfTest => {
+                            let summary = self_test::run_self_test(
+                                &status_of_rradio,
+                                &config,
+                                &mut lcd,
+                                &command_runner,
+                            )
+                            .await;
+                            println!("{summary}\r");
+                            status_of_rradio.all_4lines.update_if_changed(summary.as_str());
+                            status_of_rradio.running_status = RunningStatus::LongMessageOnAll4Lines;
+                        }
+
+                        keyboard::Event::ListStations => {
+                            let message = match get_channel_details::list_stations(&config) {
+                                Ok(stations) if stations.is_empty() => format!(
+                                    "No channel files found in {}",
+                                    config.all_stations_directories().join(", ")
+                                ),
+                                Ok(stations) => stations
+                                    .iter()
+                                    .map(|(channel_number, organisation)| {
+                                        format!("{channel_number} {organisation}")
+                                    })
+                                    .collect::<Vec<String>>()
+                                    .join("; "),
+                                Err(error) => error.to_lcd_screen(),
+                            };
+                            status_of_rradio.all_4lines.update_if_changed(message.as_str());
+                            status_of_rradio.running_status = RunningStatus::LongMessageOnAll4Lines;
+                        }
                     },
 
                     Some(Event::GStreamer(gstreamer_message)) => {
                         use gstreamer::MessageView;
                         match gstreamer_message.view() {
                             MessageView::Buffering(buffering) => {
-                                status_of_rradio.buffering_percent = buffering.percent()
+                                status_of_rradio.buffering_percent = buffering.percent();
+                                let playing_a_stream =
+                                    status_of_rradio.current_channel().is_some_and(|channel| {
+                                        matches!(
+                                            channel.channel_data.source_type,
+                                            SourceType::UrlList | SourceType::Playlist
+                                        )
+                                    });
+                                if matches!(
+                                    status_of_rradio.running_status,
+                                    RunningStatus::Buffering | RunningStatus::Reconnecting
+                                ) && status_of_rradio.buffering_percent >= 100
+                                {
+                                    if status_of_rradio.running_status == RunningStatus::Reconnecting {
+                                        println!("Reconnected to the stream\r");
+                                        // so that the next drop out gets all its attempts
+                                        if let Some(channel) =
+                                            status_of_rradio.current_channel_mut()
+                                        {
+                                            channel.stream_reconnect_attempts = 0;
+                                        }
+                                    }
+                                    status_of_rradio.running_status = RunningStatus::RunningNormally;
+                                } else if status_of_rradio.running_status
+                                    == RunningStatus::RunningNormally
+                                    && status_of_rradio.buffering_percent < 100
+                                    && playing_a_stream
+                                {
+                                    status_of_rradio.running_status = RunningStatus::Buffering;
+                                }
                             }
 
                             MessageView::Tag(tag) => {
@@ -449,9 +797,19 @@ async fn main() -> Result<(), String> {
                                     match tag_name.as_str() {
                                         "title" => {
                                             if let Ok(title) = tag_value.get::<&str>() {
+                                                let title_has_changed =
+                                                    status_of_rradio.line_34_data.text != title;
                                                 status_of_rradio
                                                     .line_34_data
-                                                    .update_if_changed(title);
+                                                    .update_if_changed(title); // still needed for the web page
+                                                if config.split_title_artist {
+                                                    status_of_rradio
+                                                        .line_3_data
+                                                        .update_if_changed(title);
+                                                }
+                                                if title_has_changed {
+                                                    now_playing_log.record(&status_of_rradio);
+                                                }
 
                                                 write_status_to_web_page(
                                                     &status_of_rradio,
@@ -460,48 +818,55 @@ async fn main() -> Result<(), String> {
                                             }
                                         }
                                         "organization" => {
-                                            if let Ok(mut organization) = tag_value.get::<&str>() {
-                                                match organization {
-                                                    // correct the name of the station
-                                                    "LaPremiere" => organization = "La Première",
-
-                                                    "Nostalgie Chansons fran??aises" => {
-                                                        organization =
-                                                            "Nostalgie Chansons françaises"
-                                                    }
-                                                    _ => {}
-                                                }
-
-                                                if status_of_rradio.position_and_duration
-                                                    [status_of_rradio.channel_number]
-                                                    .channel_data
-                                                    .organisation
-                                                    != organization
+                                            if let Ok(raw_organization) = tag_value.get::<&str>() {
+                                                // correct the name of the station if its metadata is broken
+                                                let organization = config.correct_name(raw_organization);
+
+                                                if let Some(channel) =
+                                                    status_of_rradio.current_channel_mut()
+                                                    && channel.channel_data.organisation
+                                                        != organization
                                                 {
-                                                    status_of_rradio.position_and_duration
-                                                        [status_of_rradio.channel_number]
-                                                        .channel_data
-                                                        .organisation = organization.to_string();
+                                                    channel.channel_data.organisation =
+                                                        organization.to_string();
                                                     status_of_rradio
                                                         .line_2_data
                                                         .update_if_changed(organization);
                                                     println!(
                                                         "got new organization!!! {organization:?}\r"
-                                                    )
+                                                    );
+                                                    now_playing_log.record(&status_of_rradio);
                                                 }
                                             }
                                         }
+                                        "album" => {
+                                            // CD drives give the album name from the CD-TEXT, if the CD has any; it replaces the organisation "CD"
+                                            if let Ok(album) = tag_value.get::<&str>()
+                                                && let Some(channel) =
+                                                    status_of_rradio.current_channel_mut()
+                                                && channel.channel_data.source_type
+                                                    == SourceType::Cd
+                                                && channel.channel_data.organisation != album
+                                            {
+                                                channel.channel_data.organisation =
+                                                    album.to_string();
+                                                println!("got CD-TEXT album {album:?}\r");
+                                                now_playing_log.record(&status_of_rradio);
+                                            }
+                                        }
                                         "artist" => {
                                             if let Ok(artist) = tag_value.get::<&str>()
-                                                && status_of_rradio.position_and_duration
-                                                    [status_of_rradio.channel_number]
-                                                    .artist
-                                                    != artist
+                                                && let Some(channel) =
+                                                    status_of_rradio.current_channel_mut()
+                                                && channel.artist != artist
                                             {
-                                                status_of_rradio.position_and_duration
-                                                    [status_of_rradio.channel_number]
-                                                    .artist = artist.to_string();
+                                                channel.artist = artist.to_string();
                                                 println!("got new artist!!! {artist:?}\r");
+                                                if config.split_title_artist {
+                                                    status_of_rradio
+                                                        .line_4_data
+                                                        .update_if_changed(artist);
+                                                }
                                                 if (status_of_rradio.channel_number
                                                     == PODCAST_CHANNEL_NUMBER)
                                                     || !artist.is_empty()
@@ -509,6 +874,7 @@ async fn main() -> Result<(), String> {
                                                     let line2 =
                                                         previous_or_nextrack::generate_line2(
                                                             &status_of_rradio,
+                                                            &command_runner,
                                                         );
 
                                                     status_of_rradio
@@ -520,7 +886,10 @@ async fn main() -> Result<(), String> {
                                         _ => {}
                                     }
                                 }
-                                let line2 = previous_or_nextrack::generate_line2(&status_of_rradio);
+                                let line2 = previous_or_nextrack::generate_line2(
+                                    &status_of_rradio,
+                                    &command_runner,
+                                );
                                 status_of_rradio
                                     .line_2_data
                                     .update_if_changed(line2.as_str());
@@ -543,46 +912,171 @@ async fn main() -> Result<(), String> {
                             }
 
                             MessageView::Eos(_end_of_stream)
-                                if status_of_rradio.position_and_duration
-                                    [status_of_rradio.channel_number]
-                                    .channel_data
-                                    .station_url
-                                    .len()
-                                    > 1 =>
+                                if status_of_rradio.station_announcement_playing =>
+                            {
+                                play_channel::play_announced_channel(
+                                    &mut status_of_rradio,
+                                    &config,
+                                    &playbin,
+                                    &mut lcd,
+                                    &command_runner,
+                                );
+                            }
+
+                            MessageView::Eos(_end_of_stream)
+                                if status_of_rradio.channel_number
+                                    == player_status::START_UP_DING_CHANNEL_NUMBER
+                                    && autoplay_channel_pending.is_some()
+                                    && status_of_rradio.network_data.state
+                                        != get_local_ip_address::NetworkState::Connecting =>
+                            {
+                                if let Some(autoplay_channel) = autoplay_channel_pending.take() {
+                                    play_station(
+                                        autoplay_channel,
+                                        &mut status_of_rradio,
+                                        &config,
+                                        &mut playbin,
+                                        &mut lcd,
+                                        &web_data_changed_tx,
+                                        &command_runner,
+                                    )
+                                    .await;
+                                }
+                            }
+
+                            MessageView::Eos(_end_of_stream)
+                                if status_of_rradio.stop_at_end_of_album
+                                    && previous_or_nextrack::is_playing_last_track_of_album(
+                                        &status_of_rradio,
+                                    ) =>
+                            {
+                                if let Err(error) = playbin.set_state(gstreamer::State::Null) {
+                                    eprintln!("When stopping at the end of the album got error {error}\r");
+                                }
+                                status_of_rradio.stop_at_end_of_album = false;
+                                status_of_rradio.all_4lines.update_if_changed("Finished album");
+                                status_of_rradio.running_status = RunningStatus::LongMessageOnAll4Lines;
+                            }
+
+                            MessageView::Eos(_end_of_stream)
+                                if status_of_rradio.current_channel().is_some_and(|channel| {
+                                    channel.channel_data.station_url.len() > 1
+                                }) =>
                             {
                                 previous_or_nextrack::next_track(
                                     &mut status_of_rradio,
                                     &playbin,
                                     &config,
                                     &mut lcd,
+                                    &command_runner,
                                 );
                             }
 
                             MessageView::Error(gstreamer_error) => {
                                 let mut output_message =
                                     format!("gstreamer_error {:?}", gstreamer_error);
-                                if let Some(message) = gstreamer_error.message().structure() {
-                                    let formatted_message = format!("{:?}", message);
-                                    if formatted_message.contains("No such file") {
-                                        output_message = formatted_message;
+                                // the network may have dropped out briefly while playing from a Samba share, so try to reconnect
+                                let reconnected = gstreamer_error
+                                    .error()
+                                    .is::<gstreamer::ResourceError>()
+                                    && match play_channel::reconnect_samba(
+                                        &mut status_of_rradio,
+                                        &config,
+                                        &mut playbin,
+                                        &mut lcd,
+                                        &command_runner,
+                                    ) {
+                                        Ok(()) => true,
+                                        Err(reason) => {
+                                            println!("Did not reconnect as {reason}\r");
+                                            false
+                                        }
+                                    };
+                                // likewise for a stream, which is reconnected after a short delay, unless the audio output has gone
+                                let reconnecting_stream = !reconnected
+                                    && !status_of_rradio.paused_as_audio_output_removed
+                                    && gstreamer_error
+                                        .error()
+                                        .is::<gstreamer::ResourceError>()
+                                    && match play_channel::schedule_stream_reconnect(
+                                        &mut status_of_rradio,
+                                        &config,
+                                        &playbin,
+                                    ) {
+                                        Ok(()) => true,
+                                        Err(reason) => {
+                                            println!("Did not reconnect as {reason}\r");
+                                            false
+                                        }
+                                    };
+                                if reconnected {
+                                    println!("Reconnected to the Samba share after {output_message}\r");
+                                } else if reconnecting_stream {
+                                    println!("Reconnecting to the stream after {output_message}\r");
+                                } else if status_of_rradio.paused_as_audio_output_removed {
+                                    // expected, as the output has gone; we start again when it returns
+                                    println!("Audio output removed, so ignoring {output_message}\r");
+                                } else {
+                                    if let Some(message) = gstreamer_error.message().structure() {
+                                        let formatted_message = format!("{:?}", message);
+                                        if formatted_message.contains("No such file") {
+                                            output_message = formatted_message;
+                                        }
                                     }
+                                    println!("gstreamer error {}\r", output_message);
+                                    status_of_rradio
+                                        .show_error_on_all_4lines(output_message.as_str());
                                 }
-                                println!("gstreamer error {}\r", output_message);
-                                status_of_rradio.all_4lines =
-                                    ScrollData::new(output_message.as_str(), 4);
-                                status_of_rradio.running_status =
-                                    RunningStatus::LongMessageOnAll4Lines;
                             }
 
                             _ => {}
                         }
                     }
+                    Some(Event::AudioOutput(device_message)) => match device_message.view() {
+                        gstreamer::MessageView::DeviceRemoved(removed)
+                            if status_of_rradio.gstreamer_state == gstreamer::State::Playing
+                                && playbin.plays_through(&removed.device()) =>
+                        {
+                            // pause rather than let gstreamer fail, so we can carry on when the output returns
+                            if let Err(_error_message) = playbin.set_state(gstreamer::State::Paused) {
+                                eprintln!("Could not pause when the audio output was removed\r")
+                            }
+                            set_mute_state(gstreamer::State::Paused);
+                            status_of_rradio.paused_as_audio_output_removed = true;
+                            status_of_rradio.line_1_brief_message =
+                                Some(("No audio output".to_string(), chrono::Utc::now()));
+                        }
+                        gstreamer::MessageView::DeviceAdded(added)
+                            if status_of_rradio.paused_as_audio_output_removed
+                                && playbin.plays_through(&added.device()) =>
+                        {
+                            status_of_rradio.paused_as_audio_output_removed = false;
+                            // the removal might have caused a gstreamer error, so clear it & start again where we were
+                            status_of_rradio.running_status = RunningStatus::RunningNormally;
+                            if let Err(error_message) =
+                                playbin.play_track(
+                                    &mut status_of_rradio,
+                                    &config,
+                                    &mut lcd,
+                                    true,
+                                    &command_runner,
+                                )
+                            {
+                                status_of_rradio.show_error_on_all_4lines(
+                                    format!("When the audio output returned got {error_message}")
+                                        .as_str(),
+                                );
+                            }
+                        }
+                        _ => {}
+                    },
                     Some(Event::Web(web_event)) => match web_event {
                         web::Event::NextStation => previous_or_nextrack::next_track(
                             &mut status_of_rradio,
                             &playbin,
                             &config,
                             &mut lcd,
+                            &command_runner,
                         ),
                         web::Event::PreviousStation => {
                             previous_or_nextrack::previous_track(
@@ -590,16 +1084,15 @@ async fn main() -> Result<(), String> {
                                 &playbin,
                                 &config,
                                 &mut lcd,
+                                &command_runner,
                             );
                         }
                         web::Event::AdvancePosition { advance_position } => {
-                            if let Some(duration) = status_of_rradio.position_and_duration
-                                [status_of_rradio.channel_number]
-                                .duration
+                            if let Some(channel) = status_of_rradio.current_channel()
+                                && let Some(duration) = channel.duration
                             {
                                 let mut new_position = ClockTime::from_nseconds(
-                                    status_of_rradio.position_and_duration
-                                        [status_of_rradio.channel_number]
+                                    channel
                                         .position
                                         .saturating_add_signed((advance_position) * 1_000_000_000),
                                 );
@@ -626,44 +1119,71 @@ async fn main() -> Result<(), String> {
                                 .url
                                 .clone();
                             status_of_rradio.running_status = RunningStatus::RunningNormally;
-                            status_of_rradio.position_and_duration[PODCAST_CHANNEL_NUMBER] =
-                                RealTimeDataOnOneChannel {
-                                    artist: String::new(),
-                                    address_to_ping: get_ip_address(url.as_str()),
-                                    index_to_current_track: 0,
-                                    position: ClockTime::ZERO,
-                                    duration: None,
-                                    channel_data: ChannelFileDataDecoded {
-                                        organisation: format!(
-                                            "{} {}",
-                                            episode_data_for_one_podcast_downloaded.channel_title, // eg "the Archers"
-                                            episode_data_for_one_podcast_downloaded
-                                                .data_for_multiple_episodes[episode_index]
-                                                .subtitle
-                                        ),
-                                        source_type: SourceType::UrlList,
-                                        last_track_is_a_ding: false,
-                                        pause_before_playing_ms: None,
-                                        random_tracks_wanted: false,
-                                        data_is_initialised: false,
-                                        station_url: vec![url],
-                                        media_details: None,
-                                    },
-                                };
+                            let podcast_channel = RealTimeDataOnOneChannel {
+                                artist: String::new(),
+                                address_to_ping: get_ip_address(url.as_str()),
+                                index_to_current_track: 0,
+                                position: ClockTime::ZERO,
+                                duration: None,
+                                channel_data: ChannelFileDataDecoded {
+                                    organisation: format!(
+                                        "{} {}",
+                                        episode_data_for_one_podcast_downloaded.channel_title, // eg "the Archers"
+                                        episode_data_for_one_podcast_downloaded
+                                            .data_for_multiple_episodes[episode_index]
+                                            .subtitle
+                                    ),
+                                    source_type: SourceType::UrlList,
+                                    last_track_is_a_ding: false,
+                                    pause_before_playing_ms: None,
+                                    random_tracks_wanted: false,
+                                    data_is_initialised: false,
+                                    volume: None,
+                                    playlist_file: None,
+                                    ping_address: None,
+                                    force_mono: false,
+                                    icon: None,
+                                    eq: None,
+                                    http_authentication: None,
+                                    station_url: vec![url],
+                                    media_details: None,
+                                },
+                                samba_reconnect_attempts: 0,
+                                stream_reconnect_attempts: 0,
+                            };
+                            if let Some(channel) =
+                                status_of_rradio.channel_mut(PODCAST_CHANNEL_NUMBER)
+                            {
+                                *channel = podcast_channel;
+                            }
                             status_of_rradio.channel_number = PODCAST_CHANNEL_NUMBER;
+                            status_of_rradio.stop_at_end_of_album = false;
                             status_of_rradio.initialise_for_new_station();
+                            play_channel::apply_channel_volume(
+                                &mut status_of_rradio,
+                                &config,
+                                &mut playbin,
+                            );
                             if let Err(playbin_error_message) =
-                                playbin.play_track(&mut status_of_rradio, &config, &mut lcd, true)
+                                playbin.play_track(
+                                    &mut status_of_rradio,
+                                    &config,
+                                    &mut lcd,
+                                    true,
+                                    &command_runner,
+                                )
                             {
-                                status_of_rradio.all_4lines.update_if_changed(
+                                status_of_rradio.show_error_on_all_4lines(
                                     format!("In main: When playing a track on channel {} got {playbin_error_message}", status_of_rradio.channel_number)
                                         .as_str());
-                                status_of_rradio.running_status =
-                                    RunningStatus::LongMessageOnAll4Lines;
                             } else {
                                 // play worked
+                                status_of_rradio.last_error = None;
 
-                                let line2 = previous_or_nextrack::generate_line2(&status_of_rradio);
+                                let line2 = previous_or_nextrack::generate_line2(
+                                    &status_of_rradio,
+                                    &command_runner,
+                                );
                                 status_of_rradio
                                     .line_2_data
                                     .update_if_changed(line2.as_str())
@@ -688,7 +1208,9 @@ async fn main() -> Result<(), String> {
                                 web_data_changed_tx.send(web::DataChanged::CanSeekForwards(None));
                         }
                         web::Event::PlayPause => {
-                            // user on a web client has hit the play/pause button
+                            // user on a web client has hit the play/pause button,
+                            // so any deferred play is no longer wanted
+                            status_of_rradio.deferred_play = None;
                             let new_state =
                                 if status_of_rradio.gstreamer_state == gstreamer::State::Playing {
                                     gstreamer::State::Paused
@@ -822,7 +1344,7 @@ async fn main() -> Result<(), String> {
                         }
                         web::Event::RequestRRadioStatusReport { report_tx } => {
                             if report_tx
-                                .send(status_of_rradio.generate_rradio_report())
+                                .send(status_of_rradio.generate_rradio_report(&command_runner))
                                 .is_err()
                             {
                                 eprintln!("Failed to send RRadio Status Report to web worker\r");
@@ -928,6 +1450,7 @@ async fn main() -> Result<(), String> {
                                                         &mut playbin,
                                                         &config,
                                                         &mut lcd,
+                                                        &command_runner,
                                                     );
                                                 } else if !content_type
                                                     .starts_with("application/xml")
@@ -1004,8 +1527,8 @@ async fn main() -> Result<(), String> {
                                     }
                                 };
                             } else if let Ok(channel_number) = new_text_from_user.parse::<usize>()
-                                && new_text_from_user.len() == 2 &&
-                            // it is numeric & 2 digits long (channels are two digits)
+                                && new_text_from_user.len() == config.channel_digits &&
+                            // it is numeric & config.channel_digits digits long
                             play_channel::play_channel(
                                     channel_number,
                                     &mut status_of_rradio,
@@ -1013,7 +1536,9 @@ async fn main() -> Result<(), String> {
                                     &mut playbin,
                                     &mut lcd,
                                     &web_data_changed_tx,
+                                    &command_runner,
                                 )
+                                .await
                                 .is_err()
                             {
                                 let _ = playbin.set_state(gstreamer::State::Null);
@@ -1022,7 +1547,117 @@ async fn main() -> Result<(), String> {
                         } // else do nothing as either the user is in the process of entering a valid channel or the input is obviously wrong
                     },
                     Some(Event::Ticker(_now)) => {
-                        let now = chrono::Local::now().format("%H:%M:%S").to_string();
+                        now_playing_log.flush_if_due();
+                        if let Err(error_message) =
+                            playbin.play_deferred_track_if_due(&mut status_of_rradio, &config)
+                        {
+                            status_of_rradio.show_error_on_all_4lines(error_message.as_str());
+                        }
+                        if let Err(error_message) = playbin.seek_if_ready(&mut status_of_rradio) {
+                            status_of_rradio.show_error_on_all_4lines(error_message.as_str());
+                        }
+                        play_channel::reconnect_stream_if_due(
+                            &mut status_of_rradio,
+                            &config,
+                            &playbin,
+                            &mut lcd,
+                            &command_runner,
+                        );
+                        if let Some(previous_channel) = play_channel::end_station_preview_if_due(
+                            &mut status_of_rradio,
+                            &config,
+                            &mut playbin,
+                        ) {
+                            play_station(
+                                previous_channel,
+                                &mut status_of_rradio,
+                                &config,
+                                &mut playbin,
+                                &mut lcd,
+                                &web_data_changed_tx,
+                                &command_runner,
+                            )
+                            .await;
+                        }
+                        status_of_rradio.advance_info_rotation(&config);
+                        if last_system_mute_check.elapsed()
+                            >= get_mute_state::SYSTEM_MUTE_CHECK_INTERVAL
+                        {
+                            last_system_mute_check = std::time::Instant::now();
+                            let system_muted = get_mute_state::system_is_muted(
+                                &command_runner,
+                                &config.mixer_control,
+                                status_of_rradio.gstreamer_state,
+                            )
+                            .await;
+                            if system_muted != status_of_rradio.system_muted {
+                                println!("The system mute state is now {system_muted}\r");
+                                status_of_rradio.system_muted = system_muted;
+                            }
+                        }
+                        if last_network_check.elapsed() >= get_local_ip_address::NETWORK_CHECK_INTERVAL {
+                            last_network_check = std::time::Instant::now();
+                            if status_of_rradio
+                                .check_network_while_connecting(&command_runner)
+                                .await
+                            {
+                                println!(
+                                    "Wi-Fi has finished connecting with state {:?}\r",
+                                    status_of_rradio.network_data.state
+                                );
+                                status_of_rradio.line_1_data.update_if_changed(
+                                    format!(
+                                        "{} {}",
+                                        status_of_rradio.network_data.local_ip_address,
+                                        lcd::Lc::get_vol_string(&status_of_rradio, &config)
+                                    )
+                                    .as_str(),
+                                );
+                                // we waited for the Wi-Fi, but do not cut off the startup ding if it is still playing
+                                if !(status_of_rradio.channel_number
+                                    == player_status::START_UP_DING_CHANNEL_NUMBER
+                                    && status_of_rradio.gstreamer_state == gstreamer::State::Playing)
+                                    && let Some(autoplay_channel) = autoplay_channel_pending.take()
+                                {
+                                    play_station(
+                                        autoplay_channel,
+                                        &mut status_of_rradio,
+                                        &config,
+                                        &mut playbin,
+                                        &mut lcd,
+                                        &web_data_changed_tx,
+                                        &command_runner,
+                                    )
+                                    .await;
+                                }
+                            }
+                        }
+                        if status_of_rradio.playing_has_stalled(&config) {
+                            println!("Playing has stalled, so restarting the track\r");
+                            if let Err(error_message) =
+                                playbin.play_track(
+                                    &mut status_of_rradio,
+                                    &config,
+                                    &mut lcd,
+                                    true,
+                                    &command_runner,
+                                )
+                            {
+                                status_of_rradio.show_error_on_all_4lines(
+                                    format!("When restarting a stalled track got {error_message}")
+                                        .as_str(),
+                                );
+                            }
+                        }
+                        let backlight_wanted =
+                            !config.backlight_should_be_off(config.clock_now().time());
+                        if backlight_wanted != backlight_is_on {
+                            match lcd.set_backlight(backlight_wanted) {
+                                Ok(()) => backlight_is_on = backlight_wanted,
+                                Err(lcd_error) => eprintln!("{lcd_error}\r"),
+                            }
+                        }
+                        let now = config.clock_now().format("%H:%M:%S").to_string();
                         // this for loop migh tfail to spot a wanted time match if some_timer has an interval that is not significantly shorter than 1 second
                         for one_start_time in config.start_times.iter() {
                             if one_start_time.time == now {
@@ -1045,75 +1680,140 @@ async fn main() -> Result<(), String> {
                                     &mut playbin,
                                     &mut lcd,
                                     &web_data_changed_tx,
+                                    &command_runner,
                                 )
+                                .await
                                 .is_err()
                                 {
                                     eprintln!("Failed to start channel when requested");
                                 };
                             }
                         }
+                    }
+                    Some(Event::PositionPoll(_now)) => {
                         if status_of_rradio.channel_number
-                            <= player_status::NUMBER_OF_POSSIBLE_CHANNELS
+                            <= player_status::NUMBER_OF_POSSIBLE_CHANNELS // the ding channel has no position worth keeping
+                            && !status_of_rradio.station_announcement_playing // the position is that of the announcement, not the channel
                             && let Some(position) = playbin
                                 .playbin_element
                                 .query_position::<gstreamer::ClockTime>()
+                            && let Some(channel) = status_of_rradio.current_channel_mut()
                         {
-                            status_of_rradio.position_and_duration
-                                [status_of_rradio.channel_number]
-                                .position = position;
+                            if channel.samba_reconnect_attempts > 0 && position > channel.position {
+                                println!("Reconnected to the Samba share\r");
+                                // so that the next drop out gets all its attempts
+                                channel.samba_reconnect_attempts = 0;
+                            }
+                            channel.position = position;
 
                             let duration = playbin.playbin_element.query_duration();
+                            channel.duration = duration;
 
-                            status_of_rradio.position_and_duration
-                                [status_of_rradio.channel_number]
-                                .duration = duration;
-
-                            match status_of_rradio.position_and_duration
-                                [status_of_rradio.channel_number]
-                                .channel_data
-                                .source_type
-                            {
+                            match channel.channel_data.source_type {
                                 SourceType::Cd | SourceType::Usb => {
                                     let _ = web_data_changed_tx
                                         .send(web::DataChanged::Position { position, duration });
                                 }
-                                SourceType::UnknownSource | SourceType::UrlList => { // do not send position to the web page as it is meaningless for these source types  
+                                SourceType::UnknownSource | SourceType::UrlList | SourceType::Playlist => { // do not send position to the web page as it is meaningless for these source types  
                                 }
                             }
                         }
                     }
                 }
+                if !lcd_refresh_wanted {
+                    continue; // position polls can be more frequent than LCD refreshes, so they do not rewrite the screen
+                }
+                let scroll_period_ms = status_of_rradio.scroll_period_ms;
                 status_of_rradio
                     .line_1_data
-                    .update_scroll(&config, lcd::NUM_CHARACTERS_PER_LINE);
+                    .update_scroll(&config, scroll_period_ms, lcd::NUM_CHARACTERS_PER_LINE);
+                status_of_rradio.startup_line_1_data.update_scroll(
+                    &config,
+                    scroll_period_ms,
+                    lcd::NUM_CHARACTERS_PER_LINE,
+                );
 
-                status_of_rradio
-                    .line_2_data
-                    .update_scroll(&config, lcd::NUM_CHARACTERS_PER_LINE);
+                let line_2_icon_cells = usize::from(status_of_rradio.line_2_icon().is_some());
+                status_of_rradio.line_2_data.update_scroll(
+                    &config,
+                    scroll_period_ms,
+                    lcd::NUM_CHARACTERS_PER_LINE - line_2_icon_cells,
+                );
 
                 let space_needed_for_buffer = if status_of_rradio.channel_number
                     <= NUMBER_OF_POSSIBLE_CHANNELS
-                    && status_of_rradio.position_and_duration[status_of_rradio.channel_number]
-                        .channel_data
-                        .source_type
-                        == SourceType::UrlList
+                    && status_of_rradio.current_channel().is_some_and(|channel| {
+                        matches!(
+                            channel.channel_data.source_type,
+                            SourceType::UrlList | SourceType::Playlist
+                        )
+                    })
                 {
                     3 // we need space to display the buffer
                 } else {
                     0 // we do not need space as it is not a URL list
                 };
 
-                status_of_rradio.line_34_data.update_scroll(
-                    &config,
-                    lcd::NUM_CHARACTERS_PER_LINE * 2 - space_needed_for_buffer,
-                );
+                if config.split_title_artist {
+                    status_of_rradio
+                        .line_3_data
+                        .update_scroll(&config, scroll_period_ms, lcd::NUM_CHARACTERS_PER_LINE);
+                    status_of_rradio.line_4_data.update_scroll(
+                        &config,
+                        scroll_period_ms,
+                        lcd::NUM_CHARACTERS_PER_LINE - space_needed_for_buffer,
+                    );
+                } else {
+                    status_of_rradio.line_34_data.update_scroll(
+                        &config,
+                        scroll_period_ms,
+                        lcd::NUM_CHARACTERS_PER_LINE * 2 - space_needed_for_buffer,
+                    );
+                }
                 status_of_rradio
                     .all_4lines
-                    .update_scroll(&config, lcd::NUM_CHARACTERS_PER_LINE * 4);
-                lcd.write_rradio_status_to_lcd(&status_of_rradio, &config);
+                    .update_scroll(&config, scroll_period_ms, lcd::NUM_CHARACTERS_PER_LINE * 4);
+                if let Some(test_pattern_start) = lcd_test_pattern_start {
+                    // show each test pattern for a second
+                    let mut text_buffer = lcd::TextBuffer::new();
+                    lcd::Lc::fill_text_buffer_with_test_pattern(
+                        &mut text_buffer,
+                        test_pattern_start.elapsed().as_secs() as usize
+                            % lcd::NUMBER_OF_TEST_PATTERNS,
+                    );
+                    if let Err(lcd_error) = lcd.write_text_buffer_to_lcd(&text_buffer) {
+                        eprintln!("{lcd_error}\r");
+                    }
+                } else {
+                    // the screen is rewritten every time round the loop, so a failed write is retried automatically;
+                    // we only report the first failure & the recovery so as not to flood the output
+                    match lcd.write_rradio_status_to_lcd(
+                        &status_of_rradio,
+                        &config,
+                        &command_runner,
+                    ) {
+                        Ok(()) => {
+                            if lcd_write_has_failed {
+                                println!("Writing to the LCD screen works again\r");
+                                lcd_write_has_failed = false;
+                            }
+                        }
+                        Err(lcd_error) => {
+                            if !lcd_write_has_failed {
+                                eprintln!("{lcd_error}\r");
+                                lcd_write_has_failed = true;
+                            }
+                            // the screen may have been unplugged & plugged back in
+                            if lcd.needs_reopening() && lcd.reopen().is_ok() {
+                                println!("Reopened the LCD screen\r");
+                            }
+                        }
+                    }
+                }
             } // closing parentheses of loop
 
-            if let Ok(wait_result) = child_ping.wait()
+            if let Some(child_ping) = &mut child_ping
+                && let Ok(wait_result) = child_ping.wait()
             // we need to have a wait on the ping in order to keep the compiler happy
                 && !wait_result.success()
             {
@@ -1121,11 +1821,13 @@ async fn main() -> Result<(), String> {
             }
         }
         Err(message) => {
-            status_of_rradio
-                .all_4lines
-                .update_if_changed(format!("Failed to get a playbin: {message}").as_str());
-            status_of_rradio.running_status = RunningStatus::LongMessageOnAll4Lines;
-            lcd.write_rradio_status_to_lcd(&status_of_rradio, &config);
+            show_no_audio_until_shutdown(
+                format!("Failed to get a playbin: {message}").as_str(),
+                &mut lcd,
+                &config,
+            )
+            .await;
+            unmount_all(&mut status_of_rradio);
         }
     }
 
@@ -1133,7 +1835,109 @@ async fn main() -> Result<(), String> {
     //or an error, as nothing has failed, we give the "all worked OK termination" value
 }
 
-/// Changes the volume by config.volume_offset dB up or down as controlled by "direction".
+/// Used when gstreamer cannot be used, so nothing can be played.
+/// Shows the message on the screen together with the date, time, temperature & Wi-Fi signal strength,
+/// so it is obvious that the unit is alive even though there is no audio, until SIGTERM or SIGINT is received.
+async fn show_no_audio_until_shutdown(
+    message: &str,
+    lcd: &mut lcd::Lc,
+    config: &read_config::Config,
+) {
+    eprintln!("{message}\r");
+    let mut sigterm =
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).ok();
+    let mut sigint =
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt()).ok();
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(1));
+    let message = ScrollData::new(message, 2);
+    loop {
+        let mut text_buffer = TextBuffer::new();
+        text_buffer.write_text_to_lines(message.bytes(), lcd::LineNum::Line1, 2);
+        text_buffer.write_text_to_single_line(
+            lcd::Lc::get_current_date_and_time_text(config, config.clock_24h).bytes(),
+            lcd::LineNum::Line3,
+        );
+        text_buffer.write_text_to_single_line(
+            lcd::Lc::get_temperature_and_wifi_strength_text(config.wifi_bars_character).bytes(),
+            lcd::LineNum::Line4,
+        );
+        if let Err(lcd_error) = lcd
+            .update_wifi_bars_character(config)
+            .and_then(|()| lcd.write_text_buffer_to_lcd(&text_buffer))
+        {
+            eprintln!("{lcd_error}\r");
+        }
+
+        let shutdown_wanted = std::future::poll_fn(|cx| {
+            for signal in [sigterm.as_mut(), sigint.as_mut()].into_iter().flatten() {
+                if signal.poll_recv(cx).is_ready() {
+                    return Poll::Ready(true);
+                }
+            }
+            ticker.poll_tick(cx).map(|_instant| false)
+        })
+        .await;
+        if shutdown_wanted {
+            lcd.clear();
+            return;
+        }
+    }
+}
+
+/// Plays the specified channel as if the user had entered its number on the keyboard.
+/// If it cannot be played, gstreamer is stopped so the error message stays on the screen.
+async fn play_station(
+    channel_number: usize,
+    status_of_rradio: &mut PlayerStatus,
+    config: &read_config::Config,
+    playbin: &mut PlaybinElement,
+    lcd: &mut lcd::Lc,
+    web_data_changed_tx: &tokio::sync::broadcast::Sender<DataChanged>,
+    command_runner: &impl command_runner::CommandRunner,
+) {
+    if play_channel::play_channel(
+        channel_number,
+        status_of_rradio,
+        config,
+        playbin,
+        lcd,
+        web_data_changed_tx,
+        command_runner,
+    )
+    .await
+    .is_err()
+    {
+        let _ = playbin.set_state(gstreamer::State::Null);
+        // even if it does not stop is does matter much & we do not want to hide the error message
+    }
+}
+
+/// Unmutes the ALSA mixer, & the DigiAMP+ amplifier if playing, in case something outside rradio has muted them
+async fn unmute_system(
+    status_of_rradio: &mut PlayerStatus,
+    config: &read_config::Config,
+    command_runner: &impl command_runner::CommandRunner,
+) {
+    if let Err(error_message) =
+        get_mute_state::unmute_mixer(command_runner, &config.mixer_control).await
+    {
+        status_of_rradio.show_error_on_all_4lines(error_message.as_str());
+        return;
+    }
+    if status_of_rradio.gstreamer_state == gstreamer::State::Playing {
+        set_mute_state(gstreamer::State::Playing);
+    }
+    status_of_rradio.system_muted = get_mute_state::system_is_muted(
+        command_runner,
+        &config.mixer_control,
+        status_of_rradio.gstreamer_state,
+    )
+    .await;
+    status_of_rradio.line_1_brief_message =
+        Some(("System unmuted".to_string(), chrono::Utc::now()));
+}
+
+/// Changes the volume by config.volume_offset dB, or to the adjacent entry of config.volume_steps, up or down as controlled by "direction".
 /// Checks are made that the volume remains in bounds.
 fn change_volume(
     direction: i32,
@@ -1147,11 +1951,14 @@ fn change_volume(
         "direction must be plus or minus 1 to change the volume, or zero to merely output the current volume"
     );
     status_of_rradio.current_volume =
-        (status_of_rradio.current_volume + config.volume_offset * direction).clamp(
-            gstreamer_interfaces::VOLUME_MIN,
-            gstreamer_interfaces::VOLUME_MAX,
-        );
-    if let Err(error_message) = playbin.set_volume(status_of_rradio.current_volume) {
+        config.next_volume(status_of_rradio.current_volume, direction);
+    if direction != 0 {
+        playbin.cancel_fade_in(); // a manual volume change takes precedence over a fade in
+    }
+    // if we are fading in, the fade in will end at the playing volume, so we must not jump to it now
+    if !playbin.is_fading_in()
+        && let Err(error_message) = playbin.set_volume(status_of_rradio.playing_volume(config))
+    {
         eprintln!("When changing the volume got error {}\r", error_message);
     }
 
@@ -1,14 +1,50 @@
+use crate::command_runner::CommandRunner;
 use crate::get_channel_details::{self, ChannelErrorEvents};
+use crate::lcd::{LineNum, TextBuffer};
 use crate::read_config::MediaDetails;
 use std::fs;
 
+/// The number of times we try to mount a local memory stick; a freshly inserted stick is sometimes not ready at first
+const MOUNT_ATTEMPTS: u32 = 3;
+/// The time we wait before trying again to mount a local memory stick
+const DELAY_BETWEEN_MOUNT_ATTEMPTS: std::time::Duration = std::time::Duration::from_millis(200);
+/// The Samba protocol versions we try, newest first, if the server rejects the default one & the user did not specify a version
+const SAMBA_VERSIONS_TO_TRY: [&str; 4] = ["3.1.1", "3.0", "2.1", "1.0"];
+
+/// Tells the user that we are mounting, as retrying can take a noticeable time.
+/// Does nothing if there is nothing to mount or it is already mounted.
+pub fn write_mounting_message_to_lcd(
+    media_details_as_option: &Option<MediaDetails>,
+    lcd: &mut crate::lcd::Lc,
+) {
+    if media_details_as_option
+        .as_ref()
+        .is_some_and(|media_details| !media_details.is_mounted)
+    {
+        let message = if media_details_as_option
+            .as_ref()
+            .is_some_and(|media_details| media_details.device.starts_with("//"))
+        {
+            "Mounting Samba..."
+        } else {
+            "Mounting USB..."
+        };
+        let mut text_buffer = TextBuffer::new();
+        text_buffer.write_text_to_single_line(message.bytes(), LineNum::Line1);
+        if let Err(lcd_error) = lcd.write_text_buffer_to_lcd(&text_buffer) {
+            eprintln!("{lcd_error}\r");
+        }
+    }
+}
+
 /// Mounts Samba share or local memory stick.
 /// Returns the mount folder if the mount is successful.
 pub fn mount_memory_stick_option(
     media_details_as_option: &mut Option<MediaDetails>,
+    command_runner: &impl CommandRunner,
 ) -> Result<String, ChannelErrorEvents> {
     if let Some(media_details) = media_details_as_option {
-        mount_memory_stick(media_details)
+        mount_memory_stick(media_details, command_runner)
     } else {
         Ok(String::new())
     }
@@ -16,10 +52,108 @@ pub fn mount_memory_stick_option(
 
 /// Mounts a memory stick using Samba or CIFS; sets is_mounted = true if successful
 /// & returns the mount folder if the mount is successful.
-pub fn mount_memory_stick(media_details: &mut MediaDetails) -> Result<String, ChannelErrorEvents> {
+/// A local memory stick that is missing or not ready is tried MOUNT_ATTEMPTS times before giving up.
+pub fn mount_memory_stick(
+    media_details: &mut MediaDetails,
+    command_runner: &impl CommandRunner,
+) -> Result<String, ChannelErrorEvents> {
+    let mut attempt = 1;
+    loop {
+        match try_to_mount_memory_stick(media_details, command_runner) {
+            Err(
+                ChannelErrorEvents::NoUSBDevice | ChannelErrorEvents::NoSuchDeviceOrDirectory(_),
+            ) if attempt < MOUNT_ATTEMPTS && !media_details.device.starts_with("//") => {
+                println!("USB device not ready; trying again\r");
+                std::thread::sleep(DELAY_BETWEEN_MOUNT_ATTEMPTS);
+                attempt += 1;
+            }
+            Err(ChannelErrorEvents::SambaVersionRejected { .. })
+                if media_details.version.is_none() =>
+            {
+                return mount_samba_trying_each_version(media_details, command_runner);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Tries to mount a Samba share with each of the versions in SAMBA_VERSIONS_TO_TRY in turn, as the server rejected the default version.
+/// Returns the mount folder & says which version worked if one of them is accepted; that version is kept in media_details,
+/// so later mounts use it straight away.
+fn mount_samba_trying_each_version(
+    media_details: &mut MediaDetails,
+    command_runner: &impl CommandRunner,
+) -> Result<String, ChannelErrorEvents> {
+    for version in SAMBA_VERSIONS_TO_TRY {
+        println!(
+            "trying to mount {} with Samba version {version}\r",
+            media_details.device
+        );
+        let mut trial_media_details = media_details.clone();
+        trial_media_details.version = Some(version.to_string());
+        match try_to_mount_memory_stick(&mut trial_media_details, command_runner) {
+            Ok(mount_folder) => {
+                println!(
+                    "mounted {} using Samba version {version}\r",
+                    media_details.device
+                );
+                media_details.is_mounted = true; // as we handed over a clone, we have to manually set this true
+                media_details.version = Some(version.to_string()); // & remember the version that worked
+                return Ok(mount_folder);
+            }
+            Err(ChannelErrorEvents::SambaVersionRejected { .. }) => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Err(ChannelErrorEvents::SambaVersionRejected {
+        device: media_details.device.clone(),
+        version: SAMBA_VERSIONS_TO_TRY.join(", "),
+    })
+}
+
+/// Returns true if the operating system error returned when mounting a Samba share means that the server does not accept
+/// the protocol version; EINVAL is only treated as a version problem if the version was specified, as then it is probably not a valid one.
+fn samba_version_was_rejected(error_number: i32, version_was_specified: bool) -> bool {
+    // the value returned by the operating system if the server does not support the protocol version, eg it only supports SMB1;
+    // EHOSTDOWN is not included, as it means the server could not be reached, which trying other versions cannot fix
+    const OS_ERROR_OPERATION_NOT_SUPPORTED: i32 = 95;
+    // the value returned by the operating system if the version is not one it recognises
+    const OS_ERROR_INVALID_ARGUMENT: i32 = 22;
+    match error_number {
+        OS_ERROR_OPERATION_NOT_SUPPORTED => true,
+        OS_ERROR_INVALID_ARGUMENT => version_was_specified,
+        _ => false,
+    }
+}
+
+/// Makes a single attempt to mount a memory stick using Samba or CIFS; sets is_mounted = true if successful
+/// & returns the mount folder if the mount is successful.
+fn try_to_mount_memory_stick(
+    media_details: &mut MediaDetails,
+    command_runner: &impl CommandRunner,
+) -> Result<String, ChannelErrorEvents> {
     if media_details.is_mounted {
-        println!("Device is already mounted {:?}\r", &media_details.device);
-        return Ok(media_details.mount_folder.clone()); // it is already mounted
+        // a local memory stick may have been pulled out while it was mounted, especially if config.keep_usb_mounted is true
+        if media_details.device.starts_with("/dev/")
+            && !std::path::Path::new(&media_details.device).exists()
+        {
+            println!(
+                "{} has been removed, so unmounting it\r",
+                media_details.device
+            );
+            if let Err(error) =
+                sys_mount::unmount(&media_details.mount_folder, sys_mount::UnmountFlags::DETACH)
+            {
+                eprintln!(
+                    "Failed to unmount the removed device from {}; got error {error}\r",
+                    media_details.mount_folder
+                );
+            }
+            media_details.is_mounted = false;
+        } else {
+            println!("Device is already mounted {:?}\r", &media_details.device);
+            return Ok(media_details.mount_folder.clone()); // it is already mounted
+        }
     }
 
     let mut new_details = media_details.clone();
@@ -27,7 +161,7 @@ pub fn mount_memory_stick(media_details: &mut MediaDetails) -> Result<String, Ch
         for usb_counter in 1..9 {
             // 1 to 9 as memory sticks start at 1 unlike CDs
             new_details.device = format!("/dev/sda{}", usb_counter);
-            match mount_memory_stick(&mut new_details) {
+            match try_to_mount_memory_stick(&mut new_details, command_runner) {
                 Ok(good_result) => {
                     media_details.is_mounted = true;
                     // as we handed over a clone, not the real thing, we have to manually set this true
@@ -63,10 +197,14 @@ pub fn mount_memory_stick(media_details: &mut MediaDetails) -> Result<String, Ch
         data_string = format!("{},iocharset=utf8", data_string); // add on chracter sets
 
         if media_details.disk_identifier.is_some() {
-            return mount_exact_drive_unknown(media_details); // mount_media_cannot handle it, so use mount_exact_drive_unknown, which can
+            // mount_media_cannot handle it, so use mount_exact_drive_unknown, which can
+            return mount_exact_drive_unknown(media_details, command_runner);
         };
     } else {
         println!("mounting local mem stick\r");
+        if !std::path::Path::new(&media_details.device).exists() {
+            return Err(ChannelErrorEvents::NoUSBDevice); // the device node is missing, so there is no point trying to mount it
+        }
         fstype = "vfat";
         data_string = format!("{},iocharset=utf8,utf8", data_string); // add on chracter sets
     }
@@ -84,22 +222,56 @@ pub fn mount_memory_stick(media_details: &mut MediaDetails) -> Result<String, Ch
 
         Err(mount_error) => {
             eprintln!(
-                "when trying to mount {} got Samba mount error {:?}\r",
+                "when trying to mount {} got mount error {:?}\r",
                 media_details.device, mount_error
             );
 
             // the value returned by the operating system if there is no device
             const OS_ERROR_NO_SUCH_DEVICE_OR_ADDRESS: i32 = 6;
             const OS_RESOURCE_BUSY: i32 = 16;
+            // the value returned by the operating system if the device does not contain a filesystem it can mount
+            const OS_ERROR_INVALID_ARGUMENT: i32 = 22;
             let mount_error_as_option = mount_error.raw_os_error();
             media_details.is_mounted = false; // whatever the previous status was, now we have failed
+            let is_samba = media_details.device.starts_with("//");
+            if is_samba
+                && mount_error_as_option.is_some_and(|error_number| {
+                    samba_version_was_rejected(error_number, media_details.version.is_some())
+                })
+            {
+                return Err(ChannelErrorEvents::SambaVersionRejected {
+                    device: media_details.device.clone(),
+                    version: media_details
+                        .version
+                        .clone()
+                        .unwrap_or_else(|| "default".to_string()),
+                });
+            }
+            // so that the user is told whether it is the Samba share or the USB device that could not be mounted
+            let mount_error_event = |error_message: String| {
+                if is_samba {
+                    ChannelErrorEvents::SambaMountError(error_message)
+                } else {
+                    ChannelErrorEvents::UsbMountMountError(error_message)
+                }
+            };
             match mount_error_as_option {
+                // for a Samba share, these mean the share is missing or the server will not share it
+                Some(
+                    get_channel_details::OS_ERROR_NO_SUCH_FILE_OR_DIRECTORY
+                    | OS_ERROR_INVALID_ARGUMENT,
+                ) if is_samba => Err(ChannelErrorEvents::SambaShareNotMountable(
+                    media_details.device.clone(),
+                )),
                 Some(get_channel_details::OS_ERROR_NO_SUCH_FILE_OR_DIRECTORY) => {
                     Err(ChannelErrorEvents::NoUSBDevice)
                 }
                 Some(OS_ERROR_NO_SUCH_DEVICE_OR_ADDRESS) => Err(
                     ChannelErrorEvents::NoSuchDeviceOrDirectory(media_details.device.clone()),
                 ),
+                Some(OS_ERROR_INVALID_ARGUMENT) => Err(
+                    ChannelErrorEvents::UsbFilesystemNotMountable(media_details.device.clone()),
+                ),
                 Some(OS_RESOURCE_BUSY) => {
                     // as it is already mounted, we do not need to do mount it again
                     println!("media already mounted\r");
@@ -107,13 +279,11 @@ pub fn mount_memory_stick(media_details: &mut MediaDetails) -> Result<String, Ch
 
                     Ok(media_details.mount_folder.clone())
                 }
-                Some(error_number) => Err(ChannelErrorEvents::UsbMountMountError(format!(
+                Some(error_number) => Err(mount_error_event(format!(
                     "Got Operating System error {} ",
                     error_number
                 ))),
-                None => Err(ChannelErrorEvents::UsbMountMountError(
-                    mount_error.kind().to_string(),
-                )),
+                None => Err(mount_error_event(mount_error.kind().to_string())),
             }
         }
     }
@@ -123,24 +293,25 @@ pub fn mount_memory_stick(media_details: &mut MediaDetails) -> Result<String, Ch
 /// Chooses the share where media_details.disk_identifier matches the one specified in
 fn mount_exact_drive_unknown(
     media_details: &mut MediaDetails,
+    command_runner: &impl CommandRunner,
 ) -> Result<String, ChannelErrorEvents> {
     // enumerate the Samba shares using the smbclient command.
     // the format depends on whether or not a password is supplied
 
     let samba_command_as_result = if let Some(auth_data) = &media_details.authentication_data {
-        std::process::Command::new("/bin/smbclient")
-            .args([
+        command_runner.output(
+            "/bin/smbclient",
+            &[
                 "-L",                  // IP address is the next parameter
                 &media_details.device, // the IP address of the Samba share
                 "-g", // sets the output format to be one we expect (easier to machine parse)
                 "-U", // Username & password about to follow, separated by the "%" character
                 format!("{}%{}", auth_data.username, auth_data.password).as_str(),
-            ])
-            .output()
+            ],
+        )
     } else {
-        std::process::Command::new("/bin/smbclient")
-            .args(["-N", "-L", &media_details.device, "-g"])
-            .output() // -N in previous line means no username and password 
+        // -N means no username and password
+        command_runner.output("/bin/smbclient", &["-N", "-L", &media_details.device, "-g"])
     };
     match samba_command_as_result {
         Ok(output) => {
@@ -159,7 +330,7 @@ fn mount_exact_drive_unknown(
 
                         local_media_details.device = new_device;
                         local_media_details.disk_identifier = None; // set to None so we use the simpler mount function 
-                        match mount_memory_stick(&mut local_media_details) {
+                        match mount_memory_stick(&mut local_media_details, command_runner) {
                             Ok(mount_folder) => match fs::read_dir(&mount_folder) {
                                 Ok(read_dir) => {
                                     if let Some(disk_identifier) = &media_details.disk_identifier {
@@ -179,9 +350,10 @@ fn mount_exact_drive_unknown(
                                         mount_folder.as_str(),
                                         sys_mount::UnmountFlags::DETACH,
                                     ) {
-                                        return Err(ChannelErrorEvents::UsbMountMountError(
-                                            format!("Got unmount error {}", error),
-                                        ));
+                                        return Err(ChannelErrorEvents::SambaMountError(format!(
+                                            "Got unmount error {}",
+                                            error
+                                        )));
                                     }
                                     local_media_details.is_mounted = false;
                                 }
@@ -209,8 +381,6 @@ fn mount_exact_drive_unknown(
                 ))
             }
         }
-        Err(error) => Err(ChannelErrorEvents::CouldNotEnumerateSamba(
-            error.to_string(),
-        )),
+        Err(error) => Err(ChannelErrorEvents::CouldNotEnumerateSamba(error)),
     }
 }
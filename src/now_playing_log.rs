@@ -0,0 +1,157 @@
+// keeps a history of what has been played, so the user can find out what played overnight
+use std::io::Write;
+
+use crate::player_status::PlayerStatus;
+
+/// If the log file gets bigger than this, it is truncated so we do not fill the SD card
+const MAXIMUM_LOG_FILE_SIZE: u64 = 1024 * 1024;
+
+/// How often the buffered lines are written to the file; we do not write every line as it arrives
+/// as writing to an SD card can be slow
+const FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[derive(Debug)]
+/// Buffers the "now playing" lines & writes them to the file specified by config.now_playing_log
+pub struct NowPlayingLog {
+    /// None if the user does not want a log
+    file_path: Option<String>,
+    /// lines waiting to be written to the file
+    buffered_lines: String,
+    last_flush_time: std::time::Instant,
+}
+
+impl NowPlayingLog {
+    pub fn new(config: &crate::read_config::Config) -> NowPlayingLog {
+        NowPlayingLog {
+            file_path: config.now_playing_log.clone(),
+            buffered_lines: String::new(),
+            last_flush_time: std::time::Instant::now(),
+        }
+    }
+
+    /// Adds a line containing the local time, channel number, organisation & title to the buffer.
+    /// Does nothing if the user does not want a log.
+    pub fn record(&mut self, status_of_rradio: &PlayerStatus) {
+        if self.file_path.is_none() {
+            return;
+        }
+        self.buffered_lines.push_str(
+            format!(
+                "{}\tchannel {:02}\t{}\t{}\n",
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                status_of_rradio.channel_number,
+                status_of_rradio.position_and_duration[status_of_rradio.channel_number]
+                    .channel_data
+                    .organisation,
+                status_of_rradio.line_34_data.text
+            )
+            .as_str(),
+        );
+    }
+
+    /// Writes the buffered lines to the file if FLUSH_INTERVAL has elapsed since the last write
+    pub fn flush_if_due(&mut self) {
+        if self.last_flush_time.elapsed() >= FLUSH_INTERVAL {
+            self.flush();
+        }
+    }
+
+    /// Writes the buffered lines to the file, first truncating the file if it is bigger than MAXIMUM_LOG_FILE_SIZE.
+    /// Errors are printed, but otherwise ignored, as the log is not essential.
+    pub fn flush(&mut self) {
+        self.last_flush_time = std::time::Instant::now();
+        let Some(file_path) = &self.file_path else {
+            return;
+        };
+        if self.buffered_lines.is_empty() {
+            return;
+        }
+
+        let file_is_too_big = std::fs::metadata(file_path)
+            .map(|metadata| metadata.len() > MAXIMUM_LOG_FILE_SIZE)
+            .unwrap_or(false);
+
+        let write_result = std::fs::OpenOptions::new()
+            .create(true)
+            .append(!file_is_too_big)
+            .write(true)
+            .truncate(file_is_too_big)
+            .open(file_path)
+            .and_then(|mut file| file.write_all(self.buffered_lines.as_bytes()));
+
+        if let Err(error) = write_result {
+            eprintln!("Could not write to the now playing log {file_path}; got {error}\r");
+        }
+        self.buffered_lines.clear(); // even if the write failed, so the buffer does not grow without limit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a path in the temporary directory for a log file unique to the test & to this process
+    fn log_file_path(test_name: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "rradio_now_playing_{test_name}_test_{}.log",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path); // in case an earlier run with the same process id left it behind
+        path.to_string_lossy().to_string()
+    }
+
+    /// Returns a log that writes to the file, & a status whose current channel is playing the title
+    fn log_and_status(file_path: &str, title: &str) -> (NowPlayingLog, PlayerStatus) {
+        let config = crate::read_config::Config {
+            now_playing_log: Some(file_path.to_string()),
+            ..Default::default()
+        };
+        let mut status_of_rradio = PlayerStatus::new(&config);
+        status_of_rradio.channel_number = 5;
+        status_of_rradio.position_and_duration[5]
+            .channel_data
+            .organisation = "News".to_string();
+        status_of_rradio.line_34_data.update_if_changed(title);
+        (NowPlayingLog::new(&config), status_of_rradio)
+    }
+
+    #[test]
+    fn a_recorded_line_is_written_when_flushed() {
+        let file_path = log_file_path("flush");
+        let (mut now_playing_log, status_of_rradio) = log_and_status(&file_path, "The headlines");
+
+        now_playing_log.record(&status_of_rradio);
+        let before_flush = std::fs::read_to_string(&file_path);
+        now_playing_log.flush();
+        let after_flush = std::fs::read_to_string(&file_path);
+        let _ = std::fs::remove_file(&file_path);
+
+        assert!(
+            before_flush.is_err(),
+            "the line is buffered until it is flushed"
+        );
+        let after_flush = after_flush.expect("the log file was written");
+        assert_eq!(after_flush.lines().count(), 1, "{after_flush}");
+        assert!(
+            after_flush.ends_with("\tchannel 05\tNews\tThe headlines\n"),
+            "{after_flush}"
+        );
+    }
+
+    #[test]
+    fn a_log_file_that_is_too_big_is_truncated_before_the_append() {
+        let file_path = log_file_path("truncate");
+        let too_big = usize::try_from(MAXIMUM_LOG_FILE_SIZE).expect("fits in a usize") + 1;
+        std::fs::write(&file_path, "x".repeat(too_big)).expect("can write the test log file");
+        let (mut now_playing_log, status_of_rradio) = log_and_status(&file_path, "The weather");
+
+        now_playing_log.record(&status_of_rradio);
+        now_playing_log.flush();
+        let log = std::fs::read_to_string(&file_path);
+        let _ = std::fs::remove_file(&file_path);
+
+        let log = log.expect("the log file was written");
+        assert!(!log.contains('x'), "the old lines were not removed");
+        assert!(log.ends_with("\tNews\tThe weather\n"), "{log}");
+    }
+}
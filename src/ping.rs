@@ -1,14 +1,20 @@
 // sends & receives pings & gets the ping time
-use std::process::{Command, Stdio};
 //use pnet_packet::ip;
 
 use crate::{
+    command_runner::CommandRunner,
     get_channel_details,
     lcd::RunningStatus,
     player_status::{self, NUMBER_OF_POSSIBLE_CHANNELS},
 };
 
-#[derive(Debug)]
+/// The interval between packets when config.ping_count is more than 1; ping's default of 1 second would make the result stale
+const PING_INTERVAL_MS: u64 = 200;
+
+/// The minimum time between pings, so we do not ping too often
+const MINIMUM_MS_BETWEEN_PINGS: u64 = 3000;
+
+#[derive(Debug, serde::Serialize)]
 /// Stores the ping time returned as an option (timeout => none()
 pub struct PingTimeAndDestination {
     /// If it times out, there is no time to include; it that case, it returns None
@@ -16,7 +22,7 @@ pub struct PingTimeAndDestination {
     pub destination: PingWhere,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, serde::Serialize)]
 /// Stores the address being pinged, either local, remote or nothing
 pub enum PingWhere {
     Local,
@@ -50,12 +56,13 @@ impl PingWhere {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize)]
 /// Used to store the data about the pings
 pub struct PingData {
     /// true if we can send a ping
     pub can_send_ping: bool,
     /// time of day the last ping was sent; used to ensure we do not ping too often
+    #[serde(serialize_with = "player_status::serialize_using_debug")]
     pub last_ping_time_of_day: chrono::DateTime<chrono::Utc>, // the time the last ping was sent; used so we do not ping too often
     /// the time the ping took & the destination, local, remote or nothing.
     pub ping_time_and_destination: PingTimeAndDestination,
@@ -63,17 +70,30 @@ pub struct PingData {
 }
 
 /// Sends a ping to the local or remote address as required.
-/// Panics if it cannot ping.
+/// If ping cannot be started, the error is printed & None is returned, so we try again later.
 /// Sets can_send_ping to false as cannot ping again until we have received a response.
 /// When we have sent more than max_number_of_remote_pings, all the pings go to the router
 /// so as not to cause the remote site to be concerned about the number of pings.
 /// (The display routine displays the temperature instead of the remote ping)
+/// Returns None without pinging if the current channel is a CD or a USB device (local or Samba)
+/// as there is no network destination worth pinging.
 pub fn send_ping(
     status_of_rradio: &mut player_status::PlayerStatus,
     config: &crate::read_config::Config,
-) -> std::process::Child {
+    command_runner: &impl CommandRunner,
+) -> Option<std::process::Child> {
     status_of_rradio.ping_data.last_ping_time_of_day = chrono::Utc::now();
 
+    if matches!(
+        status_of_rradio.position_and_duration[status_of_rradio.channel_number]
+            .channel_data
+            .source_type,
+        get_channel_details::SourceType::Cd | get_channel_details::SourceType::Usb
+    ) {
+        status_of_rradio.ping_data.can_send_ping = false;
+        return None;
+    }
+
     let number_of_remote_pings_to_this_channel =
         status_of_rradio.ping_data.number_of_pings_to_this_channel;
 
@@ -86,32 +106,47 @@ pub fn send_ping(
     }
     .as_str();
 
-    let return_value = Command::new("/bin/ping")
-        .args([
-            address, "-c", "1", // send one ping and then stop
-            "-W", "3", // wait that number of seconds before timing out
-        ])
-        //.stdin(Stdio::piped())    // not needed as we do not send anything after the initial command
-        .stdout(Stdio::piped()) // needed as we need to capture what is sent back
-        .spawn()
-        .expect("failed to execute child process when trying to ping");
+    let return_value = command_runner.spawn(
+        "/bin/ping",
+        &[
+            address,
+            "-c", // send this number of pings and then stop
+            config.ping_count.max(1).to_string().as_str(),
+            "-i", // the interval in seconds between pings
+            format!("{:.3}", PING_INTERVAL_MS as f64 / 1000.0).as_str(),
+            "-W", // wait this number of seconds before timing out
+            format!("{:.3}", config.ping_timeout_ms as f64 / 1000.0).as_str(),
+        ],
+    );
 
     status_of_rradio.ping_data.can_send_ping = false;
     status_of_rradio.ping_data.number_of_pings_to_this_channel += 1; // will take > 100 years to overflow; so no concern
 
     return_value
+        .inspect_err(|error| eprintln!("When trying to ping got error {error}\r"))
+        .ok()
+}
+
+/// Returns the time in ms that a ping sent with the config's count & timeout can take before it gives up
+fn longest_ping_duration_ms(config: &crate::read_config::Config) -> u64 {
+    u64::from(config.ping_count.max(1) - 1) * PING_INTERVAL_MS + config.ping_timeout_ms
 }
 
 /// status_of_rradio.ping_data.can_send_ping = true if a response is received, but not too recently so we do not ping too often
 /// Otherwise does nothing
-pub fn see_if_there_is_a_ping_response(status_of_rradio: &mut player_status::PlayerStatus) {
+pub fn see_if_there_is_a_ping_response(
+    status_of_rradio: &mut player_status::PlayerStatus,
+    config: &crate::read_config::Config,
+) {
     if ((chrono::Utc::now() - status_of_rradio.ping_data.last_ping_time_of_day).num_milliseconds()
-        > 3000)
+        > longest_ping_duration_ms(config).max(MINIMUM_MS_BETWEEN_PINGS) as i64)
         && (status_of_rradio.channel_number <= NUMBER_OF_POSSIBLE_CHANNELS)// only ping valid channels
-        && ((status_of_rradio.position_and_duration[status_of_rradio.channel_number]
-            .channel_data
-            .source_type
-            == get_channel_details::SourceType::UrlList)
+        && (matches!(
+            status_of_rradio.position_and_duration[status_of_rradio.channel_number]
+                .channel_data
+                .source_type,
+            get_channel_details::SourceType::UrlList | get_channel_details::SourceType::Playlist
+        )
             || (status_of_rradio.running_status == RunningStatus::Startingup))
     {
         status_of_rradio.ping_data.can_send_ping = true
@@ -130,8 +165,8 @@ pub fn get_ping_time(
     match ping_output {
         Ok(output) => {
             // convert the bytes to a str
-            let (ip_address_only, time_data) = std::str::from_utf8(&output.stdout)
-                .unwrap_or_default()
+            let output_as_string = String::from_utf8_lossy(&output.stdout);
+            let (ip_address_only, time_data) = output_as_string
                 .strip_prefix("PING ")
                 .unwrap_or("Error could not find prefix")
                 .split_once(" ")
@@ -143,13 +178,8 @@ pub fn get_ping_time(
                 PingWhere::Remote
             };
 
-            let (_, time_data2) = time_data.split_once("mdev = ").unwrap_or_default();
-            // the time is between the str "mdev = " & the next /
-            let (time_as_str, _) = time_data2.split_once("/").unwrap_or_default();
-            let time = time_as_str.parse::<f32>().unwrap_or_default();
-
             status_of_rradio.ping_data.ping_time_and_destination = PingTimeAndDestination {
-                time_in_ms: Some(time),
+                time_in_ms: parse_average_ping_time(time_data), // None if it timed out, which shows as NoReply
                 destination,
             };
             Ok(())
@@ -157,3 +187,68 @@ pub fn get_ping_time(
         Err(error) => Err(error.to_string()),
     }
 }
+
+/// Given the output of ping, returns the average round trip time in ms from the summary line,
+/// which looks like "rtt min/avg/max/mdev = 11.2/12.3/13.4/0.9 ms".
+/// Returns None if there is no summary line, which is what happens if no reply was received.
+pub fn parse_average_ping_time(ping_output: &str) -> Option<f32> {
+    let (_, times) = ping_output.split_once("mdev = ")?;
+    times.split('/').nth(1)?.parse::<f32>().ok() // the second of the four times is the average
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_runner::MockCommandRunner;
+
+    #[test]
+    fn ping_is_sent_with_the_configured_count_and_timeout() {
+        let mut config = crate::read_config::Config::default();
+        config.ping_count = 3;
+        config.ping_timeout_ms = 1500;
+        let mut status_of_rradio = player_status::PlayerStatus::new(&config);
+        status_of_rradio.position_and_duration[status_of_rradio.channel_number].address_to_ping =
+            "example.com".to_string();
+        let command_runner = MockCommandRunner::new("");
+
+        assert!(send_ping(&mut status_of_rradio, &config, &command_runner).is_none()); // as the mock does not start processes
+        assert!(!status_of_rradio.ping_data.can_send_ping);
+        assert_eq!(
+            *command_runner.commands_run.borrow(),
+            vec![vec![
+                "/bin/ping",
+                "example.com",
+                "-c",
+                "3",
+                "-i",
+                "0.200",
+                "-W",
+                "1.500"
+            ]]
+        );
+    }
+
+    #[test]
+    fn average_is_taken_from_the_summary_line() {
+        let output = "PING 192.168.1.1 (192.168.1.1) 56(84) bytes of data.
+64 bytes from 192.168.1.1: icmp_seq=1 ttl=64 time=1.20 ms
+64 bytes from 192.168.1.1: icmp_seq=2 ttl=64 time=3.40 ms
+64 bytes from 192.168.1.1: icmp_seq=3 ttl=64 time=2.30 ms
+
+--- 192.168.1.1 ping statistics ---
+3 packets transmitted, 3 received, 0% packet loss, time 402ms
+rtt min/avg/max/mdev = 1.200/2.300/3.400/0.898 ms
+";
+        assert_eq!(parse_average_ping_time(output), Some(2.3));
+    }
+
+    #[test]
+    fn no_reply_gives_none() {
+        let output = "PING 10.0.0.99 (10.0.0.99) 56(84) bytes of data.
+
+--- 10.0.0.99 ping statistics ---
+1 packets transmitted, 0 received, 100% packet loss, time 0ms
+";
+        assert_eq!(parse_average_ping_time(output), None);
+    }
+}
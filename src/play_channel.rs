@@ -7,25 +7,51 @@ use super::web;
 use crate::ChannelErrorEvents;
 use crate::DataChanged;
 use crate::RunningStatus;
+use crate::command_runner::CommandRunner;
 use crate::get_channel_details::SourceType;
+use crate::gstreamer_interfaces::{VOLUME_MAX, VOLUME_MIN};
 
 use crate::html_helpers::{write_message_to_web_page, write_status_to_web_page};
 
-use crate::player_status::NUMBER_OF_POSSIBLE_CHANNELS;
+use crate::lcd::get_mute_state::set_mute_state;
+use crate::player_status::{
+    AfterAnnouncement, NUMBER_OF_POSSIBLE_CHANNELS, PODCAST_CHANNEL_NUMBER,
+    START_UP_DING_CHANNEL_NUMBER, StationPreview,
+};
 use crate::read_config;
 use crate::store_channel_details_and_implement_them;
 use gstreamer::ClockTime;
 use string_replace_all::StringReplaceAll;
 
 /// plays the specified channel typically 00 to 99
-pub fn play_channel(
+pub async fn play_channel(
     channel_number: usize,
     status_of_rradio: &mut PlayerStatus,
     config: &read_config::Config,
     playbin: &mut PlaybinElement,
     lcd: &mut crate::lcd::Lc,
     web_data_changed_tx: &tokio::sync::broadcast::Sender<DataChanged>,
+    command_runner: &impl CommandRunner,
 ) -> Result<(), ()> {
+    // the keyboard can only generate config.channel_digits digit channels, but the config file (eg start_times or favorites) might specify anything
+    if channel_number == PODCAST_CHANNEL_NUMBER || channel_number == START_UP_DING_CHANNEL_NUMBER {
+        // these channels are used internally, so selecting them would clobber the podcast or the ding
+        status_of_rradio
+            .show_error_on_all_4lines(format!("Reserved channel {channel_number}").as_str());
+        write_status_to_web_page(status_of_rradio, web_data_changed_tx);
+        return Err(());
+    }
+    if channel_number >= config.number_of_channels() {
+        status_of_rradio.show_error_on_all_4lines(
+            format!(
+                "Invalid channel {channel_number}; channels must be in the range 0 to {}",
+                config.number_of_channels() - 1
+            )
+            .as_str(),
+        );
+        write_status_to_web_page(status_of_rradio, web_data_changed_tx);
+        return Err(());
+    }
     if channel_number == status_of_rradio.channel_number
         && (status_of_rradio.running_status == RunningStatus::NoChannel
             || status_of_rradio.running_status == RunningStatus::NoChannelRepeated)
@@ -34,9 +60,13 @@ pub fn play_channel(
     } else {
         let previous_channel_number = status_of_rradio.channel_number;
         status_of_rradio.channel_number = channel_number;
+        status_of_rradio.stop_at_end_of_album = false;
+        status_of_rradio.deferred_play = None; // a track of the previous channel must not start later
 
         status_of_rradio.line_2_data.update_if_changed("");
         status_of_rradio.line_34_data.update_if_changed("");
+        status_of_rradio.line_3_data.update_if_changed("");
+        status_of_rradio.line_4_data.update_if_changed("");
         status_of_rradio.all_4lines.update_if_changed("");
         write_status_to_web_page(status_of_rradio, web_data_changed_tx);
 
@@ -57,7 +87,7 @@ pub fn play_channel(
                         long_seek_time: config.long_advance_time,
                     })));
             }
-            SourceType::UrlList | SourceType::UnknownSource => {
+            SourceType::UrlList | SourceType::Playlist | SourceType::UnknownSource => {
                 let _ = web_data_changed_tx.send(web::DataChanged::CanSeekBackwards(None));
                 let _ = web_data_changed_tx.send(web::DataChanged::CanSeekForwards(None));
                 let _ = web_data_changed_tx.send(web::DataChanged::Position {
@@ -73,6 +103,7 @@ pub fn play_channel(
             playbin,
             previous_channel_number,
             lcd,
+            command_runner,
         ) {
             write_message_to_web_page(
                 format!("{:?}", the_channel_error_events),
@@ -88,14 +119,21 @@ pub fn play_channel(
                     } else {
                         RunningStatus::NoChannel
                     };
-                    if let Some(ding_filename) = &config.aural_notifications.filename_error {
-                        // play a ding if one has been specified
+                    if let Some(ding_filename) = &config.aural_notifications.filename_error
+                        && status_of_rradio.error_ding_is_due(config, chrono::Utc::now())
+                    {
+                        // play a ding if one has been specified & it has not been played too recently
                         status_of_rradio.position_and_duration
                             [crate::player_status::START_UP_DING_CHANNEL_NUMBER]
                             .channel_data
                             .station_url = vec![format!("file://{ding_filename}")];
-                        let _ignore_error_if_beep_fails =
-                            playbin.play_track(status_of_rradio, config, lcd, false);
+                        let _ignore_error_if_beep_fails = playbin.play_track(
+                            status_of_rradio,
+                            config,
+                            lcd,
+                            false,
+                            command_runner,
+                        );
                         status_of_rradio.position_and_duration
                             [crate::player_status::START_UP_DING_CHANNEL_NUMBER]
                             .index_to_current_track = 0;
@@ -132,10 +170,9 @@ pub fn play_channel(
                     return Err(());
                 }
                 _ => {
-                    status_of_rradio
-                        .all_4lines
-                        .update_if_changed(the_channel_error_events.to_lcd_screen().as_str());
-                    status_of_rradio.running_status = RunningStatus::LongMessageOnAll4Lines;
+                    status_of_rradio.show_error_on_all_4lines(
+                        the_channel_error_events.to_lcd_screen().as_str(),
+                    );
                 }
             }
         }
@@ -146,19 +183,370 @@ pub fn play_channel(
             });
         }
     }
-    if let Err(playbin_error_message) = playbin.play_track(status_of_rradio, config, lcd, true) {
-        status_of_rradio.all_4lines.update_if_changed(
+    apply_channel_volume(status_of_rradio, config, playbin);
+    let play_result = if config.announce_station
+        && status_of_rradio.running_status == RunningStatus::RunningNormally
+        && play_station_announcement(status_of_rradio, config, playbin, lcd, command_runner).await
+    {
+        Ok(()) // the channel itself is played by play_announced_channel when the announcement ends
+    } else {
+        playbin.play_track(status_of_rradio, config, lcd, true, command_runner)
+    };
+    if let Err(playbin_error_message) = play_result {
+        status_of_rradio.show_error_on_all_4lines(
             format!(
                 "When playing a track on channel {} got {playbin_error_message}",
                 status_of_rradio.channel_number
             )
             .as_str(),
         );
-        status_of_rradio.running_status = RunningStatus::LongMessageOnAll4Lines;
         Err(())
     } else {
         // play worked
-        let line2 = generate_line2(status_of_rradio);
+        status_of_rradio.last_error = None;
+        let line2 = generate_line2(status_of_rradio, command_runner);
+        status_of_rradio
+            .line_2_data
+            .update_if_changed(line2.as_str());
+        write_status_to_web_page(status_of_rradio, web_data_changed_tx);
+        Ok(())
+    }
+}
+
+/// The file that espeak-ng writes spoken announcements, such as the station name or the time, to
+const SPOKEN_ANNOUNCEMENT_FILE: &str = "/tmp/rradio_spoken_announcement.wav";
+
+/// The time espeak-ng is given to write a spoken announcement before it is abandoned
+const SPEECH_SYNTHESIS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Uses espeak-ng to write the spoken text to SPOKEN_ANNOUNCEMENT_FILE & returns its path.
+/// espeak-ng is run without blocking the runtime & is killed if it takes longer than SPEECH_SYNTHESIS_TIMEOUT.
+/// Returns None if there is no text or espeak-ng fails, typically as it is not installed.
+async fn synthesize_speech(
+    text: &str,
+    command_runner: &impl CommandRunner,
+) -> Option<&'static str> {
+    if text.is_empty() {
+        return None;
+    }
+    match command_runner
+        .output_with_timeout(
+            "espeak-ng",
+            &["-w", SPOKEN_ANNOUNCEMENT_FILE, text],
+            SPEECH_SYNTHESIS_TIMEOUT,
+        )
+        .await
+    {
+        Ok(output) if output.status.success() => Some(SPOKEN_ANNOUNCEMENT_FILE),
+        _ => None,
+    }
+}
+
+/// Speaks the name of the current station by playing it on the ding channel.
+/// Returns false, having played nothing, if the announcement could not be made, so the caller can play the channel at once.
+async fn play_station_announcement(
+    status_of_rradio: &mut PlayerStatus,
+    config: &read_config::Config,
+    playbin: &PlaybinElement,
+    lcd: &mut crate::lcd::Lc,
+    command_runner: &impl CommandRunner,
+) -> bool {
+    let organisation = status_of_rradio.position_and_duration[status_of_rradio.channel_number]
+        .channel_data
+        .organisation
+        .clone();
+    play_spoken_announcement(
+        status_of_rradio,
+        config,
+        playbin,
+        lcd,
+        &organisation,
+        AfterAnnouncement::PlayChannel,
+        command_runner,
+    )
+    .await
+}
+
+/// Speaks the current local time, eg for users who cannot read the screen.
+/// The channel that is playing stops while the time is spoken, & is then resumed from where it had got to;
+/// a paused channel is paused again where it had got to.
+pub async fn speak_time(
+    status_of_rradio: &mut PlayerStatus,
+    config: &read_config::Config,
+    playbin: &PlaybinElement,
+    lcd: &mut crate::lcd::Lc,
+    command_runner: &impl CommandRunner,
+) {
+    let time = config.clock_now().format("%-I:%M %p").to_string();
+    // a ding (or the startup ding) cannot usefully be resumed, but an announcement of a channel that has yet to start must still start it
+    let after_announcement = match status_of_rradio.gstreamer_state {
+        _ if status_of_rradio.channel_number >= START_UP_DING_CHANNEL_NUMBER => {
+            AfterAnnouncement::Nothing
+        }
+        gstreamer::State::Playing => AfterAnnouncement::PlayChannel,
+        gstreamer::State::Paused => AfterAnnouncement::PauseChannel,
+        _ => AfterAnnouncement::Nothing,
+    };
+    if after_announcement == AfterAnnouncement::PauseChannel {
+        // pausing muted the amplifier, which would make the time silent
+        set_mute_state(gstreamer::State::Playing);
+    }
+    if !play_spoken_announcement(
+        status_of_rradio,
+        config,
+        playbin,
+        lcd,
+        format!("It is {time}").as_str(),
+        after_announcement,
+        command_runner,
+    )
+    .await
+    {
+        if after_announcement == AfterAnnouncement::PauseChannel {
+            set_mute_state(gstreamer::State::Paused);
+        }
+        status_of_rradio.line_1_brief_message =
+            Some(("Can't speak the time".to_string(), chrono::Utc::now()));
+    }
+}
+
+/// Speaks the text by playing it on the ding channel.
+/// status_of_rradio.channel_number is left unchanged, so the screen still shows the channel,
+/// & station_announcement_playing is set so that play_announced_channel does what after_announcement asks when the announcement ends.
+/// Returns false, having played nothing, if the announcement could not be made.
+async fn play_spoken_announcement(
+    status_of_rradio: &mut PlayerStatus,
+    config: &read_config::Config,
+    playbin: &PlaybinElement,
+    lcd: &mut crate::lcd::Lc,
+    text: &str,
+    after_announcement: AfterAnnouncement,
+    command_runner: &impl CommandRunner,
+) -> bool {
+    let channel_number = status_of_rradio.channel_number;
+    let Some(announcement_file) = synthesize_speech(text, command_runner).await else {
+        return false;
+    };
+    let ding_channel = &mut status_of_rradio.position_and_duration[START_UP_DING_CHANNEL_NUMBER];
+    ding_channel.channel_data.station_url = vec![format!("file://{announcement_file}")];
+    ding_channel.index_to_current_track = 0;
+
+    status_of_rradio.channel_number = START_UP_DING_CHANNEL_NUMBER;
+    let play_result = playbin.play_track(status_of_rradio, config, lcd, false, command_runner);
+    status_of_rradio.channel_number = channel_number;
+
+    match play_result {
+        Ok(()) => {
+            status_of_rradio.station_announcement_playing = true;
+            status_of_rradio.after_announcement = after_announcement;
+            true
+        }
+        Err(error_message) => {
+            eprintln!("Could not play the spoken announcement; got {error_message}\r");
+            false
+        }
+    }
+}
+
+/// Called when a spoken announcement has finished, to play the channel that was announced or interrupted,
+/// or to load it again & pause it if it was paused, unless nothing was playing when the announcement started
+pub fn play_announced_channel(
+    status_of_rradio: &mut PlayerStatus,
+    config: &read_config::Config,
+    playbin: &PlaybinElement,
+    lcd: &mut crate::lcd::Lc,
+    command_runner: &impl CommandRunner,
+) {
+    let after_announcement = status_of_rradio.after_announcement;
+    if after_announcement == AfterAnnouncement::Nothing {
+        status_of_rradio.station_announcement_playing = false;
+        return;
+    }
+    if let Err(playbin_error_message) =
+        playbin.play_track(status_of_rradio, config, lcd, true, command_runner)
+    {
+        status_of_rradio.show_error_on_all_4lines(
+            format!(
+                "When playing a track on channel {} got {playbin_error_message}",
+                status_of_rradio.channel_number
+            )
+            .as_str(),
+        );
+    } else if after_announcement == AfterAnnouncement::PauseChannel {
+        // the channel is ready to resume where it was, but must not start by itself
+        status_of_rradio.deferred_play = None;
+        if let Err(_error_message) = playbin.set_state(gstreamer::State::Paused) {
+            eprintln!("Could not pause the channel after the spoken announcement\r");
+        }
+        set_mute_state(gstreamer::State::Paused);
+    }
+}
+
+/// The number of times we remount a Samba share after a gstreamer error before giving up & showing the error
+const MAX_SAMBA_RECONNECT_ATTEMPTS: u32 = 1;
+
+/// Called when gstreamer reports a resource error, which for a Samba share typically means the network dropped out briefly.
+/// Unmounts & remounts the share, then plays the current track again from where it had got to.
+/// Returns an error, so the caller can show the gstreamer error, if the current channel is not a Samba share,
+/// if we have already tried MAX_SAMBA_RECONNECT_ATTEMPTS times or if the share cannot be remounted.
+pub fn reconnect_samba(
+    status_of_rradio: &mut PlayerStatus,
+    config: &read_config::Config,
+    playbin: &mut PlaybinElement,
+    lcd: &mut crate::lcd::Lc,
+    command_runner: &impl CommandRunner,
+) -> Result<(), String> {
+    let channel = &mut status_of_rradio.position_and_duration[status_of_rradio.channel_number];
+    if channel.channel_data.source_type != SourceType::Usb
+        || !channel
+            .channel_data
+            .media_details
+            .as_ref()
+            .is_some_and(|media_details| media_details.device.starts_with("//"))
+    {
+        return Err("the current channel is not a Samba share".to_string());
+    }
+    if channel.samba_reconnect_attempts >= MAX_SAMBA_RECONNECT_ATTEMPTS {
+        return Err(format!(
+            "already tried to reconnect to the Samba share {} times",
+            channel.samba_reconnect_attempts
+        ));
+    }
+    channel.samba_reconnect_attempts += 1;
+    println!(
+        "Trying to reconnect to the Samba share; attempt {}\r",
+        channel.samba_reconnect_attempts
+    );
+
+    let _ = playbin.set_state(gstreamer::State::Null);
+    // if the network has gone, the unmount may fail, but we still want to try to mount the share again
+    let _ = crate::unmount::unmount_if_needed(&mut channel.channel_data);
+    if let Some(media_details) = &mut channel.channel_data.media_details {
+        media_details.is_mounted = false;
+    }
+
+    // play_track mounts the share again as is_mounted is false
+    playbin.play_track(status_of_rradio, config, lcd, true, command_runner)
+}
+
+/// The time waited before the first attempt to reconnect a stream; each later attempt waits this much longer
+const STREAM_RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Called when gstreamer reports a resource error, which for a stream typically means the network dropped out briefly.
+/// Stops gstreamer & sets RunningStatus::Reconnecting, so the screen shows the attempt rather than the error;
+/// reconnect_stream_if_due plays the stream again once the delay has passed.
+/// Returns an error, so the caller can show the gstreamer error, if the current channel is not a stream
+/// or if we have already tried config.max_stream_reconnect_attempts times.
+pub fn schedule_stream_reconnect(
+    status_of_rradio: &mut PlayerStatus,
+    config: &read_config::Config,
+    playbin: &PlaybinElement,
+) -> Result<(), String> {
+    let channel = &mut status_of_rradio.position_and_duration[status_of_rradio.channel_number];
+    if !matches!(
+        channel.channel_data.source_type,
+        SourceType::UrlList | SourceType::Playlist
+    ) {
+        return Err("the current channel is not a stream".to_string());
+    }
+    if channel.stream_reconnect_attempts >= config.max_stream_reconnect_attempts {
+        return Err(format!(
+            "already tried to reconnect to the stream {} times",
+            channel.stream_reconnect_attempts
+        ));
+    }
+    channel.stream_reconnect_attempts += 1;
+    let stream_reconnect_attempts = channel.stream_reconnect_attempts;
+    println!("Trying to reconnect to the stream; attempt {stream_reconnect_attempts}\r");
+
+    let _ = playbin.set_state(gstreamer::State::Null); // so gstreamer does not keep reporting the error while we wait
+    status_of_rradio.running_status = RunningStatus::Reconnecting;
+    status_of_rradio.stream_reconnect_due =
+        Some(std::time::Instant::now() + STREAM_RECONNECT_DELAY * stream_reconnect_attempts);
+    Ok(())
+}
+
+/// Called from the main loop; if a stream is waiting to be reconnected & its delay has passed, plays it again.
+/// The main loop returns to RunningNormally once gstreamer says the buffer is full.
+/// If the stream cannot be played, the error is shown on the LCD screen.
+pub fn reconnect_stream_if_due(
+    status_of_rradio: &mut PlayerStatus,
+    config: &read_config::Config,
+    playbin: &PlaybinElement,
+    lcd: &mut crate::lcd::Lc,
+    command_runner: &impl CommandRunner,
+) {
+    if status_of_rradio
+        .stream_reconnect_due
+        .take_if(|stream_reconnect_due| std::time::Instant::now() >= *stream_reconnect_due)
+        .is_none()
+    {
+        return;
+    }
+    // a live stream cannot resume where it was, so there is no point seeking
+    if let Err(error_message) =
+        playbin.play_track(status_of_rradio, config, lcd, false, command_runner)
+    {
+        status_of_rradio.show_error_on_all_4lines(
+            format!("When reconnecting to the stream got {error_message}").as_str(),
+        );
+    }
+}
+
+/// Re-reads the CD in the drive & plays it from the first track, so the user does not have to re-select the CD channel after changing the disk.
+/// Does nothing if the current channel is not a CD channel. If the disk cannot be read, the error is shown on the LCD screen.
+pub fn reload_cd(
+    status_of_rradio: &mut PlayerStatus,
+    config: &read_config::Config,
+    playbin: &mut PlaybinElement,
+    lcd: &mut crate::lcd::Lc,
+    web_data_changed_tx: &tokio::sync::broadcast::Sender<DataChanged>,
+    command_runner: &impl CommandRunner,
+) -> Result<(), ()> {
+    let channel_data =
+        &status_of_rradio.position_and_duration[status_of_rradio.channel_number].channel_data;
+    let Some(media_details) = &channel_data.media_details else {
+        return Ok(()); // there is no CD drive to reload
+    };
+    if channel_data.source_type != SourceType::Cd {
+        println!("Ignoring reload CD as the current channel is not a CD\r");
+        return Ok(());
+    }
+
+    let _ = playbin.set_state(gstreamer::State::Null); // stop playing the old disk before we read the new one
+    match crate::get_channel_details::play_cd(
+        media_details,
+        &config.aural_notifications.filename_sound_at_end_of_playlist,
+    ) {
+        Ok(cd_channel_data) => {
+            let channel =
+                &mut status_of_rradio.position_and_duration[status_of_rradio.channel_number];
+            channel.channel_data =
+                cd_channel_data.with_cd_channel_settings_of(&channel.channel_data);
+            channel.index_to_current_track = 0;
+            channel.artist = String::new();
+        }
+        Err(the_channel_error_events) => {
+            status_of_rradio
+                .show_error_on_all_4lines(the_channel_error_events.to_lcd_screen().as_str());
+            write_status_to_web_page(status_of_rradio, web_data_changed_tx);
+            return Err(());
+        }
+    }
+
+    status_of_rradio.initialise_for_new_station();
+    status_of_rradio.line_34_data.update_if_changed("");
+    status_of_rradio.line_3_data.update_if_changed("");
+    status_of_rradio.line_4_data.update_if_changed("");
+    if let Err(playbin_error_message) =
+        playbin.play_track(status_of_rradio, config, lcd, true, command_runner)
+    {
+        status_of_rradio.show_error_on_all_4lines(
+            format!("When playing the reloaded CD got {playbin_error_message}").as_str(),
+        );
+        Err(())
+    } else {
+        let line2 = generate_line2(status_of_rradio, command_runner);
         status_of_rradio
             .line_2_data
             .update_if_changed(line2.as_str());
@@ -166,3 +554,114 @@ pub fn play_channel(
         Ok(())
     }
 }
+
+/// Starts previewing channels, where each channel the user enters is played for config.station_preview_time at a reduced volume.
+/// If the user is already previewing channels, the preview ends & the channel being previewed carries on at the normal volume.
+pub fn toggle_station_preview(
+    status_of_rradio: &mut PlayerStatus,
+    config: &read_config::Config,
+    playbin: &mut PlaybinElement,
+) {
+    let message = match status_of_rradio.station_preview.take() {
+        Some(StationPreview { ends_at: None, .. }) => "Preview cancelled".to_string(),
+        Some(_station_preview) => format!("Keeping channel {}", status_of_rradio.channel_number),
+        None => {
+            // only a channel that is actually playing is worth going back to
+            let previous_channel = (status_of_rradio.gstreamer_state == gstreamer::State::Playing
+                && status_of_rradio.channel_number < config.number_of_channels())
+            .then_some(status_of_rradio.channel_number);
+            status_of_rradio.station_preview = Some(StationPreview {
+                previous_channel,
+                ends_at: None,
+            });
+            "Preview: enter channels".to_string()
+        }
+    };
+    set_playing_volume(status_of_rradio, config, playbin);
+    status_of_rradio.line_1_brief_message = Some((message, chrono::Utc::now()));
+}
+
+/// If the user is previewing channels, restarts the preview time, so that the channel just entered is previewed for config.station_preview_time
+pub fn restart_station_preview_time(
+    status_of_rradio: &mut PlayerStatus,
+    config: &read_config::Config,
+) {
+    if let Some(station_preview) = &mut status_of_rradio.station_preview {
+        station_preview.ends_at = Some(std::time::Instant::now() + config.station_preview_time);
+    }
+}
+
+/// Called from the main loop; if the channel being previewed has been played for config.station_preview_time, ends the preview
+/// & restores the normal volume. Returns the channel that was playing before the preview if it is to be played again.
+pub fn end_station_preview_if_due(
+    status_of_rradio: &mut PlayerStatus,
+    config: &read_config::Config,
+    playbin: &mut PlaybinElement,
+) -> Option<usize> {
+    let station_preview = status_of_rradio
+        .station_preview
+        .take_if(|station_preview| {
+            station_preview
+                .ends_at
+                .is_some_and(|ends_at| std::time::Instant::now() >= ends_at)
+        })?;
+    set_playing_volume(status_of_rradio, config, playbin);
+    if config.station_preview_returns_to_previous {
+        station_preview
+            .previous_channel
+            .filter(|&previous_channel| previous_channel != status_of_rradio.channel_number)
+    } else {
+        status_of_rradio.line_1_brief_message = Some((
+            format!("Keeping channel {}", status_of_rradio.channel_number),
+            chrono::Utc::now(),
+        ));
+        None
+    }
+}
+
+/// Sets gstreamer to the volume it should play at now, stopping any fade in, as a preview has started or ended
+fn set_playing_volume(
+    status_of_rradio: &PlayerStatus,
+    config: &read_config::Config,
+    playbin: &mut PlaybinElement,
+) {
+    playbin.cancel_fade_in();
+    if let Err(error_message) = playbin.set_volume(status_of_rradio.playing_volume(config)) {
+        eprintln!("When changing the preview volume got error {error_message}\r");
+    }
+}
+
+/// If the current channel specifies a volume, stores the user's volume (unless already stored) & uses the channel's volume.
+/// If it does not, restores the user's volume if the previous channel overrode it.
+/// The web page is updated when gstreamer changes state, so there is no need to do so here.
+pub fn apply_channel_volume(
+    status_of_rradio: &mut PlayerStatus,
+    config: &read_config::Config,
+    playbin: &mut PlaybinElement,
+) {
+    match status_of_rradio.position_and_duration[status_of_rradio.channel_number]
+        .channel_data
+        .volume
+    {
+        Some(channel_volume) => {
+            if status_of_rradio.volume_before_channel_override.is_none() {
+                status_of_rradio.volume_before_channel_override =
+                    Some(status_of_rradio.current_volume);
+            }
+            status_of_rradio.current_volume = channel_volume.clamp(VOLUME_MIN, VOLUME_MAX);
+        }
+        None => {
+            if let Some(users_volume) = status_of_rradio.volume_before_channel_override.take() {
+                status_of_rradio.current_volume = users_volume;
+            } else {
+                return; // nothing has changed
+            }
+        }
+    }
+    if let Err(error_message) = playbin.set_volume(status_of_rradio.playing_volume(config)) {
+        eprintln!(
+            "When setting the channel volume got error {}\r",
+            error_message
+        );
+    }
+}
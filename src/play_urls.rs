@@ -5,7 +5,9 @@ use crate::PODCAST_CHANNEL_NUMBER;
 use crate::RealTimeDataOnOneChannel;
 use crate::RunningStatus;
 use crate::SourceType;
+use crate::command_runner::CommandRunner;
 use crate::get_channel_details::get_ip_address;
+use crate::play_channel::apply_channel_volume;
 use crate::previous_or_nextrack::generate_line2;
 use gstreamer::ClockTime;
 
@@ -17,6 +19,7 @@ pub fn play_url(
     playbin: &mut PlaybinElement,
     config: &crate::read_config::Config,
     lcd: &mut crate::lcd::Lc,
+    command_runner: &impl CommandRunner,
 ) {
     status_of_rradio.running_status = RunningStatus::RunningNormally;
     status_of_rradio.position_and_duration[PODCAST_CHANNEL_NUMBER] = RealTimeDataOnOneChannel {
@@ -34,23 +37,36 @@ pub fn play_url(
             station_url: vec![new_text_from_user],
             media_details: None,
             data_is_initialised: false,
+            volume: None,
+            playlist_file: None,
+            ping_address: None,
+            force_mono: false,
+            icon: None,
+            eq: None,
+            http_authentication: None,
         },
+        samba_reconnect_attempts: 0,
+        stream_reconnect_attempts: 0,
     };
 
     status_of_rradio.channel_number = PODCAST_CHANNEL_NUMBER;
+    status_of_rradio.stop_at_end_of_album = false;
     status_of_rradio.initialise_for_new_station();
-    if let Err(playbin_error_message) = playbin.play_track(status_of_rradio, config, lcd, true) {
-        status_of_rradio.all_4lines.update_if_changed(
+    apply_channel_volume(status_of_rradio, config, playbin);
+    if let Err(playbin_error_message) =
+        playbin.play_track(status_of_rradio, config, lcd, true, command_runner)
+    {
+        status_of_rradio.show_error_on_all_4lines(
             format!(
                 "in play_url: When playing a track on channel {} got {playbin_error_message}",
                 status_of_rradio.channel_number
             )
             .as_str(),
         );
-        status_of_rradio.running_status = RunningStatus::LongMessageOnAll4Lines;
     } else {
         // play worked
-        let line2 = generate_line2(status_of_rradio);
+        status_of_rradio.last_error = None;
+        let line2 = generate_line2(status_of_rradio, command_runner);
         status_of_rradio
             .line_2_data
             .update_if_changed(line2.as_str())
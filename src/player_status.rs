@@ -13,18 +13,26 @@ use crate::{
     read_config::{self, Config},
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 /// stores the decoded channel file data, the position of the tracks, ie the time since starting to play it
 /// &, if it is a streaming channel, the duration of the channel.
 pub struct RealTimeDataOnOneChannel {
     pub artist: String,
     pub index_to_current_track: usize,
+    #[serde(serialize_with = "serialize_using_debug")]
     pub position: ClockTime,
     /// address_to_ping is derived from the first station in the list
     /// after stripping off the prefix & suffix
     pub address_to_ping: String,
+    #[serde(serialize_with = "serialize_using_debug")]
     pub duration: Option<ClockTime>,
     pub channel_data: ChannelFileDataDecoded,
+    /// the number of times the Samba share has been remounted after an error since the channel was selected;
+    /// used so we do not keep trying to reconnect for ever
+    pub samba_reconnect_attempts: u32,
+    /// the number of times the stream has been reconnected after a network error since it last played successfully;
+    /// used so we do not keep trying to reconnect for ever
+    pub stream_reconnect_attempts: u32,
 }
 impl RealTimeDataOnOneChannel {
     pub fn new() -> Self {
@@ -35,6 +43,19 @@ impl RealTimeDataOnOneChannel {
             position: ClockTime::ZERO,
             duration: None,
             address_to_ping: "8.8.8.8".to_string(), // a default value in case we do not find a valid address
+            samba_reconnect_attempts: 0,
+            stream_reconnect_attempts: 0,
+        }
+    }
+
+    /// Returns the number of tracks the user can play, which does not include the ding at the end (if there is one).
+    /// Returns 0, rather than underflowing, if there are no tracks.
+    pub fn playable_track_count(&self) -> usize {
+        let number_of_tracks = self.channel_data.station_url.len();
+        if self.channel_data.last_track_is_a_ding {
+            number_of_tracks.saturating_sub(1)
+        } else {
+            number_of_tracks
         }
     }
 }
@@ -44,22 +65,69 @@ impl Default for RealTimeDataOnOneChannel {
     }
 }
 
-/// The maximum possible as the channel number is 2 decimal digits. (The ding channel 100, so the user cannot enter it.)
-pub const NUMBER_OF_POSSIBLE_CHANNELS: usize = 100;
+/// The maximum possible as the channel number is at most read_config::MAXIMUM_CHANNEL_DIGITS (ie 3) decimal digits.
+/// (The podcast & ding channels are 1000 & 1001, so the user cannot enter them.)
+pub const NUMBER_OF_POSSIBLE_CHANNELS: usize =
+    10_usize.pow(read_config::MAXIMUM_CHANNEL_DIGITS as u32);
 /// PODCAST_CHANNEL_NUMBER must be less than START_UP_DING_CHANNEL_NUMBER or else we do not get position & duration
 pub const PODCAST_CHANNEL_NUMBER: usize = NUMBER_OF_POSSIBLE_CHANNELS;
+/// A reserved pseudo-channel, whose station_url is overwritten whenever a ding (or station announcement) is played;
+/// play_channel rejects it, so it can never be selected as a normal channel
 pub const START_UP_DING_CHANNEL_NUMBER: usize = NUMBER_OF_POSSIBLE_CHANNELS + 1;
-#[derive(Debug)] // neither Copy nor clone are implmented as the player can only have a single status
+// the reserved channels must be outside the range of normal channels, or selecting a normal channel would clobber them
+const _: () = assert!(
+    PODCAST_CHANNEL_NUMBER >= NUMBER_OF_POSSIBLE_CHANNELS
+        && START_UP_DING_CHANNEL_NUMBER > PODCAST_CHANNEL_NUMBER
+        && START_UP_DING_CHANNEL_NUMBER < NUMBER_OF_POSSIBLE_CHANNELS + 2
+);
+/// The amount the scroll period changes each time the user asks for faster or slower scrolling
+pub const SCROLL_PERIOD_STEP_MS: i64 = 200;
+/// The range the user can change the scroll period within; any faster is unreadable & any slower looks stuck
+const SCROLL_PERIOD_RANGE_MS: std::ops::RangeInclusive<u64> = 200..=5000;
+
+#[derive(Debug)]
+/// The state of a channel preview, where each channel the user enters is played briefly at a reduced volume
+pub struct StationPreview {
+    /// the channel that was playing when the preview started, if one was, so it can be returned to when the preview ends
+    pub previous_channel: Option<usize>,
+    /// the time the channel being previewed stops being previewed; None until the user has entered a channel
+    pub ends_at: Option<std::time::Instant>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize)]
+/// What happens to the channel when a spoken announcement ends
+pub enum AfterAnnouncement {
+    /// nothing was playing when the announcement started, so nothing is played
+    Nothing,
+    /// the channel is played, from where it had got to if it can seek
+    PlayChannel,
+    /// the channel was paused, so it is loaded again & paused where it had got to, ready for the user to resume it
+    PauseChannel,
+}
+
+#[derive(Debug, serde::Serialize)]
+/// An error that was shown on all 4 lines of the screen, kept so the user can see it again after it has gone
+pub struct LastError {
+    pub message: String,
+    /// the time the error was shown
+    #[serde(serialize_with = "serialize_using_debug")]
+    pub time: chrono::DateTime<chrono::Local>,
+}
+
+#[derive(Debug, serde::Serialize)] // neither Copy nor clone are implmented as the player can only have a single status
 /// A struct listing all information needed to display the status of rradio.
 pub struct PlayerStatus {
     pub toml_error: Option<String>,
     /// Specifies if we are starting up, in which case we want to see the startup message, shutting down or running normally.
     /// or there is a bad error
     pub running_status: lcd::RunningStatus,
-    /// in the range 00 to 99, normally, but the ding channel is 100
+    /// in the range 00 to 99 (or 000 to 999 if config.channel_digits is 3), normally, but the podcast & ding channels are above that
     pub startup_folder: String,
     pub channel_number: usize,
     pub current_volume: i32,
+    /// the user's volume, stored while a channel that specifies its own volume is playing, so it can be restored afterwards
+    pub volume_before_channel_override: Option<i32>,
+    #[serde(serialize_with = "serialize_using_debug")]
     pub gstreamer_state: gstreamer::State,
     pub buffering_percent: i32,
     pub podcast_data_from_toml: PodcastDataAllStations,
@@ -73,9 +141,78 @@ pub struct PlayerStatus {
     pub line_1_data: lcd::ScrollData,
     pub line_2_data: lcd::ScrollData,
     pub line_34_data: lcd::ScrollData,
+    /// only used if config.split_title_artist is true; holds the title
+    pub line_3_data: lcd::ScrollData,
+    /// only used if config.split_title_artist is true; holds the artist
+    pub line_4_data: lcd::ScrollData,
+    /// the hostname or custom text shown on line 1 while starting up, if config.startup_line1 asks for one;
+    /// the hostname is read once, as it does not change while rradio is running
+    pub startup_line_1_data: lcd::ScrollData,
+    #[serde(serialize_with = "serialize_using_debug")]
     pub time_started_playing_current_station: chrono::DateTime<Utc>,
+    /// a short message shown briefly on line 1 instead of the usual text, & the time it was set
+    #[serde(serialize_with = "serialize_using_debug")]
+    pub line_1_brief_message: Option<(String, chrono::DateTime<Utc>)>,
+    /// selects which information (temperature, Wi-Fi signal, IP address or date & time) is shown when there is a free line
+    pub info_rotation_index: usize,
+    /// the time info_rotation_index last changed
+    #[serde(serialize_with = "serialize_using_debug")]
+    pub info_rotation_last_change: chrono::DateTime<Utc>,
+    /// true while a spoken announcement (the station name or the time) is playing; the channel itself starts (or resumes) when the announcement ends
+    pub station_announcement_playing: bool,
+    /// what happens to the channel when the spoken announcement ends
+    pub after_announcement: AfterAnnouncement,
+    /// true if playing was paused because an audio output was removed; playing resumes when one is added
+    pub paused_as_audio_output_removed: bool,
+    /// true if playing should stop when the last track of the current CD or USB album ends; cleared when a new station is selected
+    pub stop_at_end_of_album: bool,
+    /// true if the next number the user types selects a track on the current CD rather than a channel; cleared when a station is selected
+    pub cd_track_select: bool,
+    /// the time between scrolls of long text; initially config.scroll.scroll_period_ms, but the user can change it while running
+    pub scroll_period_ms: u64,
+    /// true if the clock on the screen uses the 24 hour clock; initially config.clock_24h, but the user can change it while running
+    pub clock_24h: bool,
+    /// whether the elapsed or the remaining time of a CD or USB track is shown; initially config.time_display, but the user can change it while running
+    pub time_display: read_config::TimeDisplay,
+    /// true if, when last checked, the sound was muted outside rradio's control, eg the ALSA mixer was muted
+    pub system_muted: bool,
+    /// Some while the user is previewing channels; the channels entered are played briefly at a reduced volume
+    #[serde(serialize_with = "serialize_using_debug")]
+    pub station_preview: Option<StationPreview>,
+    /// the track waiting for its channel's pause_before_playing_ms to elapse before it plays, if there is one
+    #[serde(serialize_with = "serialize_using_debug")]
+    pub deferred_play: Option<crate::gstreamer_interfaces::DeferredPlay>,
+    /// the seek waiting for gstreamer to have a position after a CD or USB track has started, if there is one
+    #[serde(serialize_with = "serialize_using_debug")]
+    pub pending_seek: Option<crate::gstreamer_interfaces::PendingSeek>,
+    /// the time the stream that dropped out is to be reconnected, if it is waiting to be
+    #[serde(serialize_with = "serialize_using_debug")]
+    pub stream_reconnect_due: Option<std::time::Instant>,
+    /// the most recent error shown on all 4 lines, if there has been one since a channel last played successfully
+    pub last_error: Option<LastError>,
+    /// the time the error ding was last played, if it has been; used to stop the ding being played too often
+    #[serde(serialize_with = "serialize_using_debug")]
+    pub last_error_ding_time: Option<chrono::DateTime<Utc>>,
+    /// the position when playing_has_stalled last saw it change, & the time it changed; used to spot a stream that has stalled
+    #[serde(serialize_with = "serialize_using_debug")]
+    pub last_position_change: (ClockTime, chrono::DateTime<Utc>),
     /// Stores channel_file_data, organisation, a vec of startion URLs & whether or not the last track is a ding
-    pub position_and_duration: [RealTimeDataOnOneChannel; NUMBER_OF_POSSIBLE_CHANNELS + 2], // +1 so there is a channel to play the startup ding
+    /// It is boxed as, with 3 digit channels, it is too big to be put on the stack
+    #[serde(serialize_with = "serialize_all_channels")]
+    pub position_and_duration: Box<[RealTimeDataOnOneChannel; NUMBER_OF_POSSIBLE_CHANNELS + 2]>, // +1 so there is a channel to play the startup ding
+}
+
+/// Returns the text config.startup_line1 asks for on line 1 while starting up, if it does not change while rradio is running
+fn startup_line_1_text(config: &read_config::Config) -> String {
+    match &config.startup_line1 {
+        read_config::StartupLine1::Hostname => std::fs::read_to_string("/etc/hostname")
+            .map(|hostname| hostname.trim().to_string())
+            .unwrap_or_else(|_error| "Unknown hostname".to_string()),
+        read_config::StartupLine1::Custom(text) => text.clone(),
+        read_config::StartupLine1::IpAndVolume | read_config::StartupLine1::CompileTime => {
+            String::new()
+        }
+    }
 }
 
 impl PlayerStatus {
@@ -87,6 +224,7 @@ impl PlayerStatus {
             startup_folder: String::new(),
             channel_number: NUMBER_OF_POSSIBLE_CHANNELS,
             current_volume: config.initial_volume,
+            volume_before_channel_override: None,
             gstreamer_state: gstreamer::State::Null,
             buffering_percent: 0,
             podcast_data_from_toml: PodcastDataAllStations {
@@ -98,7 +236,7 @@ impl PlayerStatus {
                 ssid: "not known".to_string(),
                 local_ip_address: "8.8.8.8".to_string(),
                 gateway_ip_address: "8.8.8.8".to_string(),
-                is_valid: false,
+                state: get_local_ip_address::NetworkState::NotConfigured,
             },
             ping_data: ping::PingData {
                 can_send_ping: true,
@@ -113,8 +251,36 @@ impl PlayerStatus {
             line_1_data: lcd::ScrollData::new("", 1),
             line_2_data: lcd::ScrollData::new("", 1),
             line_34_data: lcd::ScrollData::new("", 2),
+            line_3_data: lcd::ScrollData::new("", 1),
+            line_4_data: lcd::ScrollData::new("", 1),
+            startup_line_1_data: lcd::ScrollData::new(&startup_line_1_text(config), 1),
             time_started_playing_current_station: chrono::Utc::now(),
-            position_and_duration: std::array::from_fn(|_index| RealTimeDataOnOneChannel::new()),
+            line_1_brief_message: None,
+            info_rotation_index: 0,
+            info_rotation_last_change: chrono::Utc::now(),
+            station_announcement_playing: false,
+            after_announcement: AfterAnnouncement::Nothing,
+            last_position_change: (ClockTime::ZERO, chrono::Utc::now()),
+            paused_as_audio_output_removed: false,
+            stop_at_end_of_album: false,
+            cd_track_select: false,
+            scroll_period_ms: config.scroll.scroll_period_ms,
+            clock_24h: config.clock_24h,
+            time_display: config.time_display,
+            system_muted: false,
+            station_preview: None,
+            deferred_play: None,
+            pending_seek: None,
+            stream_reconnect_due: None,
+            last_error: None,
+            last_error_ding_time: None,
+            // built as a Vec so the array is never on the stack
+            position_and_duration: std::iter::repeat_with(RealTimeDataOnOneChannel::new)
+                .take(NUMBER_OF_POSSIBLE_CHANNELS + 2)
+                .collect::<Vec<_>>()
+                .into_boxed_slice()
+                .try_into()
+                .unwrap_or_else(|_| unreachable!("the Vec has exactly the length of the array")),
         }
     }
     /// Initialises for a new station, sets time_started_playing_current_station, RunningStatus::RunningNormally,
@@ -123,6 +289,180 @@ impl PlayerStatus {
         self.time_started_playing_current_station = chrono::Utc::now();
         self.running_status = RunningStatus::RunningNormally;
         self.ping_data.number_of_pings_to_this_channel = 0;
+        self.position_and_duration[self.channel_number].samba_reconnect_attempts = 0;
+        self.position_and_duration[self.channel_number].stream_reconnect_attempts = 0;
+        self.cd_track_select = false;
+    }
+
+    /// Shows the error message on all 4 lines & keeps it, with the time, so the user can see it again with ShowLastError
+    pub fn show_error_on_all_4lines(&mut self, error_message: &str) {
+        self.all_4lines.update_if_changed(error_message);
+        self.running_status = RunningStatus::LongMessageOnAll4Lines;
+        self.last_error = Some(LastError {
+            message: error_message.to_string(),
+            time: chrono::Local::now(),
+        });
+    }
+
+    /// Returns the last error & the time it was shown, or "No recent errors" if there has not been one since a channel last played successfully
+    pub fn last_error_text(&self) -> String {
+        match &self.last_error {
+            Some(last_error) => format!(
+                "{} {}",
+                last_error.time.format("%H:%M:%S"),
+                last_error.message
+            ),
+            None => "No recent errors".to_string(),
+        }
+    }
+
+    /// Swaps between the 24 hour & the 12 hour clock & briefly shows which is now used
+    pub fn toggle_clock_format(&mut self) {
+        self.clock_24h = !self.clock_24h;
+        let message = if self.clock_24h {
+            "24 hour clock"
+        } else {
+            "12 hour clock"
+        };
+        self.line_1_brief_message = Some((message.to_string(), chrono::Utc::now()));
+    }
+
+    /// Swaps between showing the elapsed & the remaining time of a CD or USB track & briefly shows which is now used
+    pub fn toggle_time_display(&mut self) {
+        let (time_display, message) = match self.time_display {
+            read_config::TimeDisplay::Elapsed => {
+                (read_config::TimeDisplay::Remaining, "Time remaining")
+            }
+            read_config::TimeDisplay::Remaining => {
+                (read_config::TimeDisplay::Elapsed, "Time elapsed")
+            }
+        };
+        self.time_display = time_display;
+        self.line_1_brief_message = Some((message.to_string(), chrono::Utc::now()));
+    }
+
+    /// Returns the volume gstreamer is to play at, which is config.station_preview_volume_reduction below current_volume
+    /// while previewing channels, so the user can tell they are not yet listening properly
+    pub fn playing_volume(&self, config: &Config) -> i32 {
+        if self.station_preview.is_some() {
+            (self.current_volume - config.station_preview_volume_reduction).clamp(
+                crate::gstreamer_interfaces::VOLUME_MIN,
+                crate::gstreamer_interfaces::VOLUME_MAX,
+            )
+        } else {
+            self.current_volume
+        }
+    }
+
+    /// Returns true if the error ding can be played at the time now, as config.error_ding_cooldown has passed since it was last played,
+    /// in which case now is stored as the time it was last played
+    pub fn error_ding_is_due(&mut self, config: &Config, now: chrono::DateTime<Utc>) -> bool {
+        let cooldown_has_passed = self
+            .last_error_ding_time
+            .is_none_or(|last_error_ding_time| {
+                (now - last_error_ding_time)
+                    .to_std()
+                    .is_ok_and(|time_since_ding| time_since_ding >= config.error_ding_cooldown)
+            });
+        if cooldown_has_passed {
+            self.last_error_ding_time = Some(now);
+        }
+        cooldown_has_passed
+    }
+
+    /// Returns the real time data for channel_number, or None if channel_number is out of range,
+    /// so callers can report an invalid channel rather than panic.
+    /// Channels PODCAST_CHANNEL_NUMBER & START_UP_DING_CHANNEL_NUMBER are in range as they are used internally.
+    pub fn channel(&self, channel_number: usize) -> Option<&RealTimeDataOnOneChannel> {
+        self.position_and_duration.get(channel_number)
+    }
+
+    /// Returns the user defined character shown at the start of line 2 for the current channel, if its channel file specifies one
+    pub fn line_2_icon(&self) -> Option<u8> {
+        let icon = self.channel(self.channel_number)?.channel_data.icon?;
+        u8::try_from(icon).ok()
+    }
+
+    /// As channel, but returns a mutable reference
+    pub fn channel_mut(&mut self, channel_number: usize) -> Option<&mut RealTimeDataOnOneChannel> {
+        self.position_and_duration.get_mut(channel_number)
+    }
+
+    /// Returns the real time data for the channel in channel_number, or None if it is out of range
+    pub fn current_channel(&self) -> Option<&RealTimeDataOnOneChannel> {
+        self.channel(self.channel_number)
+    }
+
+    /// As current_channel, but returns a mutable reference
+    pub fn current_channel_mut(&mut self) -> Option<&mut RealTimeDataOnOneChannel> {
+        self.channel_mut(self.channel_number)
+    }
+
+    /// Returns the URL of the track being played, with the "file://" prefix removed from local files so it is easier to read.
+    /// Returns None if there is no track, eg as no channel has been selected yet.
+    pub fn current_url_for_display(&self) -> Option<&str> {
+        let channel = self.channel(self.channel_number)?;
+        let url = channel
+            .channel_data
+            .station_url
+            .get(channel.index_to_current_track)?;
+        Some(url.strip_prefix("file://").unwrap_or(url))
+    }
+
+    /// Changes scroll_period_ms by change_ms, keeping it within SCROLL_PERIOD_RANGE_MS, & briefly shows the new period on line 1
+    pub fn change_scroll_period(&mut self, change_ms: i64) {
+        self.scroll_period_ms = self
+            .scroll_period_ms
+            .saturating_add_signed(change_ms)
+            .clamp(
+                *SCROLL_PERIOD_RANGE_MS.start(),
+                *SCROLL_PERIOD_RANGE_MS.end(),
+            );
+        self.line_1_brief_message = Some((
+            format!("Scroll every {:.1}s", self.scroll_period_ms as f64 / 1000.0),
+            Utc::now(),
+        ));
+    }
+
+    /// Moves on to the next information to show on a free line if config.info_rotation_seconds have elapsed.
+    /// If config.info_rotation_seconds is zero, the information does not rotate.
+    pub fn advance_info_rotation(&mut self, config: &Config) {
+        if config.info_rotation_seconds > 0
+            && (chrono::Utc::now() - self.info_rotation_last_change).num_seconds()
+                >= config.info_rotation_seconds as i64
+        {
+            self.info_rotation_index = (self.info_rotation_index + 1) % lcd::NUMBER_OF_INFO_ITEMS;
+            self.info_rotation_last_change = chrono::Utc::now();
+        }
+    }
+
+    /// Returns true if config.stall_timeout_seconds is specified & the position has not advanced for that time while playing.
+    /// The time is restarted if gstreamer is not playing (eg paused), if something other than a channel is playing
+    /// or if the track is known to have zero duration, as the position is not expected to advance then.
+    pub fn playing_has_stalled(&mut self, config: &Config) -> bool {
+        let Some(stall_timeout_seconds) = config.stall_timeout_seconds else {
+            return false;
+        };
+        let now = chrono::Utc::now();
+        if self.channel_number >= START_UP_DING_CHANNEL_NUMBER {
+            return false; // dings are short, so cannot usefully stall
+        }
+        let channel = &self.position_and_duration[self.channel_number];
+        if self.gstreamer_state != gstreamer::State::Playing
+            || self.running_status != RunningStatus::RunningNormally
+            || self.station_announcement_playing
+            || channel.duration == Some(ClockTime::ZERO)
+            || channel.position != self.last_position_change.0
+        {
+            self.last_position_change = (channel.position, now);
+            return false;
+        }
+        if (now - self.last_position_change.1).num_seconds() >= stall_timeout_seconds as i64 {
+            self.last_position_change.1 = now; // so that we wait again before the next restart
+            true
+        } else {
+            false
+        }
     }
 
     /// outputs the config file
@@ -131,9 +471,29 @@ impl PlayerStatus {
             "\r\nconfigdata\r\naural_notifications\t\t{:?}\r",
             config.aural_notifications
         );
+        println!("error_ding_cooldown\t\t{:?}\r", config.error_ding_cooldown);
+        println!(
+            "check_notification_files\t{}\r",
+            config.check_notification_files
+        );
         println!("buffer_duration\t\t{:?}\r", config.buffer_duration);
+        println!("audio_sink\t\t\t{:?}\r", config.audio_sink);
         println!("initial_volume\t\t\t{}\r", config.initial_volume);
+        println!("mixer_control\t\t\t{}\r", config.mixer_control);
+        println!(
+            "station_preview_time\t\t{:?}\r",
+            config.station_preview_time
+        );
+        println!(
+            "station_preview_volume_reduction\t{}\r",
+            config.station_preview_volume_reduction
+        );
+        println!(
+            "station_preview_returns_to_previous\t{}\r",
+            config.station_preview_returns_to_previous
+        );
         println!("input_timeout\t\t\t{:?}\r", config.input_timeout);
+        println!("channel_digits\t\t\t{}\r", config.channel_digits);
         println!(
             "max_number_of_pings_to_a_remote_destination\t{}\r",
             config.max_number_of_remote_pings
@@ -145,13 +505,77 @@ impl PlayerStatus {
             config.goto_previous_track_time_delta
         );
         println!("config.start_times\t\t{:?}\r", config.start_times);
+        println!("fade_in_ms\t\t\t{:?}\r", config.fade_in_ms);
+        println!(
+            "wifi_connect_attempts\t\t{}\r",
+            config.wifi_connect_attempts
+        );
+        println!(
+            "wifi_connect_timeout\t\t{:?}\r",
+            config.wifi_connect_timeout
+        );
+        println!("split_title_artist\t\t{}\r", config.split_title_artist);
+        println!("now_playing_log\t\t\t{:?}\r", config.now_playing_log);
+        println!("autoplay_channel\t\t{:?}\r", config.autoplay_channel);
+        println!("backlight_off\t\t\t{:?}\r", config.backlight_off);
+        println!("timezone\t\t\t{:?}\r", config.timezone);
+        println!("clock_24h\t\t\t{}\r", config.clock_24h);
+        println!(
+            "keyboard_seek_seconds\t\t{}\r",
+            config.keyboard_seek_seconds
+        );
+        println!(
+            "burn_in_shift_minutes\t\t{:?}\r",
+            config.burn_in_shift_minutes
+        );
+        println!("favorites\t\t\t{:?}\r", config.favorites);
+        println!("rotary_encoder\t\t\t{:?}\r", config.rotary_encoder);
+        println!(
+            "info_rotation_seconds\t\t{}\r",
+            config.info_rotation_seconds
+        );
+        println!("lcd_refresh_ms\t\t\t{}\r", config.lcd_refresh_ms);
+        println!("position_poll_ms\t\t{}\r", config.position_poll_ms);
+        println!("announce_station\t\t{}\r", config.announce_station);
+        println!("ping_timeout_ms\t\t\t{}\r", config.ping_timeout_ms);
+        println!("ping_count\t\t\t{}\r", config.ping_count);
+        println!("volume_display\t\t\t{:?}\r", config.volume_display);
+        println!("time_display\t\t\t{:?}\r", config.time_display);
+        println!("startup_line1\t\t\t{:?}\r", config.startup_line1);
+        println!("buffer_bar_style\t\t{:?}\r", config.buffer_bar_style);
+        println!(
+            "stall_timeout_seconds\t\t{:?}\r",
+            config.stall_timeout_seconds
+        );
+        println!(
+            "max_stream_reconnect_attempts\t{}\r",
+            config.max_stream_reconnect_attempts
+        );
+        println!("name_corrections\t\t{:?}\r", config.name_corrections);
+        println!("audio_extensions\t\t{:?}\r", config.audio_extensions);
+        println!("local_media_base\t\t{:?}\r", config.local_media_base);
+        println!("keep_usb_mounted\t\t{}\r", config.keep_usb_mounted);
+        println!("retry_missing_albums\t\t{}\r", config.retry_missing_albums);
+        println!("equalizer_enabled\t\t{}\r", config.equalizer_enabled);
+        println!("custom_chars\t\t\t{:02x?}\r", config.custom_chars);
+        println!("wifi_bars_character\t\t{:?}\r", config.wifi_bars_character);
+        println!(
+            "wifi_pass_passphrase\t\t{}\r",
+            if config.wifi_pass_passphrase.is_some() {
+                "specified"
+            } else {
+                "not specified"
+            }
+        );
 
         println!("stations_directory\t\t{}\r", config.stations_directory);
+        println!("station_sources\t\t\t{:?}\r", config.station_sources);
         println!(
             "time_initial_message_displayed_after_channel_change\t{}\r",
             config.time_initial_message_displayed_after_channel_change
         );
         println!("volume_offset\t\t\t{}\r", config.volume_offset);
+        println!("volume_steps\t\t\t{:?}\r", config.volume_steps);
         println!("short_advance_time\t\t{}\r", config.short_advance_time);
         println!("long_advance_time\t\t{}\r", config.long_advance_time);
     }
@@ -182,6 +606,18 @@ impl PlayerStatus {
             report,
             "# this is useful for stations that are not very regular at providing input"
         )?;
+        writeln!(
+            report,
+            "volume = 85         # optional line; the volume used while this channel plays, instead of the user's volume"
+        )?;
+        writeln!(
+            report,
+            "ping_address = \"1.1.1.1\"         # optional line; the address to ping instead of the one derived from the first station_url"
+        )?;
+        writeln!(
+            report,
+            "playlist_file = \"/home/pi/radio.m3u\"         # optional line; an M3U or PLS playlist whose entries are played after any station_url entries"
+        )?;
         writeln!(report, "station_url = [")?;
         writeln!(
             report,
@@ -233,6 +669,10 @@ impl PlayerStatus {
             "version = \"2.0\" #optional entry to specify the Samba version to use"
         )?;
         writeln!(report, "mount_folder = \"/home/pi/remote_mount_folder/\"")?;
+        writeln!(
+            report,
+            "max_tracks = 20 #optional entry to limit the number of tracks played from the device"
+        )?;
         writeln!(
             report,
             "[media_details.authetication_data]     #omit this entry if there is no authentication data"
@@ -266,7 +706,7 @@ impl PlayerStatus {
         Ok(report)
     }
 
-    /// generates a list of the valid channels and sorts them into numeric order
+    /// generates a list of the valid channels in all the stations directories and sorts them into numeric order
     /// if there are any TOML errors, they are output first  
     /// TBD check the extension is correct ie is .toml
     pub fn generate_list_of_valid_channels(
@@ -277,51 +717,58 @@ impl PlayerStatus {
         let mut report = String::new();
         writeln!(report, "\nList of valid channels")?;
 
-        let file_names_in_playlist_folder =
-            std::fs::read_dir(&config.stations_directory).map_err(|_read_error| std::fmt::Error)?;
-
         let mut channel_number: Vec<String> = Vec::new(); //declare storage for the data we are about to display 
         let mut channel_name: Vec<String> = Vec::new(); // ditto
 
-        // look for channel files in the list
-        for file_name_in_playlist_folder in file_names_in_playlist_folder.flatten() {
-            // we have a filename, but does it start with 2 digits
-            let filename = file_name_in_playlist_folder
-                .file_name()
-                .to_string_lossy()
-                .to_string();
-
-            if filename.to_lowercase().ends_with(".toml")
-                && filename.len() > 1
-                && str::parse::<i8>(filename.substring(0, 2)).is_ok()
-            {
-                // now we know that file name starts with 2 digits, ie is a valid channel
-                let channel_file_info =
-                    std::fs::read_to_string(file_name_in_playlist_folder.path())
-                        .map_err(|_| std::fmt::Error)?;
-
-                let toml_result: Result<
-                    get_channel_details::ChannelFileDataDecoded,
-                    toml::de::Error,
-                > = toml::from_str(channel_file_info.trim_ascii_end());
-
-                match toml_result {
-                    Ok(toml_data) => {
-                        channel_number.push(
-                            file_name_in_playlist_folder
-                                .file_name()
-                                .to_string_lossy()
-                                .to_string(),
-                        );
-                        channel_name.push(toml_data.organisation);
-                    }
-                    Err(toml_error) => {
-                        writeln!(
-                            report,
-                            "{} \t{:?}",
-                            file_name_in_playlist_folder.file_name().to_string_lossy(),
-                            toml_error
-                        )?;
+        // the channel files are in stations_directory & in the directory of each of station_sources
+        for stations_directory in config.all_stations_directories() {
+            let file_names_in_playlist_folder =
+                std::fs::read_dir(stations_directory).map_err(|_read_error| std::fmt::Error)?;
+
+            // look for channel files in the list
+            for file_name_in_playlist_folder in file_names_in_playlist_folder.flatten() {
+                // we have a filename, but does it start with config.channel_digits digits
+                let filename = file_name_in_playlist_folder
+                    .file_name()
+                    .to_string_lossy()
+                    .to_string();
+
+                if filename.to_lowercase().ends_with(".toml")
+                && filename.len() >= config.channel_digits
+                && str::parse::<u16>(filename.substring(0, config.channel_digits)).is_ok()
+                // a file hidden by a station source is not used, so is not listed
+                && config.channel_is_in_directory(
+                    filename.substring(0, config.channel_digits),
+                    stations_directory,
+                ) {
+                    // now we know that file name starts with config.channel_digits digits, ie is a valid channel
+                    let channel_file_info =
+                        std::fs::read_to_string(file_name_in_playlist_folder.path())
+                            .map_err(|_| std::fmt::Error)?;
+
+                    let toml_result: Result<
+                        get_channel_details::ChannelFileDataDecoded,
+                        toml::de::Error,
+                    > = toml::from_str(channel_file_info.trim_ascii_end());
+
+                    match toml_result {
+                        Ok(toml_data) => {
+                            channel_number.push(
+                                file_name_in_playlist_folder
+                                    .file_name()
+                                    .to_string_lossy()
+                                    .to_string(),
+                            );
+                            channel_name.push(toml_data.organisation);
+                        }
+                        Err(toml_error) => {
+                            writeln!(
+                                report,
+                                "{} \t{:?}",
+                                file_name_in_playlist_folder.file_name().to_string_lossy(),
+                                toml_error
+                            )?;
+                        }
                     }
                 }
             }
@@ -343,7 +790,10 @@ impl PlayerStatus {
     }
 
     /// reports whether or not the amplifier is muted & the status information
-    pub fn generate_rradio_report(&self) -> Result<String, std::fmt::Error> {
+    pub fn generate_rradio_report(
+        &self,
+        command_runner: &impl crate::command_runner::CommandRunner,
+    ) -> Result<String, std::fmt::Error> {
         use std::fmt::Write;
         let mut report = String::new();
 
@@ -351,12 +801,12 @@ impl PlayerStatus {
         writeln!(
             report,
             "Throttled_status\t{:?}",
-            lcd::get_throttled::is_throttled()
+            lcd::get_throttled::is_throttled(command_runner)
         )?;
         writeln!(
             report,
             "Temperature & Wi-Fi\t{}",
-            lcd::Lc::get_temperature_and_wifi_strength_text()
+            lcd::Lc::get_temperature_and_wifi_strength_text(None)
         )?;
         writeln!(
             report,
@@ -369,6 +819,11 @@ impl PlayerStatus {
         writeln!(report, "startup folder\t\t{}", self.startup_folder)?;
         writeln!(report, "channel_number\t\t{}", self.channel_number)?;
         writeln!(report, "current_volume\t\t{}", self.current_volume)?;
+        writeln!(
+            report,
+            "volume_before_channel_override\t{:?}",
+            self.volume_before_channel_override
+        )?;
         writeln!(
             report,
             "podcast_data_from_toml\t{:?}",
@@ -388,6 +843,8 @@ impl PlayerStatus {
         writeln!(report, "line_1_data\t\t{:?}", self.line_1_data)?;
         writeln!(report, "line_2_data\t\t{:?}", self.line_2_data)?;
         writeln!(report, "line_34_data\t\t{:?}", self.line_34_data)?;
+        writeln!(report, "line_3_data\t\t{:?}", self.line_3_data)?;
+        writeln!(report, "line_4_data\t\t{:?}", self.line_4_data)?;
         writeln!(
             report,
             "time_started_playing_current_station\t{}",
@@ -471,6 +928,21 @@ impl PlayerStatus {
                     "\tchannel_data.media_details\t\t{:?}",
                     channel_realtime_data.channel_data.media_details
                 )?;
+                writeln!(
+                    report,
+                    "\tchannel_data.volume\t\t\t{:?}",
+                    channel_realtime_data.channel_data.volume
+                )?;
+                writeln!(
+                    report,
+                    "\tchannel_data.ping_address\t\t{:?}",
+                    channel_realtime_data.channel_data.ping_address
+                )?;
+                writeln!(
+                    report,
+                    "\tchannel_data.playlist_file\t\t{:?}",
+                    channel_realtime_data.channel_data.playlist_file
+                )?;
 
                 writeln!(report, "\n\tTrack information follows")?;
 
@@ -487,4 +959,86 @@ impl PlayerStatus {
 
         Ok(report)
     }
+
+    /// Writes the whole of status_of_rradio as JSON to a timestamped file in the startup folder,
+    /// so it can be sent to someone else to help diagnose a problem.
+    /// Returns the path of the file if successful.
+    pub fn dump_status_to_file(&self) -> Result<String, String> {
+        let file_path = format!(
+            "{}status_dump_{}.json",
+            self.startup_folder,
+            chrono::Local::now().format("%Y%m%d_%H%M%S")
+        );
+        let status_as_json = serde_json::to_string_pretty(self)
+            .map_err(|error| format!("When converting the status to JSON got error {error}"))?;
+        std::fs::write(&file_path, status_as_json)
+            .map_err(|error| format!("When writing {file_path} got error {error}"))?;
+        Ok(file_path)
+    }
+
+    /// Checks that files can be written in the startup folder by writing, then deleting, a small file.
+    /// The now playing log, the podcast list & status dumps are written there, & if the SD card is read-only or full
+    /// the errors they give are cryptic, so this lets us give a clear message at startup instead.
+    pub fn check_startup_folder_is_writable(&self) -> Result<(), String> {
+        let file_path = format!("{}.rradio_write_test", self.startup_folder);
+        let write_result = std::fs::write(&file_path, "rradio write test");
+        let _ = std::fs::remove_file(&file_path); // the write may have created the file even if it failed
+        write_result.map_err(|error| format!("When writing {file_path} got error {error}"))
+    }
+}
+
+/// Used by serde to serialise fields, such as gstreamer & chrono types, whose types do not implement serde::Serialize
+pub fn serialize_using_debug<T: std::fmt::Debug, S: serde::Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!("{value:?}"))
+}
+
+/// Used by serde to serialise position_and_duration, as serde cannot serialise arrays with more than 32 entries
+fn serialize_all_channels<S: serde::Serializer>(
+    all_channels: &[RealTimeDataOnOneChannel; NUMBER_OF_POSSIBLE_CHANNELS + 2],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(all_channels.iter())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_ding_is_not_due_again_until_the_cooldown_has_passed() {
+        let config = Config {
+            error_ding_cooldown: std::time::Duration::from_secs(5),
+            ..Default::default()
+        };
+        let mut status_of_rradio = PlayerStatus::new(&config);
+        let start = chrono::Utc::now();
+        assert!(status_of_rradio.error_ding_is_due(&config, start));
+        assert!(!status_of_rradio.error_ding_is_due(&config, start + chrono::Duration::seconds(2)));
+        assert!(!status_of_rradio.error_ding_is_due(&config, start + chrono::Duration::seconds(4)));
+        assert!(status_of_rradio.error_ding_is_due(&config, start + chrono::Duration::seconds(5)));
+    }
+
+    #[test]
+    fn playing_volume_is_reduced_only_while_previewing() {
+        let config = Config {
+            initial_volume: 50,
+            station_preview_volume_reduction: 15,
+            ..Default::default()
+        };
+        let mut status_of_rradio = PlayerStatus::new(&config);
+        assert_eq!(status_of_rradio.playing_volume(&config), 50);
+        status_of_rradio.station_preview = Some(StationPreview {
+            previous_channel: None,
+            ends_at: None,
+        });
+        assert_eq!(status_of_rradio.playing_volume(&config), 35);
+        status_of_rradio.current_volume = crate::gstreamer_interfaces::VOLUME_MIN + 5;
+        assert_eq!(
+            status_of_rradio.playing_volume(&config),
+            crate::gstreamer_interfaces::VOLUME_MIN
+        );
+    }
 }
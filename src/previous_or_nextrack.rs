@@ -4,49 +4,46 @@
 use super::PlaybinElement;
 use super::PlayerStatus;
 use super::RunningStatus;
-use super::get_channel_details::SourceType;
+use super::command_runner::CommandRunner;
+use super::get_channel_details::{CD_ORGANISATION_WITHOUT_CD_TEXT, SourceType};
 use super::lcd;
-use gstreamer::{SeekFlags, prelude::ElementExtManual};
+use super::player_status::RealTimeDataOnOneChannel;
+use gstreamer::{ClockTime, SeekFlags, prelude::ElementExtManual};
 use itertools::Itertools;
 
-/// Generates the text for line 2 for the normal running case, ie streaming, USB or CD. Adds the throttled state if the Pi is throttled
-pub fn generate_line2(status_of_rradio: &PlayerStatus) -> String {
+/// Generates the text for line 2 for the normal running case, ie streaming, USB or CD. Adds the throttled state if the Pi is throttled,
+/// debounced so that a transient throttle does not make line 2 flicker
+pub fn generate_line2(
+    status_of_rradio: &PlayerStatus,
+    command_runner: &impl CommandRunner,
+) -> String {
     let mut line2 = match status_of_rradio.position_and_duration[status_of_rradio.channel_number]
         .channel_data
         .source_type
     {
         SourceType::Cd => {
-            let mut num_tracks = status_of_rradio.position_and_duration
+            let num_tracks = status_of_rradio.position_and_duration
+                [status_of_rradio.channel_number]
+                .playable_track_count();
+            let track_number = status_of_rradio.position_and_duration
+                [status_of_rradio.channel_number]
+                .index_to_current_track
+                + 1; // +1 as humans start counting at 1, not zero
+            let organisation = &status_of_rradio.position_and_duration
                 [status_of_rradio.channel_number]
                 .channel_data
-                .station_url
-                .len();
-            if status_of_rradio.position_and_duration[status_of_rradio.channel_number]
-                .channel_data
-                .last_track_is_a_ding
-            {
-                num_tracks -= 1
+                .organisation;
+            if organisation == CD_ORGANISATION_WITHOUT_CD_TEXT {
+                format!("CD track {} of {}", track_number, num_tracks)
+            } else {
+                // the organisation is the album name from the CD-TEXT
+                format!("{} ({} of {})", organisation, track_number, num_tracks)
             }
-            format!(
-                "CD track {} of {}",
-                status_of_rradio.position_and_duration[status_of_rradio.channel_number]
-                    .index_to_current_track
-                    + 1, // +1 as humans start counting at 1, not zero
-                num_tracks
-            )
         }
         SourceType::Usb => {
-            let mut num_tracks = status_of_rradio.position_and_duration
+            let num_tracks = status_of_rradio.position_and_duration
                 [status_of_rradio.channel_number]
-                .channel_data
-                .station_url
-                .len();
-            if status_of_rradio.position_and_duration[status_of_rradio.channel_number]
-                .channel_data
-                .last_track_is_a_ding
-            {
-                num_tracks -= 1
-            }
+                .playable_track_count();
 
             let info = if status_of_rradio.position_and_duration[status_of_rradio.channel_number]
                 .channel_data
@@ -106,7 +103,7 @@ pub fn generate_line2(status_of_rradio: &PlayerStatus) -> String {
                 num_tracks
             )
         }
-        SourceType::UrlList => status_of_rradio.position_and_duration
+        SourceType::UrlList | SourceType::Playlist => status_of_rradio.position_and_duration
             [status_of_rradio.channel_number]
             .channel_data
             .organisation
@@ -129,7 +126,7 @@ pub fn generate_line2(status_of_rradio: &PlayerStatus) -> String {
             _ => "Unknown source type".to_string(),
         },
     };
-    let throttled_status = lcd::get_throttled::is_throttled();
+    let throttled_status = lcd::get_throttled::is_throttled_debounced(command_runner);
     if throttled_status.pi_is_throttled {
         line2 = format!("{line2} {}", throttled_status.result)
     };
@@ -137,34 +134,227 @@ pub fn generate_line2(status_of_rradio: &PlayerStatus) -> String {
     line2
 }
 
+/// Seeks seek_seconds forwards (or backwards if negative) within the current track, staying within the track.
+/// Only CDs & USB devices (local or Samba) can seek; for streams, a brief message is shown instead.
+pub fn seek_within_track(
+    status_of_rradio: &mut PlayerStatus,
+    playbin: &PlaybinElement,
+    seek_seconds: i64,
+) {
+    let channel = &status_of_rradio.position_and_duration[status_of_rradio.channel_number];
+    match (&channel.channel_data.source_type, channel.duration) {
+        (SourceType::Cd | SourceType::Usb, Some(duration)) => {
+            let new_position = ClockTime::from_nseconds(
+                channel
+                    .position
+                    .nseconds()
+                    .saturating_add_signed(seek_seconds * 1_000_000_000),
+            )
+            .min(duration); // must not seek beyond the end of the track
+
+            if let Err(error) = playbin.playbin_element.seek_simple(
+                SeekFlags::FLUSH | SeekFlags::KEY_UNIT | SeekFlags::SNAP_NEAREST,
+                new_position,
+            ) {
+                eprintln!("When seeking got error {error}\r");
+            }
+        }
+        _ => {
+            status_of_rradio.line_1_brief_message =
+                Some(("Can't seek live".to_string(), chrono::Utc::now()));
+        }
+    }
+}
+
+/// Seeks to the start of the current track, however long it has been playing.
+/// Only CDs & USB devices (local or Samba) can restart; for streams, a brief message is shown instead.
+pub fn restart_track(status_of_rradio: &mut PlayerStatus, playbin: &PlaybinElement) {
+    match status_of_rradio.position_and_duration[status_of_rradio.channel_number]
+        .channel_data
+        .source_type
+    {
+        SourceType::Cd | SourceType::Usb => {
+            if let Err(error) = playbin
+                .playbin_element
+                .seek_simple(SeekFlags::FLUSH | SeekFlags::KEY_UNIT, ClockTime::ZERO)
+            {
+                eprintln!("When restarting the track got error {error}\r");
+            }
+        }
+        _ => {
+            status_of_rradio.line_1_brief_message =
+                Some(("Can't restart stream".to_string(), chrono::Utc::now()));
+        }
+    }
+}
+
+/// Returns true if a CD or USB device (local or Samba) is playing the last of its tracks, including any ding at the end
+pub fn is_playing_last_track_of_album(status_of_rradio: &PlayerStatus) -> bool {
+    let channel = &status_of_rradio.position_and_duration[status_of_rradio.channel_number];
+    matches!(
+        channel.channel_data.source_type,
+        SourceType::Cd | SourceType::Usb
+    ) && channel.index_to_current_track + 1 >= channel.channel_data.station_url.len()
+}
+
+/// Turns stop_at_end_of_album on or off, confirming the choice with a brief message on line 1.
+/// Only CDs & USB devices (local or Samba) have an end; for streams, a brief message is shown instead.
+pub fn toggle_stop_at_end_of_album(status_of_rradio: &mut PlayerStatus) {
+    let message = match status_of_rradio.position_and_duration[status_of_rradio.channel_number]
+        .channel_data
+        .source_type
+    {
+        SourceType::Cd | SourceType::Usb => {
+            status_of_rradio.stop_at_end_of_album = !status_of_rradio.stop_at_end_of_album;
+            if status_of_rradio.stop_at_end_of_album {
+                "Stop at end of album"
+            } else {
+                "Play album repeatedly"
+            }
+        }
+        _ => "Streams have no end",
+    };
+    status_of_rradio.line_1_brief_message = Some((message.to_string(), chrono::Utc::now()));
+}
+
+/// Called when the user presses the track select key. If the current channel is a CD, the next number typed selects a track
+/// rather than a channel, & a prompt is shown until it is typed; pressing the key again cancels the track selection
+pub fn toggle_cd_track_select(status_of_rradio: &mut PlayerStatus, channel_digits: usize) {
+    let channel = &status_of_rradio.position_and_duration[status_of_rradio.channel_number];
+    if channel.channel_data.source_type != SourceType::Cd {
+        status_of_rradio.line_1_brief_message =
+            Some(("Not playing a CD".to_string(), chrono::Utc::now()));
+        return;
+    }
+    status_of_rradio.cd_track_select = !status_of_rradio.cd_track_select;
+    if status_of_rradio.cd_track_select {
+        let prompt = format!(
+            "Select track: type its number as {channel_digits} digits, 1 to {}",
+            channel.playable_track_count()
+        );
+        status_of_rradio
+            .all_4lines
+            .update_if_changed(prompt.as_str());
+        status_of_rradio.running_status = RunningStatus::LongMessageOnAll4Lines;
+    } else {
+        status_of_rradio.running_status = RunningStatus::RunningNormally;
+    }
+}
+
+/// Returns the index of the track with the specified number, counting from 1, or an error if the channel has no such track
+fn cd_track_index(
+    channel: &RealTimeDataOnOneChannel,
+    track_number: usize,
+) -> Result<usize, String> {
+    let number_of_tracks = channel.playable_track_count();
+    if (1..=number_of_tracks).contains(&track_number) {
+        Ok(track_number - 1)
+    } else {
+        Err(format!(
+            "There is no track {track_number}; the CD has tracks 1 to {number_of_tracks}"
+        ))
+    }
+}
+
+/// Plays the track with the number typed by the user while in track select mode, which it ends
+pub fn play_cd_track(
+    track_number: usize,
+    status_of_rradio: &mut PlayerStatus,
+    playbin: &PlaybinElement,
+    config: &crate::read_config::Config,
+    lcd: &mut crate::lcd::Lc,
+    command_runner: &impl CommandRunner,
+) {
+    status_of_rradio.cd_track_select = false;
+    let channel = &mut status_of_rradio.position_and_duration[status_of_rradio.channel_number];
+    let new_index = match cd_track_index(channel, track_number) {
+        Ok(new_index) => new_index,
+        Err(error_message) => {
+            status_of_rradio.show_error_on_all_4lines(error_message.as_str());
+            return;
+        }
+    };
+    change_track(channel, new_index);
+    status_of_rradio.running_status = RunningStatus::RunningNormally;
+    status_of_rradio.ping_data.number_of_pings_to_this_channel = 0;
+    if let Err(playbin_error_message) =
+        playbin.play_track(status_of_rradio, config, lcd, false, command_runner)
+    {
+        status_of_rradio.show_error_on_all_4lines(
+            format!("When wanting to play track {track_number} got {playbin_error_message}")
+                .as_str(),
+        );
+    } else {
+        let line2 = generate_line2(status_of_rradio, command_runner);
+        status_of_rradio
+            .line_2_data
+            .update_if_changed(line2.as_str());
+    }
+}
+
+/// Makes new_index the current track.
+/// gstreamer plays each CD track as a separate cdda:// URL, so the position it reports is within the track,
+/// but the stored position would show the end of the previous track until the next position poll;
+/// so for a CD the position & duration are reset, which makes the elapsed time restart at zero for every track.
+fn change_track(channel: &mut RealTimeDataOnOneChannel, new_index: usize) {
+    if new_index != channel.index_to_current_track
+        && channel.channel_data.source_type == SourceType::Cd
+    {
+        channel.position = ClockTime::ZERO;
+        channel.duration = None;
+    }
+    channel.index_to_current_track = new_index;
+}
+
+/// Returns the index of the track after the current one, wrapping round to the first track,
+/// or None if there are no tracks (eg an empty album), which would otherwise divide by zero
+fn next_track_index(channel: &RealTimeDataOnOneChannel) -> Option<usize> {
+    (channel.index_to_current_track + 1).checked_rem(channel.channel_data.station_url.len())
+}
+
+/// Returns the index of the track before the current one, wrapping round to the last track,
+/// or None if there are no tracks (eg an empty album), which would otherwise divide by zero
+fn previous_track_index(channel: &RealTimeDataOnOneChannel) -> Option<usize> {
+    let number_of_tracks = channel.channel_data.station_url.len();
+    (number_of_tracks > 0).then(|| {
+        (channel.index_to_current_track + number_of_tracks - 1) % number_of_tracks // % is a remainder operator not modulo
+    })
+}
+
+/// Tells the user that the channel has no tracks to change to
+fn show_no_tracks(status_of_rradio: &mut PlayerStatus) {
+    status_of_rradio.show_error_on_all_4lines(
+        format!("No tracks on channel {}", status_of_rradio.channel_number).as_str(),
+    );
+}
+
 /// Plays the next track by modulo incrementing status_of_rradio.index_to_current_track
 pub fn next_track(
     status_of_rradio: &mut PlayerStatus,
     playbin: &PlaybinElement,
     config: &crate::read_config::Config,
     lcd: &mut crate::lcd::Lc,
+    command_runner: &impl CommandRunner,
 ) {
+    let channel = &mut status_of_rradio.position_and_duration[status_of_rradio.channel_number];
+    let Some(new_index) = next_track_index(channel) else {
+        show_no_tracks(status_of_rradio);
+        return;
+    };
+    change_track(channel, new_index);
     status_of_rradio.running_status = RunningStatus::RunningNormally; // at least hope that this is true
     status_of_rradio.ping_data.number_of_pings_to_this_channel = 0;
-    status_of_rradio.position_and_duration[status_of_rradio.channel_number]
-        .index_to_current_track = (status_of_rradio.position_and_duration
-        [status_of_rradio.channel_number]
-        .index_to_current_track
-        + 1)
-        % status_of_rradio.position_and_duration[status_of_rradio.channel_number]
-            .channel_data
-            .station_url
-            .len();
-    if let Err(playbin_error_message) = playbin.play_track(status_of_rradio, config, lcd, false) {
-        status_of_rradio.all_4lines.update_if_changed(
+    if let Err(playbin_error_message) =
+        playbin.play_track(status_of_rradio, config, lcd, false, command_runner)
+    {
+        status_of_rradio.show_error_on_all_4lines(
             format!(
                 "When wanting to play the next track playing a track got {playbin_error_message}"
             )
             .as_str(),
         );
-        status_of_rradio.running_status = RunningStatus::LongMessageOnAll4Lines;
     } else {
-        let line2 = generate_line2(status_of_rradio);
+        let line2 = generate_line2(status_of_rradio, command_runner);
         status_of_rradio
             .line_2_data
             .update_if_changed(line2.as_str());
@@ -176,6 +366,7 @@ pub fn previous_track(
     playbin: &PlaybinElement,
     config: &crate::read_config::Config,
     lcd: &mut crate::lcd::Lc,
+    command_runner: &impl CommandRunner,
 ) {
     status_of_rradio.initialise_for_new_station();
     if status_of_rradio.position_and_duration[status_of_rradio.channel_number].position
@@ -188,27 +379,20 @@ pub fn previous_track(
         );
     } else {
         // we have only just started, so user wants the previous track
-        status_of_rradio.position_and_duration[status_of_rradio.channel_number]
-            .index_to_current_track = (status_of_rradio.position_and_duration
-            [status_of_rradio.channel_number]
-            .index_to_current_track
-            + status_of_rradio.position_and_duration[status_of_rradio.channel_number]
-                .channel_data
-                .station_url
-                .len()
-            - 1)
-            % status_of_rradio.position_and_duration[status_of_rradio.channel_number]
-                .channel_data
-                .station_url
-                .len(); // % is a remainder operator not modulo
+        let channel = &mut status_of_rradio.position_and_duration[status_of_rradio.channel_number];
+        let Some(new_index) = previous_track_index(channel) else {
+            show_no_tracks(status_of_rradio);
+            return;
+        };
+        change_track(channel, new_index);
 
-        if let Err(playbin_error_message) = playbin.play_track(status_of_rradio, config, lcd, false)
+        if let Err(playbin_error_message) =
+            playbin.play_track(status_of_rradio, config, lcd, false, command_runner)
         {
-            status_of_rradio.all_4lines.update_if_changed(
+            status_of_rradio.show_error_on_all_4lines(
                 format!("When wanting to play the previous track got {playbin_error_message}")
                     .as_str(),
             );
-            status_of_rradio.running_status = RunningStatus::LongMessageOnAll4Lines;
         } else {
             status_of_rradio.line_2_data.update_if_changed(
                 status_of_rradio.position_and_duration[status_of_rradio.channel_number]
@@ -220,3 +404,127 @@ pub fn previous_track(
     }
     //qq
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a CD channel with the specified number of tracks, playing the last track 50 seconds in
+    fn cd_channel_playing_last_track(number_of_tracks: usize) -> RealTimeDataOnOneChannel {
+        let mut channel = RealTimeDataOnOneChannel::new();
+        channel.channel_data.source_type = SourceType::Cd;
+        channel.channel_data.station_url = (1..=number_of_tracks)
+            .map(|track| format!("cdda://{track}"))
+            .collect();
+        channel.index_to_current_track = number_of_tracks - 1;
+        channel.position = ClockTime::from_seconds(50);
+        channel.duration = Some(ClockTime::from_seconds(180));
+        channel
+    }
+
+    #[test]
+    fn cd_position_restarts_when_wrapping_from_the_last_track_to_the_first() {
+        let mut channel = cd_channel_playing_last_track(12);
+        change_track(
+            &mut channel,
+            (channel.index_to_current_track + 1) % channel.channel_data.station_url.len(),
+        );
+        assert_eq!(channel.index_to_current_track, 0);
+        assert_eq!(channel.position, ClockTime::ZERO);
+        assert_eq!(channel.duration, None);
+    }
+
+    #[test]
+    fn position_is_kept_if_the_track_does_not_change_or_it_is_not_a_cd() {
+        let mut channel = cd_channel_playing_last_track(1); // with one track, the next track is the same track
+        change_track(&mut channel, 0);
+        assert_eq!(channel.position, ClockTime::from_seconds(50));
+
+        let mut channel = cd_channel_playing_last_track(3);
+        channel.channel_data.source_type = SourceType::Usb;
+        change_track(&mut channel, 0);
+        assert_eq!(channel.index_to_current_track, 0);
+        assert_eq!(channel.position, ClockTime::from_seconds(50));
+    }
+
+    #[test]
+    fn changing_track_on_a_channel_without_tracks_does_not_panic() {
+        let channel = RealTimeDataOnOneChannel::new();
+        assert!(channel.channel_data.station_url.is_empty());
+        assert_eq!(next_track_index(&channel), None);
+        assert_eq!(previous_track_index(&channel), None);
+
+        let mut status_of_rradio = PlayerStatus::new(&crate::read_config::Config::default());
+        show_no_tracks(&mut status_of_rradio);
+        assert_eq!(
+            status_of_rradio.running_status,
+            RunningStatus::LongMessageOnAll4Lines
+        );
+    }
+
+    #[test]
+    fn errors_are_kept_so_they_can_be_shown_again() {
+        let mut status_of_rradio = PlayerStatus::new(&crate::read_config::Config::default());
+        assert_eq!(status_of_rradio.last_error_text(), "No recent errors");
+        show_no_tracks(&mut status_of_rradio);
+        let last_error_text = status_of_rradio.last_error_text();
+        assert!(
+            last_error_text.ends_with(
+                format!(" No tracks on channel {}", status_of_rradio.channel_number).as_str()
+            ),
+            "{last_error_text}"
+        );
+    }
+
+    #[test]
+    fn track_indices_wrap_round() {
+        let channel = cd_channel_playing_last_track(3);
+        assert_eq!(next_track_index(&channel), Some(0));
+        assert_eq!(previous_track_index(&channel), Some(1));
+    }
+
+    #[test]
+    fn cd_track_numbers_are_checked_against_the_tracks_on_the_cd() {
+        let channel = cd_channel_playing_last_track(12);
+        assert_eq!(cd_track_index(&channel, 1), Ok(0));
+        assert_eq!(cd_track_index(&channel, 7), Ok(6));
+        assert_eq!(cd_track_index(&channel, 12), Ok(11));
+        assert_eq!(
+            cd_track_index(&channel, 0),
+            Err("There is no track 0; the CD has tracks 1 to 12".to_string())
+        );
+        assert!(cd_track_index(&channel, 13).is_err());
+    }
+
+    #[test]
+    fn track_select_is_only_entered_when_playing_a_cd() {
+        let mut status_of_rradio = PlayerStatus::new(&crate::read_config::Config::default());
+        status_of_rradio.channel_number = 3;
+        toggle_cd_track_select(&mut status_of_rradio, 2);
+        assert!(!status_of_rradio.cd_track_select);
+
+        status_of_rradio.position_and_duration[3] = cd_channel_playing_last_track(12);
+        toggle_cd_track_select(&mut status_of_rradio, 2);
+        assert!(status_of_rradio.cd_track_select);
+        assert_eq!(
+            status_of_rradio.all_4lines.text,
+            "Select track: type its number as 2 digits, 1 to 12"
+        );
+        toggle_cd_track_select(&mut status_of_rradio, 2);
+        assert!(!status_of_rradio.cd_track_select);
+        assert_eq!(
+            status_of_rradio.running_status,
+            RunningStatus::RunningNormally
+        );
+    }
+
+    #[test]
+    fn playable_track_count_excludes_the_ding_without_underflowing() {
+        let mut channel = cd_channel_playing_last_track(3);
+        assert_eq!(channel.playable_track_count(), 3);
+        channel.channel_data.last_track_is_a_ding = true;
+        assert_eq!(channel.playable_track_count(), 2);
+        channel.channel_data.station_url.clear();
+        assert_eq!(channel.playable_track_count(), 0);
+    }
+}
@@ -5,8 +5,6 @@ use chrono::DateTime;
 use gstreamer::ClockTime;
 use string_replace_all::StringReplaceAll;
 
-use crate::player_status::NUMBER_OF_POSSIBLE_CHANNELS;
-
 /// used to convert a TOML string to clock time
 fn deserialize_clocktime<'de, D: serde::Deserializer<'de>>(
     // "de" is, by convention, the name of the lifetime of the input.
@@ -19,6 +17,114 @@ fn deserialize_clocktime<'de, D: serde::Deserializer<'de>>(
     })
 }
 
+/// Corrections to station names that are always wanted, as the stations send broken metadata.
+/// The user's [name_corrections] are added to these, & replace them if they have the same raw name
+const BUILT_IN_NAME_CORRECTIONS: [(&str, &str); 2] = [
+    ("LaPremiere", "La Première"),
+    (
+        "Nostalgie Chansons fran??aises",
+        "Nostalgie Chansons françaises",
+    ),
+];
+
+/// Returns BUILT_IN_NAME_CORRECTIONS as a map
+fn built_in_name_corrections() -> std::collections::HashMap<String, String> {
+    BUILT_IN_NAME_CORRECTIONS
+        .iter()
+        .map(|(raw_name, display_name)| (raw_name.to_string(), display_name.to_string()))
+        .collect()
+}
+
+/// used to add the user's [name_corrections] to the built in ones, rather than replace them all
+fn deserialize_name_corrections<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<std::collections::HashMap<String, String>, D::Error> {
+    let mut name_corrections = built_in_name_corrections();
+    name_corrections.extend(std::collections::HashMap::<String, String>::deserialize(
+        deserializer,
+    )?);
+    Ok(name_corrections)
+}
+
+/// used to parse the timezone once, when the config file is read, rather than every time the clock is read
+fn deserialize_timezone<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<chrono_tz::Tz>, D::Error> {
+    let timezone = String::deserialize(deserializer)?;
+    timezone.parse::<chrono_tz::Tz>().map(Some).map_err(|_error| {
+        serde::de::Error::custom(format!(
+            "timezone {timezone} is not a known time zone; it should be an IANA name such as \"Europe/London\""
+        ))
+    })
+}
+
+/// The most digits a channel number can have; player_status::NUMBER_OF_POSSIBLE_CHANNELS allows for this many
+pub const MAXIMUM_CHANNEL_DIGITS: usize = 3;
+
+/// The file extensions that are played from USB devices (local or Samba) if config.audio_extensions is not specified
+const DEFAULT_AUDIO_EXTENSIONS: [&str; 5] = ["mp3", "wav", "ogg", "flac", "m4a"];
+
+/// Returns DEFAULT_AUDIO_EXTENSIONS as a Vec
+fn default_audio_extensions() -> Vec<String> {
+    DEFAULT_AUDIO_EXTENSIONS
+        .iter()
+        .map(|extension| extension.to_string())
+        .collect()
+}
+
+/// used to convert the user's audio_extensions to lower case, so that "MP3" matches "track.mp3"
+fn deserialize_audio_extensions<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    Ok(Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|extension| extension.to_ascii_lowercase())
+        .collect())
+}
+
+/// used to convert the [custom_chars] table, whose keys are the character numbers 0 to 7 & whose values are the 8 rows as hex bytes,
+/// eg 0 = "10 10 10 10 10 10 10 1f", into the bitmaps for all 8 characters; characters that are not specified keep their built in bitmaps
+fn deserialize_custom_chars<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<[[u8; 8]; 8]>, D::Error> {
+    let mut bitmaps = crate::lcd::character_pattern::BITMAPS;
+    for (character_number, rows_as_hex) in
+        std::collections::HashMap::<String, String>::deserialize(deserializer)?
+    {
+        let bitmap = character_number
+            .parse::<usize>()
+            .ok()
+            .and_then(|character_number| bitmaps.get_mut(character_number))
+            .ok_or_else(|| {
+                serde::de::Error::custom(format!(
+                    "In [custom_chars] {character_number} is not a character number in the range 0 to 7"
+                ))
+            })?;
+        *bitmap = parse_bitmap_rows(&rows_as_hex).ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "In [custom_chars] character {character_number} must be 8 hex bytes, eg \"10 10 10 10 10 10 10 1f\", not \"{rows_as_hex}\""
+            ))
+        })?;
+    }
+    Ok(Some(bitmaps))
+}
+
+/// Given 8 hex bytes, optionally separated by spaces, returns them as the rows of a bitmap; returns None if they are not valid
+fn parse_bitmap_rows(rows_as_hex: &str) -> Option<[u8; 8]> {
+    let hex_digits: Vec<char> = rows_as_hex
+        .chars()
+        .filter(|character| !character.is_whitespace())
+        .collect();
+    if hex_digits.len() != 16 {
+        return None;
+    }
+    let mut rows = [0; 8];
+    for (row, pair_of_hex_digits) in rows.iter_mut().zip(hex_digits.chunks(2)) {
+        *row = u8::from_str_radix(&pair_of_hex_digits.iter().collect::<String>(), 16).ok()?;
+    }
+    Some(rows)
+}
+
 use serde::{Deserialize, Serialize};
 #[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub struct StartTime {
@@ -33,22 +139,52 @@ pub struct Config {
     /// The folder that stores the stations
     pub stations_directory: String, // eg stations_directory = "/boot/playlists2"
 
-    /// The timeout when entering two digit station indices
+    /// ranges of channels whose channel files are in a folder other than stations_directory; the ranges must not overlap.
+    /// Channels that are not in any range are in stations_directory.
+    pub station_sources: Vec<StationSource>,
+
+    /// The timeout when entering multi-digit station indices
     #[serde(with = "humantime_serde")]
     // this allows us to enter the time for example as          input_timeout = "3s"
     pub input_timeout: Duration, // the duration of the keyboard timeout eg input_timeout = "3s"
 
+    /// The number of digits in a channel number, from 1 to MAXIMUM_CHANNEL_DIGITS; the channel files must start with that many digits.
+    /// eg channel_digits = 3 allows channels 000 to 999, & the channel files are named like "005 Radio 4.toml"
+    pub channel_digits: usize,
+
     /// The change in volume when the user increments or decrements the volume
     pub volume_offset: i32,
 
+    /// if specified, the volumes, in increasing order, that the user steps between when they increment or decrement the volume,
+    /// instead of changing it by volume_offset; this allows finer steps at low volumes, eg volume_steps = [0, 20, 35, 45, 52, 58, 63, 67, 70]
+    pub volume_steps: Option<Vec<i32>>,
+
     /// The inital volum ewhen the program starts
     pub initial_volume: i32,
 
+    /// the ALSA mixer control that is checked to see if the system has been muted, & unmuted when the user asks; eg mixer_control = "PCM"
+    pub mixer_control: String,
+
+    /// the time each channel entered while previewing channels is played for before the preview ends eg station_preview_time = "5s"
+    #[serde(with = "humantime_serde")]
+    pub station_preview_time: Duration,
+
+    /// the number of dB the volume is reduced by while previewing channels
+    pub station_preview_volume_reduction: i32,
+
+    /// if true, when a preview ends without the user keeping the channel, the channel that was playing before the preview is played again;
+    /// if false, the previewed channel carries on playing at the normal volume. eg station_preview_returns_to_previous = false
+    pub station_preview_returns_to_previous: bool,
+
     ///buffer-duration is a configuration property for the playbin element that defines the
     /// maximum amount of media data to buffer in time (measured in nanoseconds) when streaming content over a network
     #[serde(with = "humantime_serde")]
     pub buffer_duration: Option<Duration>,
 
+    /// if specified, the gstreamer description of the audio sink that playbin plays through, instead of the one it chooses itself,
+    /// eg audio_sink = "alsasink device=hw:1"; if the sink cannot be created, the error is shown rather than playing silently
+    pub audio_sink: Option<String>,
+
     /// if the user preses "previous track", within this time, program goes to start of the current track
     /// if longer, to the previous track
     #[serde(deserialize_with = "deserialize_clocktime")]
@@ -65,9 +201,153 @@ pub struct Config {
     /// Notification sounds
     pub aural_notifications: AuralNotifications,
 
+    /// if true, each notification sound is decoded briefly at startup & the user is warned if one cannot be played,
+    /// eg as it is a text file renamed to .mp3; this makes starting up slower. eg check_notification_files = true
+    pub check_notification_files: bool,
+
+    /// the error ding is not played again until this time has passed since it was last played, so that
+    /// repeatedly entering a channel that does not exist does not give a stream of dings; the error is still shown eg error_ding_cooldown = "5s"
+    #[serde(with = "humantime_serde")]
+    pub error_ding_cooldown: Duration,
+
     /// list of times when the program automatically starts to play a channel
     pub start_times: Vec<StartTime>,
 
+    /// if specified, the volume is ramped up over this number of milliseconds when a track starts to avoid loud pops
+    pub fade_in_ms: Option<u64>,
+
+    /// the number of times the program tries to connect to the Wi-Fi using the SSID & password in pass.toml
+    pub wifi_connect_attempts: u32,
+
+    /// the time after which an attempt to connect to the Wi-Fi is abandoned eg wifi_connect_timeout = "30s"
+    #[serde(with = "humantime_serde")]
+    pub wifi_connect_timeout: Duration,
+
+    /// if true, line 3 shows the title & line 4 shows the artist, each scrolling on its own;
+    /// if false, the title is wrapped over lines 3 & 4
+    pub split_title_artist: bool,
+
+    /// if specified, the path of a file to which the time, channel number, organisation & title are appended whenever they change
+    pub now_playing_log: Option<String>,
+
+    /// if specified, the channel played once the program has started up, so the user does not have to enter it
+    pub autoplay_channel: Option<usize>,
+
+    /// if specified, the hours during which the LCD backlight is turned off, eg backlight_off = "23:00-06:00"
+    /// the period can span midnight
+    pub backlight_off: Option<String>,
+
+    /// if specified, the IANA time zone of the clock on the screen, the spoken time, start_times & backlight_off,
+    /// eg timezone = "Europe/London";
+    /// otherwise the system's time zone is used, which is often not set on a headless Pi
+    #[serde(deserialize_with = "deserialize_timezone")]
+    pub timezone: Option<chrono_tz::Tz>,
+
+    /// if true, the clock on the screen uses the 24 hour clock, otherwise the 12 hour clock;
+    /// the user can swap between them while running. eg clock_24h = false
+    pub clock_24h: bool,
+
+    /// the number of seconds the seek forward & seek backward keys move within a CD or USB track
+    pub keyboard_seek_seconds: i64,
+
+    /// if specified, while the program is idle after starting up, the screen contents are shifted
+    /// one column to the right every burn_in_shift_minutes to prevent the LCD burning in
+    pub burn_in_shift_minutes: Option<u64>,
+
+    /// the channels played when the function keys are pressed; F1 plays the first entry, F2 the second & so on
+    /// eg favorites = [2, 15, 7]
+    pub favorites: Vec<usize>,
+
+    /// if specified, the GPIO pins of a rotary encoder, which changes the volume when turned & plays or pauses when pressed;
+    /// the keyboard still works as well; see RotaryEncoder for the format
+    pub rotary_encoder: Option<RotaryEncoder>,
+
+    /// the number of seconds each piece of information (temperature, Wi-Fi signal, IP address or date & time)
+    /// is shown on a free line before moving on to the next; if zero, which is the default, only the date & time are shown
+    /// eg info_rotation_seconds = 5
+    pub info_rotation_seconds: u64,
+
+    /// if specified, the passphrase used to decrypt pass.toml.enc, the age encrypted version of the Wi-Fi password file pass.toml
+    pub wifi_pass_passphrase: Option<String>,
+
+    /// how often, in ms, the LCD screen is refreshed; start_times only work if this is significantly shorter than 1 second
+    pub lcd_refresh_ms: u64,
+
+    /// how often, in ms, the position within the current track is read from gstreamer
+    pub position_poll_ms: u64,
+
+    /// if true, the name of the station is spoken, using espeak-ng, before the channel starts to play
+    pub announce_station: bool,
+
+    /// the time, in ms, ping waits for a reply before the ping is reported as NoReply
+    pub ping_timeout_ms: u64,
+
+    /// the number of packets sent each time we ping; if more than 1, the average round trip time is shown
+    pub ping_count: u32,
+
+    /// how the volume is shown on the screen, either as the value used internally, which is in dB, or as a percentage
+    /// eg volume_display = "percent"
+    pub volume_display: VolumeDisplay,
+
+    /// whether the time shown while a CD or USB track plays is the time since it started or the time left;
+    /// the user can swap between them while running. eg time_display = "remaining"
+    pub time_display: TimeDisplay,
+
+    /// what line 1 shows while starting up, before a channel has been selected; by default the IP address & the volume
+    /// eg startup_line1 = "hostname" or startup_line1 = { custom = "Welcome to rradio" }
+    pub startup_line1: StartupLine1,
+
+    /// how the buffer state of a stream is shown on line 4; compact marks the percentage with a line in one of the 20 cells,
+    /// while full_width also fills the cells before it, so it reads as a bar. eg buffer_bar_style = "full_width"
+    pub buffer_bar_style: BufferBarStyle,
+
+    /// if specified, the current track is restarted if the position has not advanced for this number of seconds while playing,
+    /// as sometimes a stream stalls without gstreamer reporting an error; if not specified, stalls are not looked for
+    pub stall_timeout_seconds: Option<u64>,
+
+    /// the number of times a stream is reconnected after gstreamer reports a network error, before the error is shown;
+    /// the screen shows "Reconnecting" while it tries. 0 shows the error straight away. eg max_stream_reconnect_attempts = 3
+    pub max_stream_reconnect_attempts: u32,
+
+    /// corrections to the organisation names sent by stations with broken metadata; the raw name must match exactly
+    /// eg [name_corrections]
+    ///    "LaPremiere" = "La Première"
+    #[serde(deserialize_with = "deserialize_name_corrections")]
+    pub name_corrections: std::collections::HashMap<String, String>,
+
+    /// the extensions of the files that are played from USB devices (local or Samba); the case of the extension does not matter
+    /// eg audio_extensions = ["mp3", "flac", "opus"]
+    #[serde(deserialize_with = "deserialize_audio_extensions")]
+    pub audio_extensions: Vec<String>,
+
+    /// if specified, the folder that relative paths in a channel file's station_url are relative to,
+    /// eg with local_media_base = "/home/pi/media", station_url = ["sounds/jingle.mp3"] plays "file:///home/pi/media/sounds/jingle.mp3"
+    pub local_media_base: Option<String>,
+
+    /// if true, a USB device (local or Samba) stays mounted when the user switches to another channel & is only unmounted on shutdown,
+    /// which saves remounting it when switching back; eg keep_usb_mounted = true
+    pub keep_usb_mounted: bool,
+
+    /// if true, when the album chosen at random from a memory stick or a playlist cannot be found, another is chosen,
+    /// until there are none left; if false, the missing album is reported straight away. eg retry_missing_albums = true
+    pub retry_missing_albums: bool,
+
+    /// if false, the equalizers specified in the channel files are ignored, which saves CPU on low-end Pis
+    /// such as the Pi Zero; eg equalizer_enabled = false
+    pub equalizer_enabled: bool,
+
+    /// if specified, replaces the built in bitmaps of the 8 user defined characters, which are used for the cursors & é, è & à;
+    /// each character is 8 rows of 5 pixels, given as hex bytes starting with the top row, eg
+    /// [custom_chars]
+    ///    0 = "10 10 10 10 10 10 10 1f"
+    #[serde(deserialize_with = "deserialize_custom_chars")]
+    pub custom_chars: Option<[[u8; 8]; 8]>,
+
+    /// if specified, the user defined character (0 to 7) that is redefined to show the Wi-Fi signal as 0 to 4 bars in a single cell,
+    /// instead of its strength in dB, which saves space on line 4; pick one that is not otherwise needed, as characters 0 to 4
+    /// are used by the buffer bar & 5 to 7 are é, è & à, eg wifi_bars_character = 7
+    pub wifi_bars_character: Option<usize>,
+
     ///details on the local memory stick
     //pub usb: Option<UsbConfig>, //details on the local memory stick
 
@@ -92,14 +372,25 @@ fn long_advance_time_default() -> i32 {
     60
 }
 
-#[derive(Debug, Default, PartialEq, Clone, serde::Deserialize)]
-/// Authneticaton data for a Samba share is stored here
+#[derive(Default, PartialEq, Clone, serde::Deserialize, serde::Serialize)]
+/// Authneticaton data for a Samba share or a stream that needs HTTP basic authentication is stored here
 pub struct AuthenticationData {
     pub username: String,
+    #[serde(skip_serializing)] // so the password does not end up in status dumps
     pub password: String,
 }
 
-#[derive(Debug, PartialEq, Clone, serde::Deserialize)]
+impl std::fmt::Debug for AuthenticationData {
+    /// Hides the password, so that it is not printed when the status or the channel data is output for debugging
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthenticationData")
+            .field("username", &self.username)
+            .field("password", &"********")
+            .finish()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, serde::Deserialize, serde::Serialize)]
 /// needs to start with the following so TOML expects the media details.
 pub struct MediaDetails {
     //details of a local memory stick or a Samba device
@@ -120,6 +411,9 @@ pub struct MediaDetails {
 
     #[serde(default = "empty_string")]
     pub mount_folder: String,
+    /// If specified, the maximum number of tracks played from the device, so a session is bounded;  eg max_tracks = 20
+    /// Zero means there is no limit.
+    pub max_tracks: Option<usize>,
     /// specifies if the device is mounted
     #[serde(skip, default = "is_mounted_default")]
     // skip means that even if the users specify it as true,
@@ -134,6 +428,54 @@ fn is_mounted_default() -> bool {
 fn empty_string() -> String {
     String::new()
 }
+#[derive(Debug, Default, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// How the volume is shown on the screen; only the display changes, the volume itself is always handled in dB
+pub enum VolumeDisplay {
+    /// the volume as used internally, in dB offset so that VOLUME_ZERO_DB is 0 dB
+    #[default]
+    Db,
+    /// the range VOLUME_MIN to VOLUME_MAX shown as 0 to 100
+    Percent,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+/// How the time is shown on the screen while a CD or USB track, whose duration is known, is playing
+pub enum TimeDisplay {
+    /// the time since the track started
+    #[default]
+    Elapsed,
+    /// the time until the track ends
+    Remaining,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// What line 1 shows while starting up, before a channel has been selected
+pub enum StartupLine1 {
+    /// the IP address & the volume, or the state of the Wi-Fi connection if it is not connected
+    #[default]
+    IpAndVolume,
+    /// the name of the Pi, as given in /etc/hostname
+    Hostname,
+    /// the time rradio was compiled
+    CompileTime,
+    /// the text specified, eg a welcome message
+    Custom(String),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// How the buffer state of a stream is shown on line 4, each of the 20 cells representing 5%
+pub enum BufferBarStyle {
+    /// a single cell, with a vertical line whose position within the cell shows the remainder
+    #[default]
+    Compact,
+    /// every cell up to the percentage is filled, & the last cell has a vertical line whose position shows the remainder
+    FullWidth,
+}
+
 #[derive(Debug, Default, serde::Deserialize)]
 #[serde(default)]
 /// the paramaters used by the scroll function
@@ -143,6 +485,43 @@ pub struct Scroll {
     pub scroll_period_ms: u64,
 }
 
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+/// A range of channels whose channel files are in their own folder, eg
+/// [[station_sources]]
+///    first_channel = 30
+///    last_channel = 59
+///    directory = "/boot/music_stations"
+pub struct StationSource {
+    pub first_channel: usize,
+    /// the last channel in the range, which is included in it
+    pub last_channel: usize,
+    /// the folder containing the channel files of the channels in the range
+    pub directory: String,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+/// The BCM GPIO pin numbers of a rotary encoder, whose pins are connected to ground when active, eg
+/// [rotary_encoder]
+///    pin_a = 17
+///    pin_b = 27
+///    push_button_pin = 23
+/// The internal pull-up resistors are used, so no external resistors are needed.
+/// Pin 22 must not be used if there is a DigiAMP+ amplifier, as it controls the mute.
+pub struct RotaryEncoder {
+    pub pin_a: u8,
+    pub pin_b: u8,
+    /// the pin of the push button, if the encoder has one
+    pub push_button_pin: Option<u8>,
+    /// the number of changes of the pins for each click as the encoder is turned; most encoders have 4, but some have 2
+    #[serde(default = "transitions_per_click_default")]
+    pub transitions_per_click: u8,
+}
+
+/// the default value for transitions_per_click
+fn transitions_per_click_default() -> u8 {
+    4
+}
+
 #[derive(Debug, Default, serde::Deserialize)] // the parameters that specify how the scroll reacts
 #[serde(default)]
 /// Notifications allows rradio to play sounds to notify the user of events
@@ -163,10 +542,18 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             stations_directory: "/home/pi/playlists".to_string(),
+            station_sources: Vec::new(),
             input_timeout: Duration::from_secs(3),
+            channel_digits: 2,
+            volume_steps: None,
             volume_offset: 5,   // step the volum in 5 dB intervals
             initial_volume: 70, // initial volume is 70 dB
+            mixer_control: "Master".to_string(),
+            station_preview_time: Duration::from_secs(5),
+            station_preview_volume_reduction: 15,
+            station_preview_returns_to_previous: true,
             buffer_duration: None,
+            audio_sink: None,
             goto_previous_track_time_delta: ClockTime::from_mseconds(2000),
             time_initial_message_displayed_after_channel_change: ClockTime::from_mseconds(3000),
             scroll: Scroll {
@@ -175,10 +562,46 @@ impl Default for Config {
                 scroll_period_ms: 1600, //  the time between scrolls in milli-seconds
             },
             aural_notifications: AuralNotifications::default(),
+            check_notification_files: false,
+            error_ding_cooldown: Duration::from_secs(5),
             max_number_of_remote_pings: 15,
             short_advance_time: 10,
             long_advance_time: 60,
             start_times: vec![],
+            fade_in_ms: None,
+            wifi_connect_attempts: 3,
+            wifi_connect_timeout: Duration::from_secs(30),
+            split_title_artist: false,
+            now_playing_log: None,
+            autoplay_channel: None,
+            backlight_off: None,
+            timezone: None,
+            clock_24h: true,
+            keyboard_seek_seconds: 30,
+            burn_in_shift_minutes: None,
+            favorites: vec![],
+            rotary_encoder: None,
+            info_rotation_seconds: 0,
+            wifi_pass_passphrase: None,
+            lcd_refresh_ms: 300,
+            position_poll_ms: 300,
+            announce_station: false,
+            ping_timeout_ms: 3000,
+            ping_count: 1,
+            volume_display: VolumeDisplay::Db,
+            time_display: TimeDisplay::Elapsed,
+            startup_line1: StartupLine1::IpAndVolume,
+            buffer_bar_style: BufferBarStyle::Compact,
+            stall_timeout_seconds: None,
+            max_stream_reconnect_attempts: 3,
+            name_corrections: built_in_name_corrections(),
+            audio_extensions: default_audio_extensions(),
+            local_media_base: None,
+            keep_usb_mounted: false,
+            retry_missing_albums: false,
+            equalizer_enabled: true,
+            custom_chars: None,
+            wifi_bars_character: None,
         }
     }
 }
@@ -243,13 +666,512 @@ impl Config {
                     ));
                 }
 
-                if start_time.channel < NUMBER_OF_POSSIBLE_CHANNELS {
+                if start_time.channel < return_value.number_of_channels() {
                 } else {
                     return Err(format!("Start channel {} is invalid", start_time.channel));
                 }
             }
+
+            if let Some(backlight_off) = &return_value.backlight_off {
+                parse_backlight_off_hours(backlight_off)?;
+            }
+
+            return_value.validate()?;
         }
 
         return_value_as_result
     }
+
+    /// Checks that the values that cannot be checked by the TOML parser are sensible.
+    /// Returns a descriptive error string if they are not.
+    pub fn validate(&self) -> Result<(), String> {
+        // the HD44780 only has 5 pixels per row
+        const MAXIMUM_ROW_BITMAP: u8 = 0b11111;
+        for (character_number, bitmap) in self.custom_chars.iter().flatten().enumerate() {
+            if let Some(row) = bitmap.iter().position(|&row| row > MAXIMUM_ROW_BITMAP) {
+                return Err(format!(
+                    "In [custom_chars] row {row} of character {character_number} is {:#04x}, but the screen's characters are only 5 pixels wide, so it must not be bigger than 0x1f",
+                    bitmap[row]
+                ));
+            }
+        }
+        if let Some(wifi_bars_character) = self.wifi_bars_character
+            && wifi_bars_character >= crate::lcd::character_pattern::BITMAPS.len()
+        {
+            return Err(format!(
+                "wifi_bars_character is {wifi_bars_character}, but it must be in the range 0 to {}",
+                crate::lcd::character_pattern::BITMAPS.len() - 1
+            ));
+        }
+        if let Some(rotary_encoder) = &self.rotary_encoder {
+            let pins: Vec<u8> = [
+                Some(rotary_encoder.pin_a),
+                Some(rotary_encoder.pin_b),
+                rotary_encoder.push_button_pin,
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            if pins.contains(&crate::lcd::get_mute_state::MUTE_PORT) {
+                return Err(format!(
+                    "In [rotary_encoder] GPIO pin {} cannot be used, as it mutes the DigiAMP+ amplifier",
+                    crate::lcd::get_mute_state::MUTE_PORT
+                ));
+            }
+            if (1..pins.len()).any(|index| pins[..index].contains(&pins[index])) {
+                return Err(format!(
+                    "In [rotary_encoder] pin_a, pin_b & push_button_pin must be different pins, but they are {pins:?}"
+                ));
+            }
+        }
+        if let Some(station_source) = self
+            .station_sources
+            .iter()
+            .find(|station_source| station_source.first_channel > station_source.last_channel)
+        {
+            return Err(format!(
+                "In [[station_sources]] for {} first_channel ({}) must not be bigger than last_channel ({})",
+                station_source.directory, station_source.first_channel, station_source.last_channel
+            ));
+        }
+        let mut station_sources_in_order: Vec<&StationSource> =
+            self.station_sources.iter().collect();
+        station_sources_in_order.sort_by_key(|station_source| station_source.first_channel);
+        if let Some((lower, higher)) = station_sources_in_order
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .find(|(lower, higher)| higher.first_channel <= lower.last_channel)
+        {
+            return Err(format!(
+                "In [[station_sources]] channels {} to {} in {} overlap channels {} to {} in {}",
+                lower.first_channel,
+                lower.last_channel,
+                lower.directory,
+                higher.first_channel,
+                higher.last_channel,
+                higher.directory
+            ));
+        }
+        if !(1..=MAXIMUM_CHANNEL_DIGITS).contains(&self.channel_digits) {
+            return Err(format!(
+                "channel_digits is {}, but it must be in the range 1 to {MAXIMUM_CHANNEL_DIGITS}",
+                self.channel_digits
+            ));
+        }
+        if let Some(autoplay_channel) = self.autoplay_channel
+            && autoplay_channel >= self.number_of_channels()
+        {
+            return Err(format!(
+                "autoplay_channel is {autoplay_channel}, but it must be in the range 0 to {}",
+                self.number_of_channels() - 1
+            ));
+        }
+        if let Some(volume_steps) = &self.volume_steps {
+            if volume_steps.is_empty() {
+                return Err("volume_steps must contain at least one volume".to_string());
+            }
+            if !volume_steps.is_sorted_by(|lower, higher| lower < higher) {
+                return Err(format!(
+                    "volume_steps {volume_steps:?} must be in increasing order, without repeats"
+                ));
+            }
+            if let Some(volume) = volume_steps.iter().find(|volume| {
+                !(crate::gstreamer_interfaces::VOLUME_MIN..=crate::gstreamer_interfaces::VOLUME_MAX)
+                    .contains(volume)
+            }) {
+                return Err(format!(
+                    "volume_steps contains {volume}, but volumes must be in the range {} to {}",
+                    crate::gstreamer_interfaces::VOLUME_MIN,
+                    crate::gstreamer_interfaces::VOLUME_MAX
+                ));
+            }
+        }
+        if self.scroll.min_scroll > self.scroll.max_scroll {
+            // update_scroll would never find a space to scroll to & would always scroll by min_scroll
+            return Err(format!(
+                "In [scroll] min_scroll ({}) must not be bigger than max_scroll ({})",
+                self.scroll.min_scroll, self.scroll.max_scroll
+            ));
+        }
+        if self.ping_timeout_ms == 0 {
+            return Err(
+                "ping_timeout_ms must be more than 0, or every ping would time out & report NoReply"
+                    .to_string(),
+            );
+        }
+        if self.scroll.scroll_period_ms == 0 {
+            return Err(
+                "In [scroll] scroll_period_ms must be more than 0, or the text would scroll every time the screen is refreshed"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the volume after the user has incremented (direction 1) or decremented (direction -1) the current volume.
+    /// If volume_steps is specified, moves to the adjacent step, leaving the volume unchanged if there is no step beyond it
+    /// in that direction, eg if it was set above the last step from the web page; otherwise changes it by volume_offset. Either way, the volume is kept in bounds.
+    pub fn next_volume(&self, current_volume: i32, direction: i32) -> i32 {
+        let next_volume = match (&self.volume_steps, direction.signum()) {
+            (Some(volume_steps), 1) => volume_steps
+                .iter()
+                .find(|&&volume| volume > current_volume)
+                .copied()
+                .unwrap_or(current_volume),
+            (Some(volume_steps), -1) => volume_steps
+                .iter()
+                .rev()
+                .find(|&&volume| volume < current_volume)
+                .copied()
+                .unwrap_or(current_volume),
+            _ => current_volume + self.volume_offset * direction,
+        };
+        next_volume.clamp(
+            crate::gstreamer_interfaces::VOLUME_MIN,
+            crate::gstreamer_interfaces::VOLUME_MAX,
+        )
+    }
+
+    /// Returns the number of channels the user can select, given config.channel_digits, eg 100 for 2 digits.
+    /// It is never more than NUMBER_OF_POSSIBLE_CHANNELS, even if channel_digits has not yet been validated.
+    pub fn number_of_channels(&self) -> usize {
+        10_usize.pow(self.channel_digits.min(MAXIMUM_CHANNEL_DIGITS) as u32)
+    }
+
+    /// Returns the folder containing the channel file of the given channel, which is the directory of the station source
+    /// whose range includes the channel if there is one, otherwise stations_directory
+    pub fn stations_directory_for_channel(&self, channel_number: usize) -> &str {
+        self.station_sources
+            .iter()
+            .find(|station_source| {
+                (station_source.first_channel..=station_source.last_channel)
+                    .contains(&channel_number)
+            })
+            .map_or(&self.stations_directory, |station_source| {
+                &station_source.directory
+            })
+    }
+
+    /// Returns the folders that contain channel files, ie stations_directory followed by the directory of each of station_sources,
+    /// without repeats
+    pub fn all_stations_directories(&self) -> Vec<&str> {
+        let mut directories = vec![self.stations_directory.as_str()];
+        for station_source in &self.station_sources {
+            if !directories.contains(&station_source.directory.as_str()) {
+                directories.push(&station_source.directory);
+            }
+        }
+        directories
+    }
+
+    /// Returns true if the channel's file is looked for in the directory, ie the channel is in the range of the station source
+    /// whose directory it is, or, if it is stations_directory, the channel is not in the range of any station source
+    pub fn channel_is_in_directory(&self, channel_number: &str, directory: &str) -> bool {
+        channel_number.parse::<usize>().is_ok_and(|channel_number| {
+            self.stations_directory_for_channel(channel_number) == directory
+        })
+    }
+
+    /// Returns the name to display for the organisation sent by a station, which is unchanged unless name_corrections corrects it
+    pub fn correct_name<'a>(&'a self, raw_name: &'a str) -> &'a str {
+        self.name_corrections
+            .get(raw_name)
+            .map_or(raw_name, String::as_str)
+    }
+
+    /// Returns the time now in config.timezone if it is specified, or else in the system's time zone
+    pub fn clock_now(&self) -> chrono::DateTime<chrono::FixedOffset> {
+        match self.timezone {
+            Some(timezone) => chrono::Utc::now().with_timezone(&timezone).fixed_offset(),
+            None => chrono::Local::now().fixed_offset(),
+        }
+    }
+
+    /// Returns true if config.backlight_off is specified & the given local time is inside that period
+    pub fn backlight_should_be_off(&self, now: chrono::NaiveTime) -> bool {
+        match self.backlight_off.as_deref().map(parse_backlight_off_hours) {
+            Some(Ok((off_time, on_time))) => {
+                if off_time <= on_time {
+                    off_time <= now && now < on_time
+                } else {
+                    // the period spans midnight, eg 23:00-06:00
+                    now >= off_time || now < on_time
+                }
+            }
+            _ => false, // either not specified, or invalid, which from_file has already reported
+        }
+    }
+}
+
+/// Parses a period such as "23:00-06:00" into the time the backlight goes off & the time it comes back on
+fn parse_backlight_off_hours(
+    backlight_off: &str,
+) -> Result<(chrono::NaiveTime, chrono::NaiveTime), String> {
+    let parse_time = |time: &str| {
+        chrono::NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|error| {
+            format!(
+                "When parsing backlight_off {} got error {}; expected a format such as \"23:00-06:00\"",
+                backlight_off, error
+            )
+        })
+    };
+    match backlight_off.split_once('-') {
+        Some((off_time, on_time)) => Ok((parse_time(off_time)?, parse_time(on_time)?)),
+        None => Err(format!(
+            "backlight_off {} should be two times separated by \"-\" eg \"23:00-06:00\"",
+            backlight_off
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_is_valid() {
+        assert_eq!(Config::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn station_sources_choose_the_directory_and_must_not_overlap() {
+        let mut config: Config = toml::from_str(
+            r#"
+            stations_directory = "/boot/playlists"
+            [[station_sources]]
+            first_channel = 30
+            last_channel = 59
+            directory = "/boot/music"
+            [[station_sources]]
+            first_channel = 0
+            last_channel = 9
+            directory = "/boot/news"
+            "#,
+        )
+        .expect("valid TOML");
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.stations_directory_for_channel(0), "/boot/news");
+        assert_eq!(config.stations_directory_for_channel(10), "/boot/playlists");
+        assert_eq!(config.stations_directory_for_channel(59), "/boot/music");
+        assert_eq!(config.stations_directory_for_channel(60), "/boot/playlists");
+
+        config.station_sources[1].last_channel = 30;
+        let error = config
+            .validate()
+            .expect_err("overlapping ranges should be rejected");
+        assert!(error.contains("overlap"), "{error}");
+    }
+
+    #[test]
+    fn min_scroll_bigger_than_max_scroll_is_rejected() {
+        let mut config = Config::default();
+        config.scroll.min_scroll = 20;
+        config.scroll.max_scroll = 6;
+        let error = config
+            .validate()
+            .expect_err("min_scroll > max_scroll should be rejected");
+        assert!(error.contains("min_scroll (20)"), "{error}");
+    }
+
+    #[test]
+    fn equal_min_and_max_scroll_are_accepted() {
+        let mut config = Config::default();
+        config.scroll.min_scroll = 8;
+        config.scroll.max_scroll = 8;
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn volume_steps_move_to_the_adjacent_step_and_stop_at_the_ends() {
+        let config: Config =
+            toml::from_str("volume_steps = [0, 20, 35, 45, 52]").expect("valid TOML");
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.next_volume(20, 1), 35);
+        assert_eq!(config.next_volume(20, -1), 0);
+        assert_eq!(config.next_volume(40, 1), 45); // a volume between steps moves to the next step
+        assert_eq!(config.next_volume(40, -1), 35);
+        assert_eq!(config.next_volume(52, 1), 52);
+        assert_eq!(config.next_volume(0, -1), 0);
+        assert_eq!(config.next_volume(40, 0), 40);
+    }
+
+    #[test]
+    fn volumes_beyond_the_volume_steps_are_unchanged() {
+        let config: Config = toml::from_str("volume_steps = [10, 20, 30]").expect("valid TOML");
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.next_volume(40, 1), 40); // rather than dropping to the last step
+        assert_eq!(config.next_volume(40, -1), 30);
+        assert_eq!(config.next_volume(5, -1), 5); // rather than rising to the first step
+        assert_eq!(config.next_volume(5, 1), 10);
+    }
+
+    #[test]
+    fn without_volume_steps_the_volume_changes_by_volume_offset() {
+        let config = Config::default();
+        assert_eq!(config.next_volume(50, 1), 50 + config.volume_offset);
+        assert_eq!(config.next_volume(50, -1), 50 - config.volume_offset);
+        assert_eq!(
+            config.next_volume(crate::gstreamer_interfaces::VOLUME_MIN, -1),
+            crate::gstreamer_interfaces::VOLUME_MIN
+        );
+    }
+
+    #[test]
+    fn rotary_encoder_pins_must_differ_and_not_be_the_mute_pin() {
+        let config: Config =
+            toml::from_str("[rotary_encoder]\npin_a = 17\npin_b = 27\npush_button_pin = 23")
+                .expect("valid TOML");
+        assert_eq!(config.validate(), Ok(()));
+        let config: Config =
+            toml::from_str("[rotary_encoder]\npin_a = 17\npin_b = 17").expect("valid TOML");
+        assert!(config.validate().is_err());
+        let config: Config =
+            toml::from_str("[rotary_encoder]\npin_a = 17\npin_b = 22").expect("valid TOML");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn volume_steps_must_be_increasing() {
+        let config: Config = toml::from_str("volume_steps = [0, 30, 20]").expect("valid TOML");
+        assert!(config.validate().is_err());
+        let config: Config = toml::from_str("volume_steps = []").expect("valid TOML");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn passwords_are_not_output_for_debugging() {
+        let authentication_data = AuthenticationData {
+            username: "listener".to_string(),
+            password: "secret".to_string(),
+        };
+        let debug_output = format!("{authentication_data:?}");
+        assert!(debug_output.contains("listener"), "{debug_output}");
+        assert!(!debug_output.contains("secret"), "{debug_output}");
+    }
+
+    #[test]
+    fn clock_is_shown_in_the_configured_timezone() {
+        let config: Config = toml::from_str(r#"timezone = "Asia/Kolkata""#).expect("valid TOML");
+        assert_eq!(config.validate(), Ok(()));
+        // India has no daylight saving, so is always 5½ hours ahead of UTC
+        assert_eq!(
+            config.clock_now().offset().local_minus_utc(),
+            (5 * 60 + 30) * 60
+        );
+
+        // an unknown time zone is rejected when the config file is read
+        assert!(toml::from_str::<Config>(r#"timezone = "Europe/Nowhere""#).is_err());
+    }
+
+    #[test]
+    fn user_name_corrections_are_added_to_the_built_in_ones() {
+        let config: Config = toml::from_str(
+            r#"
+            [name_corrections]
+            "BBC R4" = "BBC Radio 4"
+            "#,
+        )
+        .expect("valid TOML");
+        assert_eq!(config.correct_name("BBC R4"), "BBC Radio 4");
+        assert_eq!(config.correct_name("LaPremiere"), "La Première");
+        assert_eq!(config.correct_name("Radio Tradcan"), "Radio Tradcan");
+    }
+
+    #[test]
+    fn audio_extensions_are_converted_to_lower_case() {
+        let config: Config =
+            toml::from_str(r#"audio_extensions = ["MP3", "Opus"]"#).expect("valid TOML");
+        assert_eq!(config.audio_extensions, vec!["mp3", "opus"]);
+    }
+
+    #[test]
+    fn custom_chars_replace_only_the_characters_specified() {
+        let config: Config = toml::from_str(
+            r#"
+            [custom_chars]
+            1 = "00 04 06 1f 06 04 00 00"
+            "#,
+        )
+        .expect("valid TOML");
+        let bitmaps = config.custom_chars.expect("custom_chars were specified");
+        assert_eq!(bitmaps[1], [0x00, 0x04, 0x06, 0x1f, 0x06, 0x04, 0x00, 0x00]);
+        assert_eq!(bitmaps[0], crate::lcd::character_pattern::BITMAPS[0]);
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn custom_chars_wider_than_5_pixels_are_rejected() {
+        let config: Config = toml::from_str(
+            r#"
+            [custom_chars]
+            7 = "0000000000003f00"
+            "#,
+        )
+        .expect("valid TOML");
+        let error = config
+            .validate()
+            .expect_err("a row wider than 5 pixels should be rejected");
+        assert!(error.contains("row 6 of character 7"), "{error}");
+    }
+
+    #[test]
+    fn zero_scroll_period_is_rejected() {
+        let mut config = Config::default();
+        config.scroll.scroll_period_ms = 0;
+        let error = config
+            .validate()
+            .expect_err("a zero scroll_period_ms should be rejected");
+        assert!(error.contains("scroll_period_ms"), "{error}");
+    }
+
+    #[test]
+    fn zero_ping_timeout_is_rejected() {
+        let mut config = Config::default();
+        config.ping_timeout_ms = 0;
+        let error = config
+            .validate()
+            .expect_err("a zero ping_timeout_ms should be rejected");
+        assert!(error.contains("ping_timeout_ms"), "{error}");
+    }
+
+    #[test]
+    fn startup_line1_can_be_a_keyword_or_custom_text() {
+        let config: Config =
+            toml::from_str(r#"startup_line1 = "compile_time""#).expect("valid TOML");
+        assert_eq!(config.startup_line1, StartupLine1::CompileTime);
+        let config: Config =
+            toml::from_str(r#"startup_line1 = { custom = "Welcome" }"#).expect("valid TOML");
+        assert_eq!(
+            config.startup_line1,
+            StartupLine1::Custom("Welcome".to_string())
+        );
+    }
+
+    #[test]
+    fn channel_digits_must_fit_in_the_possible_channels() {
+        let mut config = Config::default();
+        config.channel_digits = 3;
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(
+            config.number_of_channels(),
+            crate::player_status::NUMBER_OF_POSSIBLE_CHANNELS
+        );
+        config.channel_digits = 4;
+        let error = config
+            .validate()
+            .expect_err("4 channel digits should be rejected");
+        assert!(error.contains("channel_digits"), "{error}");
+    }
+
+    #[test]
+    fn autoplay_channel_must_be_a_channel_the_user_can_select() {
+        let mut config = Config::default();
+        config.autoplay_channel = Some(config.number_of_channels() - 1);
+        assert_eq!(config.validate(), Ok(()));
+        config.autoplay_channel = Some(config.number_of_channels());
+        let error = config
+            .validate()
+            .expect_err("an autoplay_channel beyond the last channel should be rejected");
+        assert!(error.contains("autoplay_channel"), "{error}");
+    }
 }
@@ -0,0 +1,164 @@
+// runs a check of each of the subsystems that rradio relies on & summarises which passed,
+// so that a unit can be diagnosed by someone who can only press a key & read the screen
+use crate::cd_drive;
+use crate::command_runner::CommandRunner;
+use crate::get_channel_details::SourceType;
+use crate::get_local_ip_address;
+use crate::lcd::{self, LineNum, TextBuffer};
+use crate::ping;
+use crate::player_status::PlayerStatus;
+
+/// The CD drive that the self test looks for
+const CD_DEVICE: &str = "/dev/sr0";
+
+/// The time allowed for the ping to the gateway on top of config.ping_timeout_ms, so ping can time out before it is killed
+const PING_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// The outcome of one check; Ok with what was found (which may be empty) if it passed, or Err with the reason it failed
+type CheckResult = Result<String, String>;
+
+/// Checks, one after the other, that the LCD screen can be written to, the network is up, the gateway answers a ping,
+/// there is a CD drive, the mount folders of the USB channels played so far exist, the temperature can be read
+/// & a gstreamer element can be created.
+/// Returns a summary, giving the number of checks that passed followed by the result of each check.
+pub async fn run_self_test(
+    status_of_rradio: &PlayerStatus,
+    config: &crate::read_config::Config,
+    lcd: &mut lcd::Lc,
+    command_runner: &impl CommandRunner,
+) -> String {
+    let lcd_result = check_lcd(lcd);
+    let network_result = get_local_ip_address::try_once_to_get_wifi_network_data(command_runner);
+    let ping_result = match &network_result {
+        Ok(network_data) => {
+            check_ping(&network_data.gateway_ip_address, config, command_runner).await
+        }
+        Err(_error) => Err("no gateway".to_string()),
+    };
+    let checks = [
+        ("LCD", lcd_result),
+        (
+            "IP",
+            network_result.map(|network_data| network_data.local_ip_address),
+        ),
+        ("Ping", ping_result),
+        ("CD", check_cd_drive()),
+        ("USB", check_usb_mount_folders(status_of_rradio)),
+        ("Temp", check_temperature()),
+        ("GStreamer", check_gstreamer()),
+    ];
+    summarise(&checks)
+}
+
+/// Returns the number of checks that passed, followed by the result of each check, eg "Self test 6/7 passed: LCD ok; CD FAIL no drive; ..."
+fn summarise(checks: &[(&str, CheckResult)]) -> String {
+    let number_passed = checks
+        .iter()
+        .filter(|(_name, result)| result.is_ok())
+        .count();
+    let results = checks
+        .iter()
+        .map(|(name, result)| match result {
+            Ok(found) if found.is_empty() => format!("{name} ok"),
+            Ok(found) => format!("{name} ok {found}"),
+            Err(reason) => format!("{name} FAIL {reason}"),
+        })
+        .collect::<Vec<String>>()
+        .join("; ");
+    format!(
+        "Self test {number_passed}/{} passed: {results}",
+        checks.len()
+    )
+}
+
+/// Writes a message to the screen, which both tells the user that the test is running & checks that the screen can be written to
+fn check_lcd(lcd: &mut lcd::Lc) -> CheckResult {
+    let mut text_buffer = TextBuffer::new();
+    text_buffer.write_text_to_single_line("Running self test".bytes(), LineNum::Line1);
+    lcd.write_text_buffer_to_lcd(&text_buffer)
+        .map(|()| String::new())
+        .map_err(|lcd_error| lcd_error.to_string())
+}
+
+/// Pings the gateway once, without blocking the runtime, & returns the round trip time
+async fn check_ping(
+    gateway_ip_address: &str,
+    config: &crate::read_config::Config,
+    command_runner: &impl CommandRunner,
+) -> CheckResult {
+    let output = command_runner
+        .output_with_timeout(
+            "/bin/ping",
+            &[
+                gateway_ip_address,
+                "-c", // send this number of pings and then stop
+                "1",
+                "-W", // wait this number of seconds before timing out
+                format!("{:.3}", config.ping_timeout_ms as f64 / 1000.0).as_str(),
+            ],
+            std::time::Duration::from_millis(config.ping_timeout_ms) + PING_GRACE_PERIOD,
+        )
+        .await?;
+    ping::parse_average_ping_time(&String::from_utf8_lossy(&output.stdout))
+        .map(|time_in_ms| format!("{time_in_ms:.1}ms"))
+        .ok_or_else(|| "no reply".to_string())
+}
+
+/// Checks that the CD drive exists & can report its status; it does not matter if there is no disc in it
+fn check_cd_drive() -> CheckResult {
+    let device = std::fs::File::open(CD_DEVICE).map_err(|_error| "no drive".to_string())?;
+    cd_drive::drive_status(&device)
+        .map(|drive_status| format!("{drive_status:?}"))
+        .map_err(|raw_status| format!("status {raw_status}"))
+}
+
+/// Checks that the mount folder of every USB channel played so far exists; passes if no USB channel has been played
+fn check_usb_mount_folders(status_of_rradio: &PlayerStatus) -> CheckResult {
+    let missing_mount_folders = status_of_rradio
+        .position_and_duration
+        .iter()
+        .filter(|channel| channel.channel_data.source_type == SourceType::Usb)
+        .filter_map(|channel| channel.channel_data.media_details.as_ref())
+        .map(|media_details| media_details.mount_folder.as_str())
+        .filter(|mount_folder| !std::path::Path::new(mount_folder).is_dir())
+        .collect::<Vec<&str>>();
+    if missing_mount_folders.is_empty() {
+        Ok(String::new())
+    } else {
+        Err(format!("no {}", missing_mount_folders.join(" ")))
+    }
+}
+
+/// Checks that the CPU temperature can be read; get_cpu_temperature returns a negative number if it cannot
+fn check_temperature() -> CheckResult {
+    match lcd::get_temperature::get_cpu_temperature() {
+        temperature if temperature >= 0 => Ok(format!("{temperature}C")),
+        error_number => Err(format!("error {error_number}")),
+    }
+}
+
+/// Checks that gstreamer can still create the element that rradio plays through
+fn check_gstreamer() -> CheckResult {
+    gstreamer::ElementFactory::make("playbin")
+        .build()
+        .map(|_playbin| String::new())
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_counts_the_checks_that_passed() {
+        let checks = [
+            ("LCD", Ok(String::new())),
+            ("IP", Ok("192.168.1.20".to_string())),
+            ("CD", Err("no drive".to_string())),
+        ];
+        assert_eq!(
+            summarise(&checks),
+            "Self test 2/3 passed: LCD ok; IP ok 192.168.1.20; CD FAIL no drive"
+        );
+    }
+}
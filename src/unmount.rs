@@ -27,6 +27,48 @@ pub fn unmount_if_needed(
     Ok(())
 }
 
+/// Used when config.keep_usb_mounted is true, as then other channels may still have their devices mounted.
+/// If another channel has the same device mounted on the mount folder of channel_number, the mount is handed over to channel_number,
+/// so it is not mounted again; if another channel has a different device mounted there, it is unmounted to free the folder.
+pub fn take_over_mount_folder(status_of_rradio: &mut PlayerStatus, channel_number: usize) {
+    let Some(wanted_media_details) = status_of_rradio.position_and_duration[channel_number]
+        .channel_data
+        .media_details
+        .clone()
+    else {
+        return;
+    };
+    let mut mount_handed_over = false;
+    for (other_channel_number, other_channel) in status_of_rradio
+        .position_and_duration
+        .iter_mut()
+        .enumerate()
+    {
+        let Some(other_media_details) = &mut other_channel.channel_data.media_details else {
+            continue;
+        };
+        if other_channel_number == channel_number
+            || !other_media_details.is_mounted
+            || other_media_details.mount_folder != wanted_media_details.mount_folder
+        {
+            continue;
+        }
+        if other_media_details.device == wanted_media_details.device {
+            other_media_details.is_mounted = false; // it is now recorded as mounted by channel_number
+            mount_handed_over = true;
+        } else {
+            let _ = unmount_if_needed(&mut other_channel.channel_data);
+        }
+    }
+    if mount_handed_over
+        && let Some(media_details) = &mut status_of_rradio.position_and_duration[channel_number]
+            .channel_data
+            .media_details
+    {
+        media_details.is_mounted = true;
+    }
+}
+
 pub fn unmount_all(status_of_rradio: &mut PlayerStatus) {
     for one_channel in &mut status_of_rradio.position_and_duration {
         let _ = unmount_if_needed(&mut one_channel.channel_data);